@@ -0,0 +1,103 @@
+//! List the host functions a wasm proc macro imports, by the groups that
+//! `watt::ImportPolicy` allows or denies, along with any imports watt does not
+//! provide or provides with a different signature. Both wasm files and the
+//! compact format are accepted.
+//!
+//! ```console
+//! $ cargo run -p watt-audit -- my_macro.wasm
+//! ```
+
+#[path = "../../src/abi.rs"]
+#[macro_use]
+mod abi;
+#[path = "../../src/policy.rs"]
+#[allow(dead_code)]
+mod policy;
+#[path = "../../src/signature.rs"]
+mod signature;
+
+use crate::signature::ABI;
+use std::env;
+use std::fs;
+use std::process;
+use watt_runtime::{decode_compact_module, decode_module, module_imports, types, Extern};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 1 {
+        eprintln!("usage: watt-audit <input.wasm>");
+        process::exit(2);
+    }
+
+    let bytes = fs::read(&args[0]).unwrap_or_else(|err| {
+        eprintln!("failed to read {}: {}", args[0], err);
+        process::exit(1);
+    });
+    let module = if bytes.starts_with(b"\0asm") {
+        decode_module(&bytes)
+    } else {
+        decode_compact_module(&bytes)
+    };
+    let module = module.unwrap_or_else(|err| {
+        eprintln!("failed to load {}: {:?}", args[0], err);
+        process::exit(1);
+    });
+
+    // Groups in the order of the ABI table
+    let mut groups = Vec::new();
+    for signature in ABI {
+        if !groups.contains(&signature.group) {
+            groups.push(signature.group);
+        }
+    }
+
+    let mut requested = vec![Vec::new(); groups.len()];
+    let mut host = Vec::new();
+    let mut mismatched = Vec::new();
+    let mut unsupported = Vec::new();
+    for (module, name, sig) in module_imports(&module) {
+        let import = format!("{}::{}", module, name);
+        match sig {
+            Extern::Func(func) => match signature::find(name) {
+                Some(signature) => match signature::check(signature, &func) {
+                    Ok(()) => {
+                        let i = groups.iter().position(|&g| g == signature.group).unwrap();
+                        requested[i].push(import);
+                    }
+                    Err(reason) => mismatched.push(format!("{}: {}", import, reason)),
+                },
+                None => unsupported.push(import),
+            },
+            Extern::Global(_) | Extern::Tag(_) => host.push(import),
+            Extern::Table(_) | Extern::Memory(_) => unsupported.push(import),
+        }
+    }
+
+    for (group, imports) in groups.iter().zip(&requested) {
+        if !imports.is_empty() {
+            let total = ABI.iter().filter(|s| s.group == *group).count();
+            println!("{:?} ({} of {})", group, imports.len(), total);
+            print_imports(imports);
+        }
+    }
+    if !host.is_empty() {
+        println!(
+            "Globals and exception tags, owned by the host and not subject to the import policy"
+        );
+        print_imports(&host);
+    }
+    if !mismatched.is_empty() {
+        println!("Provided by watt with a different signature");
+        print_imports(&mismatched);
+    }
+    if !unsupported.is_empty() {
+        println!("Not provided by watt");
+        print_imports(&unsupported);
+    }
+}
+
+fn print_imports(imports: &[String]) {
+    for import in imports {
+        println!("    {}", import);
+    }
+}
@@ -0,0 +1,31 @@
+//! Convert a wasm proc macro to the compact format loaded by
+//! `watt::proc_macro_compact` and its derive and attribute counterparts.
+//!
+//! ```console
+//! $ cargo run -p watt-compact -- my_macro.wasm my_macro.watt
+//! ```
+
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 2 {
+        eprintln!("usage: watt-compact <input.wasm> <output.watt>");
+        process::exit(2);
+    }
+
+    let wasm = fs::read(&args[0]).unwrap_or_else(|err| {
+        eprintln!("failed to read {}: {}", args[0], err);
+        process::exit(1);
+    });
+    let compact = watt_runtime::compact_module(&wasm).unwrap_or_else(|err| {
+        eprintln!("failed to compact {}: {:?}", args[0], err);
+        process::exit(1);
+    });
+    if let Err(err) = fs::write(&args[1], &compact) {
+        eprintln!("failed to write {}: {}", args[1], err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,822 @@
+const SENTINEL: u32 = u32::max_value();
+const SPAN_CALL_SITE: u32 = 0;
+const TOKEN_GROUP: u32 = 0;
+const TOKEN_IDENT: u32 = 1;
+const TOKEN_PUNCT: u32 = 2;
+const TOKEN_LITERAL: u32 = 3;
+const DELIMITER_PARENTHESIS: u32 = 0;
+const DELIMITER_BRACE: u32 = 1;
+const DELIMITER_BRACKET: u32 = 2;
+const DELIMITER_NONE: u32 = 3;
+const SPACING_ALONE: u32 = 0;
+const SPACING_JOINT: u32 = 1;
+
+mod ffi {
+    use super::*;
+
+    // Strings sent to the host are dropped once the call taking them returns
+    pub struct SentString(pub handle::String);
+
+    impl Drop for SentString {
+        fn drop(&mut self) {
+            unsafe { watt_string_drop(self.0) }
+        }
+    }
+
+    pub fn send_str(data: &str) -> SentString {
+        SentString(unsafe { watt_string_new(data.as_ptr(), data.len()) })
+    }
+
+    pub fn recv_string(handle: handle::String) -> String {
+        unsafe {
+            let len = watt_string_len(handle);
+            let mut bytes = Vec::with_capacity(len);
+            watt_string_copy(handle, bytes.as_mut_ptr());
+            bytes.set_len(len);
+            watt_string_drop(handle);
+            String::from_utf8_unchecked(bytes)
+        }
+    }
+
+    // The host takes ownership of the bytes
+    pub fn send_bytes(data: &[u8]) -> handle::Bytes {
+        unsafe { watt_bytes_new(data.as_ptr(), data.len()) }
+    }
+
+    pub fn recv_bytes(handle: handle::Bytes) -> Vec<u8> {
+        unsafe {
+            let len = watt_bytes_len(handle);
+            let mut bytes = Vec::with_capacity(len);
+            watt_bytes_copy(handle, bytes.as_mut_ptr());
+            bytes.set_len(len);
+            watt_bytes_drop(handle);
+            bytes
+        }
+    }
+}
+
+// Token streams cross the boundary in the serialized form described in the
+// local module of watt: u32 little-endian integers and length-prefixed
+// strings, spans being handles into a table kept by the host.
+mod wire {
+    use super::*;
+
+    // Entry points called by the host before and after the macro
+    #[no_mangle]
+    extern "C" fn watt_token_stream_decode(bytes: handle::Bytes) -> TokenStream {
+        let bytes = ffi::recv_bytes(bytes);
+        decode(&bytes)
+    }
+
+    #[no_mangle]
+    extern "C" fn watt_token_stream_encode(stream: TokenStream) -> handle::Bytes {
+        let mut bytes = Vec::new();
+        encode(&stream, &mut bytes);
+        ffi::send_bytes(&bytes)
+    }
+
+    pub fn decode(bytes: &[u8]) -> TokenStream {
+        Reader { bytes }.stream()
+    }
+
+    fn encode(stream: &TokenStream, bytes: &mut Vec<u8>) {
+        push_u32(bytes, stream.inner.len() as u32);
+        for token in stream.inner.iter() {
+            match token {
+                TokenTree::Group(group) => {
+                    push_u32(bytes, TOKEN_GROUP);
+                    push_u32(bytes, (group.span.0).0);
+                    push_u32(
+                        bytes,
+                        match group.delimiter {
+                            Delimiter::Parenthesis => DELIMITER_PARENTHESIS,
+                            Delimiter::Brace => DELIMITER_BRACE,
+                            Delimiter::Bracket => DELIMITER_BRACKET,
+                            Delimiter::None => DELIMITER_NONE,
+                        },
+                    );
+                    encode(&group.stream, bytes);
+                }
+                TokenTree::Ident(ident) => {
+                    push_u32(bytes, TOKEN_IDENT);
+                    push_u32(bytes, (ident.span.0).0);
+                    push_str(bytes, &ident.sym);
+                }
+                TokenTree::Punct(punct) => {
+                    push_u32(bytes, TOKEN_PUNCT);
+                    push_u32(bytes, (punct.span.0).0);
+                    push_u32(bytes, punct.op as u32);
+                    push_u32(
+                        bytes,
+                        match punct.spacing {
+                            Spacing::Alone => SPACING_ALONE,
+                            Spacing::Joint => SPACING_JOINT,
+                        },
+                    );
+                }
+                TokenTree::Literal(literal) => {
+                    push_u32(bytes, TOKEN_LITERAL);
+                    push_u32(bytes, (literal.span.0).0);
+                    push_str(bytes, &literal.text);
+                }
+            }
+        }
+    }
+
+    fn push_u32(bytes: &mut Vec<u8>, n: u32) {
+        bytes.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn push_str(bytes: &mut Vec<u8>, string: &str) {
+        push_u32(bytes, string.len() as u32);
+        bytes.extend_from_slice(string.as_bytes());
+    }
+
+    // The host only ever hands out well-formed token streams
+    struct Reader<'a> {
+        bytes: &'a [u8],
+    }
+
+    impl<'a> Reader<'a> {
+        fn stream(&mut self) -> TokenStream {
+            let len = self.u32();
+            let mut inner = Vec::with_capacity(len as usize);
+            for _ in 0..len {
+                let kind = self.u32();
+                let span = Span(handle::Span(self.u32()));
+                inner.push(if kind == TOKEN_GROUP {
+                    let delimiter = self.u32();
+                    let delimiter = if delimiter == DELIMITER_PARENTHESIS {
+                        Delimiter::Parenthesis
+                    } else if delimiter == DELIMITER_BRACE {
+                        Delimiter::Brace
+                    } else if delimiter == DELIMITER_BRACKET {
+                        Delimiter::Bracket
+                    } else if delimiter == DELIMITER_NONE {
+                        Delimiter::None
+                    } else {
+                        unreachable!()
+                    };
+                    let stream = self.stream();
+                    TokenTree::Group(Group {
+                        delimiter,
+                        stream,
+                        span,
+                    })
+                } else if kind == TOKEN_IDENT {
+                    let sym = self.str().to_owned();
+                    TokenTree::Ident(Ident { sym, span })
+                } else if kind == TOKEN_PUNCT {
+                    let op = char::from_u32(self.u32()).unwrap();
+                    let spacing = if self.u32() == SPACING_JOINT {
+                        Spacing::Joint
+                    } else {
+                        Spacing::Alone
+                    };
+                    TokenTree::Punct(Punct { op, spacing, span })
+                } else if kind == TOKEN_LITERAL {
+                    let text = self.str().to_owned();
+                    TokenTree::Literal(Literal { text, span })
+                } else {
+                    unreachable!()
+                });
+            }
+            TokenStream {
+                inner: Box::new(inner),
+            }
+        }
+
+        fn u32(&mut self) -> u32 {
+            let (bytes, rest) = self.bytes.split_at(4);
+            self.bytes = rest;
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        }
+
+        fn str(&mut self) -> &'a str {
+            let len = self.u32() as usize;
+            let (bytes, rest) = self.bytes.split_at(len);
+            self.bytes = rest;
+            str::from_utf8(bytes).unwrap()
+        }
+    }
+}
+
+use crate::sys::*;
+use std::char;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::RangeBounds;
+use std::panic::{self, PanicInfo};
+use std::str::{self, FromStr};
+use std::vec;
+
+pub fn set_wasm_panic_hook() {
+    panic::set_hook(Box::new(panic_hook));
+}
+
+fn panic_hook(panic: &PanicInfo) {
+    let string = panic.to_string();
+    unsafe {
+        watt_print_panic(ffi::send_str(&string).0);
+    }
+}
+
+// A single pointer, so that macros take and return it like a handle
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct TokenStream {
+    inner: Box<Vec<TokenTree>>,
+}
+
+impl From<proc_macro::TokenStream> for TokenStream {
+    fn from(_: proc_macro::TokenStream) -> Self {
+        unimplemented!("From<proc_macro::TokenStream> does not exist in wasm");
+    }
+}
+
+pub struct LexError {
+    _private: (),
+}
+
+impl TokenStream {
+    pub fn new() -> Self {
+        TokenStream {
+            inner: Box::new(Vec::new()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+impl Default for TokenStream {
+    fn default() -> Self {
+        TokenStream::new()
+    }
+}
+
+impl FromStr for TokenStream {
+    type Err = LexError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let bytes = unsafe { watt_token_stream_parse(ffi::send_str(input).0) };
+        if bytes.0 == SENTINEL {
+            Err(LexError { _private: () })
+        } else {
+            Ok(wire::decode(&ffi::recv_bytes(bytes)))
+        }
+    }
+}
+
+impl From<TokenTree> for TokenStream {
+    fn from(token: TokenTree) -> Self {
+        TokenStream {
+            inner: Box::new(vec![token]),
+        }
+    }
+}
+
+impl Extend<TokenTree> for TokenStream {
+    fn extend<I: IntoIterator<Item = TokenTree>>(&mut self, iter: I) {
+        self.inner.extend(iter);
+    }
+}
+
+impl Extend<TokenStream> for TokenStream {
+    fn extend<I: IntoIterator<Item = TokenStream>>(&mut self, iter: I) {
+        for stream in iter {
+            self.inner.extend(*stream.inner);
+        }
+    }
+}
+
+impl FromIterator<TokenTree> for TokenStream {
+    fn from_iter<I: IntoIterator<Item = TokenTree>>(iter: I) -> Self {
+        let mut stream = TokenStream::new();
+        stream.extend(iter);
+        stream
+    }
+}
+
+impl FromIterator<TokenStream> for TokenStream {
+    fn from_iter<I: IntoIterator<Item = TokenStream>>(iter: I) -> Self {
+        let mut stream = TokenStream::new();
+        stream.extend(iter);
+        stream
+    }
+}
+
+impl Display for TokenStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut joint = false;
+        for (i, token) in self.inner.iter().enumerate() {
+            if i != 0 && !joint {
+                f.write_str(" ")?;
+            }
+            joint = match token {
+                TokenTree::Punct(punct) => punct.spacing == Spacing::Joint,
+                _ => false,
+            };
+            Display::fmt(token, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for TokenStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("TokenStream ")?;
+        f.debug_list().entries(self.inner.iter()).finish()
+    }
+}
+
+impl Debug for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LexError")
+    }
+}
+
+#[repr(transparent)]
+#[derive(Copy, Clone)]
+pub struct Span(handle::Span);
+
+impl Span {
+    pub fn call_site() -> Self {
+        Span(handle::Span(SPAN_CALL_SITE))
+    }
+
+    pub fn join(&self, other: Span) -> Option<Span> {
+        let _ = other;
+        None
+    }
+}
+
+impl Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string = ffi::recv_string(unsafe { span_debug(self.0) });
+        f.write_str(&string)
+    }
+}
+
+#[derive(Clone)]
+pub enum TokenTree {
+    Group(Group),
+    Ident(Ident),
+    Punct(Punct),
+    Literal(Literal),
+}
+
+impl TokenTree {
+    pub fn span(&self) -> Span {
+        match self {
+            TokenTree::Group(t) => t.span(),
+            TokenTree::Ident(t) => t.span(),
+            TokenTree::Punct(t) => t.span(),
+            TokenTree::Literal(t) => t.span(),
+        }
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        match self {
+            TokenTree::Group(t) => t.set_span(span),
+            TokenTree::Ident(t) => t.set_span(span),
+            TokenTree::Punct(t) => t.set_span(span),
+            TokenTree::Literal(t) => t.set_span(span),
+        }
+    }
+}
+
+impl From<Group> for TokenTree {
+    fn from(g: Group) -> TokenTree {
+        TokenTree::Group(g)
+    }
+}
+
+impl From<Ident> for TokenTree {
+    fn from(g: Ident) -> TokenTree {
+        TokenTree::Ident(g)
+    }
+}
+
+impl From<Punct> for TokenTree {
+    fn from(g: Punct) -> TokenTree {
+        TokenTree::Punct(g)
+    }
+}
+
+impl From<Literal> for TokenTree {
+    fn from(g: Literal) -> TokenTree {
+        TokenTree::Literal(g)
+    }
+}
+
+impl Display for TokenTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenTree::Group(group) => Display::fmt(group, f),
+            TokenTree::Ident(ident) => Display::fmt(ident, f),
+            TokenTree::Punct(punct) => Display::fmt(punct, f),
+            TokenTree::Literal(literal) => Display::fmt(literal, f),
+        }
+    }
+}
+
+impl Debug for TokenTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenTree::Group(group) => Debug::fmt(group, f),
+            TokenTree::Ident(ident) => Debug::fmt(ident, f),
+            TokenTree::Punct(punct) => Debug::fmt(punct, f),
+            TokenTree::Literal(literal) => Debug::fmt(literal, f),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Group {
+    delimiter: Delimiter,
+    stream: TokenStream,
+    span: Span,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Delimiter {
+    Parenthesis,
+    Brace,
+    Bracket,
+    None,
+}
+
+impl Group {
+    pub fn new(delimiter: Delimiter, stream: TokenStream) -> Self {
+        Group {
+            delimiter,
+            stream,
+            span: Span::call_site(),
+        }
+    }
+
+    pub fn delimiter(&self) -> Delimiter {
+        self.delimiter
+    }
+
+    pub fn stream(&self) -> TokenStream {
+        self.stream.clone()
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn span_open(&self) -> Span {
+        self.span
+    }
+
+    pub fn span_close(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+
+impl Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (open, close) = match self.delimiter {
+            Delimiter::Parenthesis => ("(", ")"),
+            Delimiter::Brace => ("{ ", "}"),
+            Delimiter::Bracket => ("[", "]"),
+            Delimiter::None => ("", ""),
+        };
+        f.write_str(open)?;
+        Display::fmt(&self.stream, f)?;
+        if self.delimiter == Delimiter::Brace && !self.stream.is_empty() {
+            f.write_str(" ")?;
+        }
+        f.write_str(close)
+    }
+}
+
+impl Debug for Group {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Group")
+            .field("delimiter", &self.delimiter)
+            .field("stream", &self.stream)
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub struct Punct {
+    op: char,
+    spacing: Spacing,
+    span: Span,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Spacing {
+    Alone,
+    Joint,
+}
+
+impl Punct {
+    pub fn new(op: char, spacing: Spacing) -> Self {
+        Punct {
+            op,
+            spacing,
+            span: Span::call_site(),
+        }
+    }
+
+    pub fn as_char(&self) -> char {
+        self.op
+    }
+
+    pub fn spacing(&self) -> Spacing {
+        self.spacing
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+
+impl Display for Punct {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.op, f)
+    }
+}
+
+impl Debug for Punct {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Punct")
+            .field("op", &self.op)
+            .field("spacing", &self.spacing)
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub struct Ident {
+    sym: String,
+    span: Span,
+}
+
+impl Ident {
+    pub fn new(string: &str, span: Span) -> Self {
+        Ident {
+            sym: string.to_owned(),
+            span,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+}
+
+impl PartialEq for Ident {
+    fn eq(&self, other: &Ident) -> bool {
+        self.sym == other.sym
+    }
+}
+
+impl<T> PartialEq<T> for Ident
+where
+    T: ?Sized + AsRef<str>,
+{
+    fn eq(&self, other: &T) -> bool {
+        self.sym == other.as_ref()
+    }
+}
+
+impl Eq for Ident {}
+
+impl PartialOrd for Ident {
+    fn partial_cmp(&self, other: &Ident) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ident {
+    fn cmp(&self, other: &Ident) -> Ordering {
+        self.sym.cmp(&other.sym)
+    }
+}
+
+impl Hash for Ident {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.sym.hash(hasher);
+    }
+}
+
+impl Display for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.sym)
+    }
+}
+
+impl Debug for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ident")
+            .field("sym", &format_args!("{}", self.sym))
+            .finish()
+    }
+}
+
+// The source text of the literal, which the host parses back
+#[derive(Clone)]
+pub struct Literal {
+    text: String,
+    span: Span,
+}
+
+macro_rules! suffixed_literals {
+    ($($name:ident => $kind:ident,)*) => ($(
+        pub fn $name(n: $kind) -> Self {
+            Literal::from_text(format!(concat!("{}", stringify!($kind)), n))
+        }
+    )*)
+}
+
+macro_rules! unsuffixed_literals {
+    ($($name:ident => $kind:ident,)*) => ($(
+        pub fn $name(n: $kind) -> Self {
+            Literal::from_text(n.to_string())
+        }
+    )*)
+}
+
+impl Literal {
+    fn from_text(text: String) -> Self {
+        Literal {
+            text,
+            span: Span::call_site(),
+        }
+    }
+
+    suffixed_literals! {
+        u8_suffixed => u8,
+        u16_suffixed => u16,
+        u32_suffixed => u32,
+        u64_suffixed => u64,
+        u128_suffixed => u128,
+        usize_suffixed => usize,
+        i8_suffixed => i8,
+        i16_suffixed => i16,
+        i32_suffixed => i32,
+        i64_suffixed => i64,
+        i128_suffixed => i128,
+        isize_suffixed => isize,
+        f64_suffixed => f64,
+        f32_suffixed => f32,
+    }
+
+    unsuffixed_literals! {
+        u8_unsuffixed => u8,
+        u16_unsuffixed => u16,
+        u32_unsuffixed => u32,
+        u64_unsuffixed => u64,
+        u128_unsuffixed => u128,
+        usize_unsuffixed => usize,
+        i8_unsuffixed => i8,
+        i16_unsuffixed => i16,
+        i32_unsuffixed => i32,
+        i64_unsuffixed => i64,
+        i128_unsuffixed => i128,
+        isize_unsuffixed => isize,
+    }
+
+    pub fn f64_unsuffixed(f: f64) -> Self {
+        assert!(f.is_finite(), "invalid float literal {}", f);
+        let mut text = f.to_string();
+        if !text.contains('.') {
+            text.push_str(".0");
+        }
+        Literal::from_text(text)
+    }
+
+    pub fn f32_unsuffixed(f: f32) -> Self {
+        assert!(f.is_finite(), "invalid float literal {}", f);
+        let mut text = f.to_string();
+        if !text.contains('.') {
+            text.push_str(".0");
+        }
+        Literal::from_text(text)
+    }
+
+    pub fn string(string: &str) -> Self {
+        let mut text = String::with_capacity(string.len() + 2);
+        text.push('"');
+        for ch in string.chars() {
+            // An unescaped single quote is fine in a string
+            if ch == '\'' {
+                text.push(ch);
+            } else {
+                text.extend(ch.escape_debug());
+            }
+        }
+        text.push('"');
+        Literal::from_text(text)
+    }
+
+    pub fn character(ch: char) -> Self {
+        let mut text = String::new();
+        text.push('\'');
+        // An unescaped double quote is fine in a character
+        if ch == '"' {
+            text.push(ch);
+        } else {
+            text.extend(ch.escape_debug());
+        }
+        text.push('\'');
+        Literal::from_text(text)
+    }
+
+    pub fn byte_string(bytes: &[u8]) -> Self {
+        let mut text = "b\"".to_owned();
+        for &b in bytes {
+            match b {
+                b'\0' => text.push_str(r"\0"),
+                b'\t' => text.push_str(r"\t"),
+                b'\n' => text.push_str(r"\n"),
+                b'\r' => text.push_str(r"\r"),
+                b'"' => text.push_str("\\\""),
+                b'\\' => text.push_str("\\\\"),
+                b'\x20'..=b'\x7E' => text.push(b as char),
+                _ => text.push_str(&format!("\\x{:02X}", b)),
+            }
+        }
+        text.push('"');
+        Literal::from_text(text)
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+
+    pub fn subspan<R: RangeBounds<usize>>(&self, range: R) -> Option<Span> {
+        let _ = range;
+        None
+    }
+}
+
+impl Debug for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Literal")
+            .field("lit", &format_args!("{}", self.text))
+            .finish()
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+pub mod token_stream {
+    use super::*;
+
+    pub use crate::TokenStream;
+
+    #[derive(Clone)]
+    pub struct IntoIter(vec::IntoIter<TokenTree>);
+
+    impl Iterator for IntoIter {
+        type Item = TokenTree;
+
+        fn next(&mut self) -> Option<TokenTree> {
+            self.0.next()
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.0.size_hint()
+        }
+    }
+
+    impl IntoIterator for TokenStream {
+        type Item = TokenTree;
+        type IntoIter = IntoIter;
+
+        fn into_iter(self) -> Self::IntoIter {
+            IntoIter(self.inner.into_iter())
+        }
+    }
+}
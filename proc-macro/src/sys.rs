@@ -0,0 +1,73 @@
+// Functions imported from watt, declared from the same table that watt checks
+// the imports of a module against. Each flavor of the shim only uses some of
+// them, and none uses those kept for modules built against earlier versions.
+
+#![allow(dead_code)]
+
+use self::handle::{
+    Bytes, Group, Ident, IntoIter, Literal, Punct, Span, String, TokenStream, TokenTree,
+};
+
+type Ptr = *const u8;
+type PtrMut = *mut u8;
+type Batch = *mut [u32; 2];
+
+macro_rules! extern_block {
+    ($($group:ident {
+        $(fn $name:ident($($arg:ident: $ty:ident),*) $(-> $ret:ident)?;)*
+    })*) => {
+        extern "C" {
+            $($(
+                pub fn $name($($arg: $ty),*) $(-> $ret)?;
+            )*)*
+        }
+    };
+}
+
+watt_abi!(extern_block);
+
+pub mod handle {
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct TokenStream(pub u32);
+
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct LexError(pub u32);
+
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct Span(pub u32);
+
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct Group(pub u32);
+
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct Punct(pub u32);
+
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct Ident(pub u32);
+
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct Literal(pub u32);
+
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct IntoIter(pub u32);
+
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct TokenTree(pub u32);
+
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct String(pub u32);
+
+    #[repr(transparent)]
+    #[derive(Copy, Clone)]
+    pub struct Bytes(pub u32);
+}
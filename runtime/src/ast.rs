@@ -1,3 +1,4 @@
+use super::lower::Code;
 use crate::{types, values};
 
 #[derive(Debug)]
@@ -18,7 +19,7 @@ pub enum FUnOp {
     Sqrt,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum IBinOp {
     Add,
     Sub,
@@ -100,6 +101,23 @@ pub enum ConvertOp {
     F64PromoteF32,
 }
 
+impl ConvertOp {
+    /// The operand and result types of the conversion
+    pub fn types(&self) -> (types::Value, types::Value) {
+        match *self {
+            ConvertOp::I32WrapI64 => (types::I64, types::I32),
+            ConvertOp::I64ExtendUI32 | ConvertOp::I64ExtendSI32 => (types::I32, types::I64),
+            ConvertOp::Trunc { from, to, .. } => (types::Value::Float(from), types::Value::Int(to)),
+            ConvertOp::Convert { from, to, .. } => {
+                (types::Value::Int(from), types::Value::Float(to))
+            }
+            ConvertOp::Reinterpret { from, to } => (from, to),
+            ConvertOp::F32DemoteF64 => (types::F64, types::F32),
+            ConvertOp::F64PromoteF32 => (types::F32, types::F64),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct MemOp<T> {
     pub align: u32,
@@ -114,55 +132,261 @@ pub type LoadOp = MemOp<(u32, bool)>;
 /// A memory store with optional size
 pub type StoreOp = MemOp<(u32)>;
 
+/// The bytes read by a load and their extension to a value, selected when
+/// decoding. Floats are loaded as their bits.
+#[derive(Debug, Clone, Copy)]
+pub enum LoadKind {
+    I32,
+    I64,
+    I32S8,
+    I32U8,
+    I32S16,
+    I32U16,
+    I64S8,
+    I64U8,
+    I64S16,
+    I64U16,
+    I64S32,
+    I64U32,
+}
+
+/// The number of low bytes of a value written by a store, selected when decoding
+#[derive(Debug, Clone, Copy)]
+pub enum StoreKind {
+    Bits8,
+    Bits16,
+    Bits32,
+    Bits64,
+}
+
+/// An atomic memory access with optional size, narrow values being zero-extended
+pub type AtomicOp = MemOp<u32>;
+
+#[derive(Debug, Clone, Copy)]
+pub enum AtomicRmwOp {
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+    Xchg,
+}
+
+/// Lane interpretation of a 128-bit vector
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Shape {
+    I8x16,
+    I16x8,
+    I32x4,
+    I64x2,
+    F32x4,
+    F64x2,
+}
+
+impl Shape {
+    pub fn lanes(self) -> u8 {
+        match self {
+            Shape::I8x16 => 16,
+            Shape::I16x8 => 8,
+            Shape::I32x4 | Shape::F32x4 => 4,
+            Shape::I64x2 | Shape::F64x2 => 2,
+        }
+    }
+
+    pub fn lane_bits(self) -> u32 {
+        128 / self.lanes() as u32
+    }
+
+    /// The scalar type of a single lane, i8 and i16 lanes are carried as i32
+    pub fn lane_type(self) -> types::Value {
+        match self {
+            Shape::I8x16 | Shape::I16x8 | Shape::I32x4 => types::I32,
+            Shape::I64x2 => types::I64,
+            Shape::F32x4 => types::F32,
+            Shape::F64x2 => types::F64,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum VIUnOp {
+    Abs,
+    Neg,
+    Popcnt,
+    ExtendLow(bool),      // widen the low half lanes, signed
+    ExtendHigh(bool),     // widen the high half lanes, signed
+    ExtAddPairwise(bool), // add adjacent half-width lanes, signed
+}
+
+#[derive(Debug)]
+pub enum VIBinOp {
+    Add,
+    Sub,
+    Mul,
+    AddSat(bool),
+    SubSat(bool),
+    Min(bool),
+    Max(bool),
+    AvgrU,
+    Q15MulrSatS,
+    Dot,              // dot product of signed half-width lanes
+    ExtMulLow(bool),  // multiply the widened low half lanes, signed
+    ExtMulHigh(bool), // multiply the widened high half lanes, signed
+    Narrow(bool),     // saturate double-width lanes, signed result
+}
+
+#[derive(Debug)]
+pub enum VFBinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Min,
+    Max,
+    PMin,
+    PMax,
+}
+
+#[derive(Debug)]
+pub enum VShiftOp {
+    Shl,
+    ShrS,
+    ShrU,
+}
+
+#[derive(Debug)]
+pub enum VBitOp {
+    And,
+    AndNot,
+    Or,
+    Xor,
+}
+
+#[derive(Debug)]
+pub enum VConvertOp {
+    F32x4DemoteF64x2Zero,
+    F64x2PromoteLowF32x4,
+    I32x4TruncSatF32x4(bool),
+    I32x4TruncSatF64x2Zero(bool),
+    F32x4ConvertI32x4(bool),
+    F64x2ConvertLowI32x4(bool),
+}
+
+#[derive(Debug)]
+pub enum VLoadKind {
+    Full,                // load 128 bits
+    Extend(Shape, bool), // load 64 bits widened to the lanes of shape, signed
+    Splat(Shape),        // load a single lane and replicate it
+    Zero(Shape),         // load a single lane into lane 0, zero the others
+    Lane(Shape, u8),     // load a single lane into an existing vector
+}
+
+#[derive(Debug)]
+pub enum VStoreKind {
+    Full,            // store 128 bits
+    Lane(Shape, u8), // store a single lane
+}
+
+#[derive(Debug)]
+pub struct VMemOp<T> {
+    pub align: u32,
+    pub offset: u32,
+    pub kind: T,
+}
+
+/// A handler of a `try_table`, branching to `label` when it catches an exception
+#[derive(Debug)]
+pub enum Catch {
+    Tag(Index, Index),    // exception with the given tag, push its arguments
+    TagRef(Index, Index), // exception with the given tag, push its arguments and the exnref
+    All(Index),           // any exception
+    AllRef(Index),        // any exception, push the exnref
+}
+
 #[derive(Debug)]
 pub enum Instr {
-    Unreachable,                                   // trap unconditionally
-    Nop,                                           // do nothing
-    Block(Vec<types::Value>, Vec<Instr>),          // execute in sequence
-    Loop(Vec<types::Value>, Vec<Instr>),           // loop header
-    If(Vec<types::Value>, Vec<Instr>, Vec<Instr>), // conditional
-    Br(Index),                                     // break to n-th surrounding label
-    BrIf(Index),                                   // conditional break
-    BrTable(Vec<Index>, Index),                    // indexed break
-    Return,                                        // break from function body
-    Call(Index),                                   // call function
-    CallIndirect(Index),                           // call function through table
-    Drop_,                                         // forget a value
-    Select,                                        // branchless conditional
-    GetLocal(Index),                               // read local variable
-    SetLocal(Index),                               // write local variable
-    TeeLocal(Index),                               // write local variable and keep value
-    GetGlobal(Index),                              // read global variable
-    SetGlobal(Index),                              // write global variable
-    Load(LoadOp),                                  // read memory at address
-    Store(StoreOp),                                // write memory at address
-    CurrentMemory,                                 // size(linear memory
-    GrowMemory,                                    // grow linear memory
-    Const(values::Value),                          // constant
-    IUnary(types::Int, IUnOp),                     // integer unary numeric operators
-    FUnary(types::Float, FUnOp),                   // floating unary numeric operators
-    IBin(types::Int, IBinOp),                      // integer binary numeric operators
-    FBin(types::Float, FBinOp),                    // floating binary numeric operators
-    ITest(types::Int, ITestOp),                    // integer numeric test
-    IRel(types::Int, IRelOp),                      // integer numeric comparison
-    FRel(types::Float, FRelOp),                    // floating numeric comparison
-    Convert(ConvertOp),                            // conversion
+    Unreachable,                                         // trap unconditionally
+    Nop,                                                 // do nothing
+    Block(Vec<types::Value>, Vec<Instr>),                // execute in sequence
+    Loop(Vec<types::Value>, Vec<Instr>),                 // loop header
+    If(Vec<types::Value>, Vec<Instr>, Vec<Instr>),       // conditional
+    TryTable(Vec<types::Value>, Vec<Catch>, Vec<Instr>), // block with exception handlers
+    Throw(Index),                                        // throw exception with tag
+    ThrowRef,                                            // rethrow exception reference
+    Br(Index),                                           // break to n-th surrounding label
+    BrIf(Index),                                         // conditional break
+    BrTable(Vec<Index>, Index),                          // indexed break
+    Return,                                              // break from function body
+    Call(Index),                                         // call function
+    CallIndirect(Index),                                 // call function through table
+    ReturnCall(Index),                                   // tail call function
+    ReturnCallIndirect(Index),                           // tail call function through table
+    Drop_,                                               // forget a value
+    Select,                                              // branchless conditional
+    GetLocal(Index),                                     // read local variable
+    SetLocal(Index),                                     // write local variable
+    TeeLocal(Index),                                     // write local variable and keep value
+    GetGlobal(Index),                                    // read global variable
+    SetGlobal(Index),                                    // write global variable
+    Load(LoadOp, LoadKind),                              // read memory at address
+    Store(StoreOp, StoreKind),                           // write memory at address
+    CurrentMemory,                                       // size(linear memory
+    GrowMemory,                                          // grow linear memory
+    Const(values::Value),                                // constant
+    IUnary(types::Int, IUnOp),                           // integer unary numeric operators
+    FUnary(types::Float, FUnOp),                         // floating unary numeric operators
+    IBin(types::Int, IBinOp),                            // integer binary numeric operators
+    FBin(types::Float, FBinOp),                          // floating binary numeric operators
+    ITest(types::Int, ITestOp),                          // integer numeric test
+    IRel(types::Int, IRelOp),                            // integer numeric comparison
+    FRel(types::Float, FRelOp),                          // floating numeric comparison
+    Convert(ConvertOp),                                  // conversion
+    VLoad(VMemOp<VLoadKind>),                            // read vector from memory
+    VStore(VMemOp<VStoreKind>),                          // write vector to memory
+    Shuffle([u8; 16]),                                   // select bytes from two vectors
+    Swizzle,                                             // select bytes by dynamic indices
+    Splat(Shape),                                        // replicate scalar to all lanes
+    ExtractLane(Shape, u8, bool),                        // read lane, sign-extended
+    ReplaceLane(Shape, u8),                              // write lane
+    VNot,                                                // bitwise not
+    VBit(VBitOp),                                        // bitwise binary operators
+    Bitselect,                                           // bitwise select
+    AnyTrue,                                             // any bit set
+    AllTrue(Shape),                                      // all lanes non-zero
+    Bitmask(Shape),                                      // collect lane sign bits
+    VIUnary(Shape, VIUnOp),                              // vector integer unary operators
+    VFUnary(Shape, FUnOp),                               // vector floating unary operators
+    VIBin(Shape, VIBinOp),                               // vector integer binary operators
+    VFBin(Shape, VFBinOp),                               // vector floating binary operators
+    VShift(Shape, VShiftOp),                             // vector shift by scalar
+    VIRel(Shape, IRelOp),                                // vector integer comparison
+    VFRel(Shape, FRelOp),                                // vector floating comparison
+    VConvert(VConvertOp),                                // vector conversion
+    AtomicLoad(AtomicOp),                                // read memory atomically
+    AtomicStore(AtomicOp),                               // write memory atomically
+    AtomicRmw(AtomicRmwOp, AtomicOp),                    // read-modify-write memory atomically
+    AtomicCmpxchg(AtomicOp),                             // compare and exchange memory atomically
+    AtomicWait(AtomicOp),                                // wait for a notification
+    AtomicNotify(AtomicOp),                              // wake up waiting threads
+    AtomicFence,                                         // order memory accesses
 }
 
 pub type Expr = Vec<Instr>;
 
 #[derive(Debug)]
-pub struct Module {
+pub struct Module<'a> {
     pub(crate) types: Vec<types::Func>,
     pub(crate) funcs: Vec<Func>,
     pub(crate) tables: Vec<Table>,
     pub(crate) memories: Vec<Memory>,
     pub(crate) globals: Vec<Global>,
+    pub(crate) tags: Vec<Tag>,
     pub(crate) elems: Vec<Segment<Index>>, // initial values for tables
-    pub(crate) data: Vec<Segment<u8>>,     // initial values for memories
+    pub(crate) data: Vec<Data<'a>>,        // initial values for memories
     pub(crate) start: Option<Index>,       // optionnal index to a start function
-    pub(crate) imports: Vec<Import>,
-    pub(crate) exports: Vec<Export>,
+    pub(crate) imports: Vec<Import<'a>>,
+    pub(crate) exports: Vec<Export<'a>>,
+    pub(crate) code: Option<Vec<Code>>, // lowered bodies of a compact module
 }
 
 pub type Index = u32;
@@ -190,6 +414,11 @@ pub struct Global {
     pub value: Expr, // NB: Must be constant
 }
 
+#[derive(Debug)]
+pub struct Tag {
+    pub type_index: Index,
+}
+
 #[derive(Debug)]
 pub struct Segment<T> {
     pub index: Index,
@@ -198,8 +427,15 @@ pub struct Segment<T> {
 }
 
 #[derive(Debug)]
-pub struct Export {
-    pub name: String,
+pub struct Data<'a> {
+    pub index: Index,
+    pub offset: Expr, // NB: Must be constant
+    pub init: &'a [u8],
+}
+
+#[derive(Debug)]
+pub struct Export<'a> {
+    pub name: &'a str,
     pub desc: ExportDesc,
 }
 
@@ -209,12 +445,13 @@ pub enum ExportDesc {
     Table(Index),
     Memory(Index),
     Global(Index),
+    Tag(Index),
 }
 
 #[derive(Debug)]
-pub struct Import {
-    pub module: String,
-    pub name: String,
+pub struct Import<'a> {
+    pub module: &'a str,
+    pub name: &'a str,
     pub desc: ImportDesc,
 }
 
@@ -224,34 +461,38 @@ pub enum ImportDesc {
     Table(types::Table),
     Memory(types::Memory),
     Global(types::Global),
+    Tag(Index),
 }
 
-impl Import {
+impl<'a> Import<'a> {
     pub fn type_(&self, module: &Module) -> types::Extern {
         match self.desc {
             ImportDesc::Func(idx) => types::Extern::Func(module.types[idx as usize].clone()),
             ImportDesc::Table(ref t) => types::Extern::Table(t.clone()),
             ImportDesc::Memory(ref t) => types::Extern::Memory(t.clone()),
             ImportDesc::Global(ref t) => types::Extern::Global(t.clone()),
+            ImportDesc::Tag(idx) => types::Extern::Tag(module.types[idx as usize].clone()),
         }
     }
 }
 
 // Helper function for tests
-impl Module {
+impl<'a> Module<'a> {
     #[cfg(feature = "test")]
-    pub fn empty() -> Module {
+    pub fn empty() -> Module<'a> {
         Module {
             types: Vec::new(),
             funcs: Vec::new(),
             tables: Vec::new(),
             memories: Vec::new(),
             globals: Vec::new(),
+            tags: Vec::new(),
             elems: Vec::new(),
             data: Vec::new(),
             start: None,
             imports: Vec::new(),
             exports: Vec::new(),
+            code: None,
         }
     }
 }
@@ -2,65 +2,168 @@ use crate::ast::*;
 use crate::ops::IntOp;
 use crate::types;
 use crate::values::Value;
-use std::io::Read;
-use std::{i32, i64, io};
+use std::convert::TryInto;
+use std::ops::Range;
+use std::{i32, i64, mem, str};
 
 const MAGIC: u32 = 0x6d736100;
 
 pub const VERSION: u32 = 1;
 
-/// Decode a Web Assembly module from the given `reader`
-pub fn decode<R: Read>(reader: R) -> Result<Module, DecodeError> {
-    Decoder {
-        reader: reader,
-        pos: 0,
+/// Type and size of atomic accesses, in the order used by each group of
+/// atomic opcodes: i32, i64, i32 8u, i32 16u, i64 8u, i64 16u, i64 32u.
+const ATOMIC_VARIANTS: [(types::Value, Option<u32>); 7] = [
+    (types::Value::Int(types::Int::I32), None),
+    (types::Value::Int(types::Int::I64), None),
+    (types::Value::Int(types::Int::I32), Some(8)),
+    (types::Value::Int(types::Int::I32), Some(16)),
+    (types::Value::Int(types::Int::I64), Some(8)),
+    (types::Value::Int(types::Int::I64), Some(16)),
+    (types::Value::Int(types::Int::I64), Some(32)),
+];
+
+/// Decode a Web Assembly module from the given `bytes`
+pub fn decode(bytes: &[u8]) -> Result<Module<'_>, DecodeError> {
+    Decoder::new(bytes).read_module()
+}
+
+/// Decode a single instruction without structured control flow, returning it
+/// with the number of bytes it takes
+pub fn decode_instr(bytes: &[u8]) -> Result<(Instr, usize), DecodeError> {
+    let mut decoder = Decoder::new(bytes);
+    match decoder.read_meta_instr()? {
+        MetaInstr::Instr(instr) => Ok((instr, decoder.pos)),
+        MetaInstr::PseudoInstr(_) => Err(DecodeError::MalformedBinary),
+    }
+}
+
+/// Helpers converting modules to the compact format, which is only produced
+/// ahead of time by the watt-compact tool
+#[cfg(feature = "test")]
+pub mod compact {
+    use super::*;
+
+    /// Byte ranges of the instructions of every function body
+    pub type CodeRanges = Vec<Vec<Range<usize>>>;
+
+    /// Decode a module, also returning for every function the byte range of each
+    /// instruction of its body, in a pre-order traversal of the body
+    pub fn decode_with_ranges(bytes: &[u8]) -> Result<(Module<'_>, CodeRanges), DecodeError> {
+        let mut decoder = Decoder::new(bytes);
+        decoder.code_ranges = Some(Vec::new());
+        let module = decoder.read_module()?;
+        Ok((module, decoder.code_ranges.unwrap()))
+    }
+
+    /// Copy a module without its custom sections, such as names and debug info,
+    /// and with empty function bodies
+    pub fn strip_bodies(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut decoder = Decoder::new(bytes);
+        if decoder.read_u32()? != MAGIC || decoder.read_u32()? != VERSION {
+            return Err(DecodeError::MalformedBinary);
+        }
+
+        let mut stripped = bytes[..decoder.pos].to_vec();
+        while decoder.pos < bytes.len() {
+            let start = decoder.pos;
+            let id = decoder.read_byte()?;
+            let size = decoder.read_vu32()?;
+            let section = decoder.read_bytes(size as usize)?;
+            match id {
+                0 => {}
+                10 => {
+                    // no locals followed by `end`
+                    const EMPTY_BODY: [u8; 3] = [2, 0, 0x0b];
+                    let count = Decoder::new(section).read_vu32()?;
+                    let mut code = Vec::new();
+                    write_vu32(&mut code, count);
+                    for _ in 0..count {
+                        code.extend_from_slice(&EMPTY_BODY);
+                    }
+                    stripped.push(id);
+                    write_vu32(&mut stripped, code.len() as u32);
+                    stripped.extend_from_slice(&code);
+                }
+                _ => stripped.extend_from_slice(&bytes[start..decoder.pos]),
+            }
+        }
+        Ok(stripped)
+    }
+
+    /// Append an unsigned LEB128 integer to `bytes`
+    pub fn write_vu32(bytes: &mut Vec<u8>, mut n: u32) {
+        loop {
+            let b = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                bytes.push(b);
+                return;
+            }
+            bytes.push(b | 0x80);
+        }
     }
-    .read_module()
 }
 
 #[derive(Debug)]
 pub enum DecodeError {
-    Io(io::Error),
+    UnexpectedEof,
     MalformedBinary,
 }
 
-impl From<io::Error> for DecodeError {
-    fn from(e: io::Error) -> Self {
-        DecodeError::Io(e)
-    }
-}
-
 type DecodeResult<T> = Result<T, DecodeError>;
 
-struct Decoder<R: Read> {
-    reader: R,
+/// Decoder reading directly from an in-memory module. Byte strings (names,
+/// data segments, skipped sections) are sliced out of the input rather than
+/// read byte by byte.
+struct Decoder<'a> {
+    bytes: &'a [u8],
     pos: usize,
+    /// Byte ranges of the instructions of each function body, if requested
+    code_ranges: Option<Vec<Vec<Range<usize>>>>,
+    /// Byte ranges of the instructions of the expression being read
+    ranges: Vec<Range<usize>>,
 }
 
-impl<R: Read> Decoder<R> {
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Decoder {
+            bytes,
+            pos: 0,
+            code_ranges: None,
+            ranges: Vec::new(),
+        }
+    }
+
     fn read_byte(&mut self) -> DecodeResult<u8> {
-        let mut buf = [0];
-        self.reader.read_exact(&mut buf)?;
-        self.pos += 1;
-        Ok(buf[0])
+        match self.bytes.get(self.pos) {
+            Some(&b) => {
+                self.pos += 1;
+                Ok(b)
+            }
+            None => Err(DecodeError::UnexpectedEof),
+        }
     }
 
-    fn read_u16(&mut self) -> DecodeResult<u16> {
-        let lo = self.read_byte()?;
-        let hi = self.read_byte()?;
-        Ok((hi as u16) << 8 | lo as u16)
+    /// Borrow the next `n` bytes of the input
+    fn read_bytes(&mut self, n: usize) -> DecodeResult<&'a [u8]> {
+        let bytes = self.bytes;
+        match bytes.get(self.pos..).and_then(|rest| rest.get(..n)) {
+            Some(slice) => {
+                self.pos += n;
+                Ok(slice)
+            }
+            None => Err(DecodeError::UnexpectedEof),
+        }
     }
 
     fn read_u32(&mut self) -> DecodeResult<u32> {
-        let lo = self.read_u16()?;
-        let hi = self.read_u16()?;
-        Ok((hi as u32) << 16 | lo as u32)
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
     }
 
     fn read_u64(&mut self) -> DecodeResult<u64> {
-        let lo = self.read_u32()?;
-        let hi = self.read_u32()?;
-        Ok((hi as u64) << 32 | lo as u64)
+        let bytes = self.read_bytes(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
     }
 
     fn read_bool(&mut self) -> DecodeResult<bool> {
@@ -165,7 +268,7 @@ impl<R: Read> Decoder<R> {
 
     fn read_vec<T, F>(&mut self, read_elem: F) -> DecodeResult<Vec<T>>
     where
-        F: Fn(&mut Decoder<R>) -> DecodeResult<T>,
+        F: Fn(&mut Decoder<'a>) -> DecodeResult<T>,
     {
         let n = self.read_vu32()?;
         let mut vec = Vec::with_capacity(n as usize);
@@ -175,12 +278,15 @@ impl<R: Read> Decoder<R> {
         Ok(vec)
     }
 
-    fn read_name(&mut self) -> DecodeResult<String> {
-        let vec = self.read_vec(Decoder::read_byte)?;
-        match String::from_utf8(vec) {
-            Ok(s) => Ok(s),
-            Err(_) => Err(DecodeError::MalformedBinary),
-        }
+    /// Borrow a length-prefixed byte string
+    fn read_byte_vec(&mut self) -> DecodeResult<&'a [u8]> {
+        let n = self.read_vu32()?;
+        self.read_bytes(n as usize)
+    }
+
+    fn read_name(&mut self) -> DecodeResult<&'a str> {
+        let bytes = self.read_byte_vec()?;
+        str::from_utf8(bytes).map_err(|_| DecodeError::MalformedBinary)
     }
 
     fn read_value_type(&mut self) -> DecodeResult<types::Value> {
@@ -207,6 +313,10 @@ impl<R: Read> Decoder<R> {
 
     fn read_limits(&mut self) -> DecodeResult<types::Limits> {
         let has_max = self.read_bool()?;
+        self.read_limits_bounds(has_max)
+    }
+
+    fn read_limits_bounds(&mut self, has_max: bool) -> DecodeResult<types::Limits> {
         let min = self.read_vu32()?;
         let max = if has_max {
             Some(self.read_vu32()?)
@@ -218,8 +328,16 @@ impl<R: Read> Decoder<R> {
     }
 
     fn read_memory_type(&mut self) -> DecodeResult<types::Memory> {
+        // Shared memories must declare a maximum size
+        let (has_max, shared) = match self.read_byte()? {
+            0x00 => (false, false),
+            0x01 => (true, false),
+            0x03 => (true, true),
+            _ => return Err(DecodeError::MalformedBinary),
+        };
         Ok(types::Memory {
-            limits: self.read_limits()?,
+            limits: self.read_limits_bounds(has_max)?,
+            shared,
         })
     }
 
@@ -247,7 +365,35 @@ impl<R: Read> Decoder<R> {
         self.read_vu32()
     }
 
+    fn read_catch(&mut self) -> DecodeResult<Catch> {
+        match self.read_byte()? {
+            0x00 => Ok(Catch::Tag(self.read_index()?, self.read_index()?)),
+            0x01 => Ok(Catch::TagRef(self.read_index()?, self.read_index()?)),
+            0x02 => Ok(Catch::All(self.read_index()?)),
+            0x03 => Ok(Catch::AllRef(self.read_index()?)),
+            _ => Err(DecodeError::MalformedBinary),
+        }
+    }
+
     fn read_meta_instr(&mut self) -> DecodeResult<MetaInstr> {
+        if self.code_ranges.is_none() {
+            return self.read_meta_instr_inner();
+        }
+
+        // reserve the range before reading nested instructions
+        let index = self.ranges.len();
+        self.ranges.push(self.pos..self.pos);
+        let meta_instr = self.read_meta_instr_inner()?;
+        match meta_instr {
+            MetaInstr::Instr(_) => self.ranges[index].end = self.pos,
+            MetaInstr::PseudoInstr(_) => {
+                self.ranges.pop();
+            }
+        }
+        Ok(meta_instr)
+    }
+
+    fn read_meta_instr_inner(&mut self) -> DecodeResult<MetaInstr> {
         use crate::ast::Instr::*;
         use crate::types::Float::*;
         use crate::types::Int::*;
@@ -280,8 +426,17 @@ impl<R: Read> Decoder<R> {
             }
 
             0x05 => return Ok(MetaInstr::PseudoInstr(PseudoInstr::Else)),
+            0x08 => Throw(self.read_index()?),
+            0x0a => ThrowRef,
             0x0b => return Ok(MetaInstr::PseudoInstr(PseudoInstr::End)),
 
+            0x1f => {
+                let block_type = self.read_block_type()?;
+                let catches = self.read_vec(Decoder::read_catch)?;
+                let instrs = self.read_instr_block()?;
+                TryTable(block_type, catches, instrs)
+            }
+
             0x0c => Br(self.read_index()?),
             0x0d => BrIf(self.read_index()?),
             0x0e => BrTable(self.read_vec(Decoder::read_index)?, self.read_index()?),
@@ -295,6 +450,14 @@ impl<R: Read> Decoder<R> {
                 }
                 CallIndirect(index)
             }
+            0x12 => ReturnCall(self.read_index()?),
+            0x13 => {
+                let index = self.read_index()?;
+                if self.read_byte()? != 0 {
+                    return Err(DecodeError::MalformedBinary);
+                }
+                ReturnCallIndirect(index)
+            }
 
             0x1a => Drop_,
             0x1b => Select,
@@ -305,30 +468,30 @@ impl<R: Read> Decoder<R> {
             0x23 => GetGlobal(self.read_index()?),
             0x24 => SetGlobal(self.read_index()?),
 
-            0x28 => self.read_load_op(Int(I32), None)?,
-            0x29 => self.read_load_op(Int(I64), None)?,
-            0x2a => self.read_load_op(Float(F32), None)?,
-            0x2b => self.read_load_op(Float(F64), None)?,
-            0x2c => self.read_load_op(Int(I32), Some((8, true)))?,
-            0x2d => self.read_load_op(Int(I32), Some((8, false)))?,
-            0x2e => self.read_load_op(Int(I32), Some((16, true)))?,
-            0x2f => self.read_load_op(Int(I32), Some((16, false)))?,
-            0x30 => self.read_load_op(Int(I64), Some((8, true)))?,
-            0x31 => self.read_load_op(Int(I64), Some((8, false)))?,
-            0x32 => self.read_load_op(Int(I64), Some((16, true)))?,
-            0x33 => self.read_load_op(Int(I64), Some((16, false)))?,
-            0x34 => self.read_load_op(Int(I64), Some((32, true)))?,
-            0x35 => self.read_load_op(Int(I64), Some((32, false)))?,
-
-            0x36 => self.read_store_op(Int(I32), None)?,
-            0x37 => self.read_store_op(Int(I64), None)?,
-            0x38 => self.read_store_op(Float(F32), None)?,
-            0x39 => self.read_store_op(Float(F64), None)?,
-            0x3a => self.read_store_op(Int(I32), Some(8))?,
-            0x3b => self.read_store_op(Int(I32), Some(16))?,
-            0x3c => self.read_store_op(Int(I64), Some(8))?,
-            0x3d => self.read_store_op(Int(I64), Some(16))?,
-            0x3e => self.read_store_op(Int(I64), Some(32))?,
+            0x28 => self.read_load_op(Int(I32), None, LoadKind::I32)?,
+            0x29 => self.read_load_op(Int(I64), None, LoadKind::I64)?,
+            0x2a => self.read_load_op(Float(F32), None, LoadKind::I32)?,
+            0x2b => self.read_load_op(Float(F64), None, LoadKind::I64)?,
+            0x2c => self.read_load_op(Int(I32), Some((8, true)), LoadKind::I32S8)?,
+            0x2d => self.read_load_op(Int(I32), Some((8, false)), LoadKind::I32U8)?,
+            0x2e => self.read_load_op(Int(I32), Some((16, true)), LoadKind::I32S16)?,
+            0x2f => self.read_load_op(Int(I32), Some((16, false)), LoadKind::I32U16)?,
+            0x30 => self.read_load_op(Int(I64), Some((8, true)), LoadKind::I64S8)?,
+            0x31 => self.read_load_op(Int(I64), Some((8, false)), LoadKind::I64U8)?,
+            0x32 => self.read_load_op(Int(I64), Some((16, true)), LoadKind::I64S16)?,
+            0x33 => self.read_load_op(Int(I64), Some((16, false)), LoadKind::I64U16)?,
+            0x34 => self.read_load_op(Int(I64), Some((32, true)), LoadKind::I64S32)?,
+            0x35 => self.read_load_op(Int(I64), Some((32, false)), LoadKind::I64U32)?,
+
+            0x36 => self.read_store_op(Int(I32), None, StoreKind::Bits32)?,
+            0x37 => self.read_store_op(Int(I64), None, StoreKind::Bits64)?,
+            0x38 => self.read_store_op(Float(F32), None, StoreKind::Bits32)?,
+            0x39 => self.read_store_op(Float(F64), None, StoreKind::Bits64)?,
+            0x3a => self.read_store_op(Int(I32), Some(8), StoreKind::Bits8)?,
+            0x3b => self.read_store_op(Int(I32), Some(16), StoreKind::Bits16)?,
+            0x3c => self.read_store_op(Int(I64), Some(8), StoreKind::Bits8)?,
+            0x3d => self.read_store_op(Int(I64), Some(16), StoreKind::Bits16)?,
+            0x3e => self.read_store_op(Int(I64), Some(32), StoreKind::Bits32)?,
 
             0x3f => {
                 if self.read_byte()? != 0 {
@@ -557,10 +720,291 @@ impl<R: Read> Decoder<R> {
                 to: Float(F64),
             }),
 
+            0xfd => self.read_simd_instr()?,
+            0xfe => self.read_atomic_instr()?,
+
             _ => return Err(DecodeError::MalformedBinary),
         }))
     }
 
+    /// Read an instruction following the 0xfd vector prefix
+    fn read_simd_instr(&mut self) -> DecodeResult<Instr> {
+        use crate::ast::Instr::*;
+        use crate::ast::Shape::*;
+        use crate::ast::{VIBinOp as IB, VIUnOp as IU};
+
+        let opcode = self.read_vu32()?;
+
+        Ok(match opcode {
+            0x00 => self.read_vload_op(VLoadKind::Full)?,
+            0x01 => self.read_vload_op(VLoadKind::Extend(I16x8, true))?,
+            0x02 => self.read_vload_op(VLoadKind::Extend(I16x8, false))?,
+            0x03 => self.read_vload_op(VLoadKind::Extend(I32x4, true))?,
+            0x04 => self.read_vload_op(VLoadKind::Extend(I32x4, false))?,
+            0x05 => self.read_vload_op(VLoadKind::Extend(I64x2, true))?,
+            0x06 => self.read_vload_op(VLoadKind::Extend(I64x2, false))?,
+            0x07 => self.read_vload_op(VLoadKind::Splat(I8x16))?,
+            0x08 => self.read_vload_op(VLoadKind::Splat(I16x8))?,
+            0x09 => self.read_vload_op(VLoadKind::Splat(I32x4))?,
+            0x0a => self.read_vload_op(VLoadKind::Splat(I64x2))?,
+            0x0b => self.read_vstore_op(VStoreKind::Full)?,
+
+            0x0c => {
+                let lo = self.read_u64()?;
+                let hi = self.read_u64()?;
+                Const(Value::V128((hi as u128) << 64 | lo as u128))
+            }
+            0x0d => {
+                let mut lanes = [0; 16];
+                for lane in lanes.iter_mut() {
+                    *lane = self.read_byte()?;
+                }
+                Shuffle(lanes)
+            }
+            0x0e => Swizzle,
+
+            0x0f => Splat(I8x16),
+            0x10 => Splat(I16x8),
+            0x11 => Splat(I32x4),
+            0x12 => Splat(I64x2),
+            0x13 => Splat(F32x4),
+            0x14 => Splat(F64x2),
+
+            0x15 => ExtractLane(I8x16, self.read_byte()?, true),
+            0x16 => ExtractLane(I8x16, self.read_byte()?, false),
+            0x17 => ReplaceLane(I8x16, self.read_byte()?),
+            0x18 => ExtractLane(I16x8, self.read_byte()?, true),
+            0x19 => ExtractLane(I16x8, self.read_byte()?, false),
+            0x1a => ReplaceLane(I16x8, self.read_byte()?),
+            0x1b => ExtractLane(I32x4, self.read_byte()?, false),
+            0x1c => ReplaceLane(I32x4, self.read_byte()?),
+            0x1d => ExtractLane(I64x2, self.read_byte()?, false),
+            0x1e => ReplaceLane(I64x2, self.read_byte()?),
+            0x1f => ExtractLane(F32x4, self.read_byte()?, false),
+            0x20 => ReplaceLane(F32x4, self.read_byte()?),
+            0x21 => ExtractLane(F64x2, self.read_byte()?, false),
+            0x22 => ReplaceLane(F64x2, self.read_byte()?),
+
+            0x23 => VIRel(I8x16, IRelOp::Eq_),
+            0x24 => VIRel(I8x16, IRelOp::Ne),
+            0x25 => VIRel(I8x16, IRelOp::LtS),
+            0x26 => VIRel(I8x16, IRelOp::LtU),
+            0x27 => VIRel(I8x16, IRelOp::GtS),
+            0x28 => VIRel(I8x16, IRelOp::GtU),
+            0x29 => VIRel(I8x16, IRelOp::LeS),
+            0x2a => VIRel(I8x16, IRelOp::LeU),
+            0x2b => VIRel(I8x16, IRelOp::GeS),
+            0x2c => VIRel(I8x16, IRelOp::GeU),
+
+            0x2d => VIRel(I16x8, IRelOp::Eq_),
+            0x2e => VIRel(I16x8, IRelOp::Ne),
+            0x2f => VIRel(I16x8, IRelOp::LtS),
+            0x30 => VIRel(I16x8, IRelOp::LtU),
+            0x31 => VIRel(I16x8, IRelOp::GtS),
+            0x32 => VIRel(I16x8, IRelOp::GtU),
+            0x33 => VIRel(I16x8, IRelOp::LeS),
+            0x34 => VIRel(I16x8, IRelOp::LeU),
+            0x35 => VIRel(I16x8, IRelOp::GeS),
+            0x36 => VIRel(I16x8, IRelOp::GeU),
+
+            0x37 => VIRel(I32x4, IRelOp::Eq_),
+            0x38 => VIRel(I32x4, IRelOp::Ne),
+            0x39 => VIRel(I32x4, IRelOp::LtS),
+            0x3a => VIRel(I32x4, IRelOp::LtU),
+            0x3b => VIRel(I32x4, IRelOp::GtS),
+            0x3c => VIRel(I32x4, IRelOp::GtU),
+            0x3d => VIRel(I32x4, IRelOp::LeS),
+            0x3e => VIRel(I32x4, IRelOp::LeU),
+            0x3f => VIRel(I32x4, IRelOp::GeS),
+            0x40 => VIRel(I32x4, IRelOp::GeU),
+
+            0x41 => VFRel(F32x4, FRelOp::Eq_),
+            0x42 => VFRel(F32x4, FRelOp::Ne),
+            0x43 => VFRel(F32x4, FRelOp::Lt),
+            0x44 => VFRel(F32x4, FRelOp::Gt),
+            0x45 => VFRel(F32x4, FRelOp::Le),
+            0x46 => VFRel(F32x4, FRelOp::Ge),
+
+            0x47 => VFRel(F64x2, FRelOp::Eq_),
+            0x48 => VFRel(F64x2, FRelOp::Ne),
+            0x49 => VFRel(F64x2, FRelOp::Lt),
+            0x4a => VFRel(F64x2, FRelOp::Gt),
+            0x4b => VFRel(F64x2, FRelOp::Le),
+            0x4c => VFRel(F64x2, FRelOp::Ge),
+
+            0x4d => VNot,
+            0x4e => VBit(VBitOp::And),
+            0x4f => VBit(VBitOp::AndNot),
+            0x50 => VBit(VBitOp::Or),
+            0x51 => VBit(VBitOp::Xor),
+            0x52 => Bitselect,
+            0x53 => AnyTrue,
+
+            0x54 => self.read_vload_lane_op(I8x16)?,
+            0x55 => self.read_vload_lane_op(I16x8)?,
+            0x56 => self.read_vload_lane_op(I32x4)?,
+            0x57 => self.read_vload_lane_op(I64x2)?,
+            0x58 => self.read_vstore_lane_op(I8x16)?,
+            0x59 => self.read_vstore_lane_op(I16x8)?,
+            0x5a => self.read_vstore_lane_op(I32x4)?,
+            0x5b => self.read_vstore_lane_op(I64x2)?,
+            0x5c => self.read_vload_op(VLoadKind::Zero(I32x4))?,
+            0x5d => self.read_vload_op(VLoadKind::Zero(I64x2))?,
+
+            0x5e => VConvert(VConvertOp::F32x4DemoteF64x2Zero),
+            0x5f => VConvert(VConvertOp::F64x2PromoteLowF32x4),
+
+            0x60 => VIUnary(I8x16, IU::Abs),
+            0x61 => VIUnary(I8x16, IU::Neg),
+            0x62 => VIUnary(I8x16, IU::Popcnt),
+            0x63 => AllTrue(I8x16),
+            0x64 => Bitmask(I8x16),
+            0x65 => VIBin(I8x16, IB::Narrow(true)),
+            0x66 => VIBin(I8x16, IB::Narrow(false)),
+            0x67 => VFUnary(F32x4, FUnOp::Ceil),
+            0x68 => VFUnary(F32x4, FUnOp::Floor),
+            0x69 => VFUnary(F32x4, FUnOp::Trunc),
+            0x6a => VFUnary(F32x4, FUnOp::Nearest),
+            0x6b => VShift(I8x16, VShiftOp::Shl),
+            0x6c => VShift(I8x16, VShiftOp::ShrS),
+            0x6d => VShift(I8x16, VShiftOp::ShrU),
+            0x6e => VIBin(I8x16, IB::Add),
+            0x6f => VIBin(I8x16, IB::AddSat(true)),
+            0x70 => VIBin(I8x16, IB::AddSat(false)),
+            0x71 => VIBin(I8x16, IB::Sub),
+            0x72 => VIBin(I8x16, IB::SubSat(true)),
+            0x73 => VIBin(I8x16, IB::SubSat(false)),
+            0x74 => VFUnary(F64x2, FUnOp::Ceil),
+            0x75 => VFUnary(F64x2, FUnOp::Floor),
+            0x76 => VIBin(I8x16, IB::Min(true)),
+            0x77 => VIBin(I8x16, IB::Min(false)),
+            0x78 => VIBin(I8x16, IB::Max(true)),
+            0x79 => VIBin(I8x16, IB::Max(false)),
+            0x7a => VFUnary(F64x2, FUnOp::Trunc),
+            0x7b => VIBin(I8x16, IB::AvgrU),
+            0x7c => VIUnary(I16x8, IU::ExtAddPairwise(true)),
+            0x7d => VIUnary(I16x8, IU::ExtAddPairwise(false)),
+            0x7e => VIUnary(I32x4, IU::ExtAddPairwise(true)),
+            0x7f => VIUnary(I32x4, IU::ExtAddPairwise(false)),
+
+            0x80 => VIUnary(I16x8, IU::Abs),
+            0x81 => VIUnary(I16x8, IU::Neg),
+            0x82 => VIBin(I16x8, IB::Q15MulrSatS),
+            0x83 => AllTrue(I16x8),
+            0x84 => Bitmask(I16x8),
+            0x85 => VIBin(I16x8, IB::Narrow(true)),
+            0x86 => VIBin(I16x8, IB::Narrow(false)),
+            0x87 => VIUnary(I16x8, IU::ExtendLow(true)),
+            0x88 => VIUnary(I16x8, IU::ExtendHigh(true)),
+            0x89 => VIUnary(I16x8, IU::ExtendLow(false)),
+            0x8a => VIUnary(I16x8, IU::ExtendHigh(false)),
+            0x8b => VShift(I16x8, VShiftOp::Shl),
+            0x8c => VShift(I16x8, VShiftOp::ShrS),
+            0x8d => VShift(I16x8, VShiftOp::ShrU),
+            0x8e => VIBin(I16x8, IB::Add),
+            0x8f => VIBin(I16x8, IB::AddSat(true)),
+            0x90 => VIBin(I16x8, IB::AddSat(false)),
+            0x91 => VIBin(I16x8, IB::Sub),
+            0x92 => VIBin(I16x8, IB::SubSat(true)),
+            0x93 => VIBin(I16x8, IB::SubSat(false)),
+            0x94 => VFUnary(F64x2, FUnOp::Nearest),
+            0x95 => VIBin(I16x8, IB::Mul),
+            0x96 => VIBin(I16x8, IB::Min(true)),
+            0x97 => VIBin(I16x8, IB::Min(false)),
+            0x98 => VIBin(I16x8, IB::Max(true)),
+            0x99 => VIBin(I16x8, IB::Max(false)),
+            0x9b => VIBin(I16x8, IB::AvgrU),
+            0x9c => VIBin(I16x8, IB::ExtMulLow(true)),
+            0x9d => VIBin(I16x8, IB::ExtMulHigh(true)),
+            0x9e => VIBin(I16x8, IB::ExtMulLow(false)),
+            0x9f => VIBin(I16x8, IB::ExtMulHigh(false)),
+
+            0xa0 => VIUnary(I32x4, IU::Abs),
+            0xa1 => VIUnary(I32x4, IU::Neg),
+            0xa3 => AllTrue(I32x4),
+            0xa4 => Bitmask(I32x4),
+            0xa7 => VIUnary(I32x4, IU::ExtendLow(true)),
+            0xa8 => VIUnary(I32x4, IU::ExtendHigh(true)),
+            0xa9 => VIUnary(I32x4, IU::ExtendLow(false)),
+            0xaa => VIUnary(I32x4, IU::ExtendHigh(false)),
+            0xab => VShift(I32x4, VShiftOp::Shl),
+            0xac => VShift(I32x4, VShiftOp::ShrS),
+            0xad => VShift(I32x4, VShiftOp::ShrU),
+            0xae => VIBin(I32x4, IB::Add),
+            0xb1 => VIBin(I32x4, IB::Sub),
+            0xb5 => VIBin(I32x4, IB::Mul),
+            0xb6 => VIBin(I32x4, IB::Min(true)),
+            0xb7 => VIBin(I32x4, IB::Min(false)),
+            0xb8 => VIBin(I32x4, IB::Max(true)),
+            0xb9 => VIBin(I32x4, IB::Max(false)),
+            0xba => VIBin(I32x4, IB::Dot),
+            0xbc => VIBin(I32x4, IB::ExtMulLow(true)),
+            0xbd => VIBin(I32x4, IB::ExtMulHigh(true)),
+            0xbe => VIBin(I32x4, IB::ExtMulLow(false)),
+            0xbf => VIBin(I32x4, IB::ExtMulHigh(false)),
+
+            0xc0 => VIUnary(I64x2, IU::Abs),
+            0xc1 => VIUnary(I64x2, IU::Neg),
+            0xc3 => AllTrue(I64x2),
+            0xc4 => Bitmask(I64x2),
+            0xc7 => VIUnary(I64x2, IU::ExtendLow(true)),
+            0xc8 => VIUnary(I64x2, IU::ExtendHigh(true)),
+            0xc9 => VIUnary(I64x2, IU::ExtendLow(false)),
+            0xca => VIUnary(I64x2, IU::ExtendHigh(false)),
+            0xcb => VShift(I64x2, VShiftOp::Shl),
+            0xcc => VShift(I64x2, VShiftOp::ShrS),
+            0xcd => VShift(I64x2, VShiftOp::ShrU),
+            0xce => VIBin(I64x2, IB::Add),
+            0xd1 => VIBin(I64x2, IB::Sub),
+            0xd5 => VIBin(I64x2, IB::Mul),
+            0xd6 => VIRel(I64x2, IRelOp::Eq_),
+            0xd7 => VIRel(I64x2, IRelOp::Ne),
+            0xd8 => VIRel(I64x2, IRelOp::LtS),
+            0xd9 => VIRel(I64x2, IRelOp::GtS),
+            0xda => VIRel(I64x2, IRelOp::LeS),
+            0xdb => VIRel(I64x2, IRelOp::GeS),
+            0xdc => VIBin(I64x2, IB::ExtMulLow(true)),
+            0xdd => VIBin(I64x2, IB::ExtMulHigh(true)),
+            0xde => VIBin(I64x2, IB::ExtMulLow(false)),
+            0xdf => VIBin(I64x2, IB::ExtMulHigh(false)),
+
+            0xe0 => VFUnary(F32x4, FUnOp::Abs),
+            0xe1 => VFUnary(F32x4, FUnOp::Neg),
+            0xe3 => VFUnary(F32x4, FUnOp::Sqrt),
+            0xe4 => VFBin(F32x4, VFBinOp::Add),
+            0xe5 => VFBin(F32x4, VFBinOp::Sub),
+            0xe6 => VFBin(F32x4, VFBinOp::Mul),
+            0xe7 => VFBin(F32x4, VFBinOp::Div),
+            0xe8 => VFBin(F32x4, VFBinOp::Min),
+            0xe9 => VFBin(F32x4, VFBinOp::Max),
+            0xea => VFBin(F32x4, VFBinOp::PMin),
+            0xeb => VFBin(F32x4, VFBinOp::PMax),
+
+            0xec => VFUnary(F64x2, FUnOp::Abs),
+            0xed => VFUnary(F64x2, FUnOp::Neg),
+            0xef => VFUnary(F64x2, FUnOp::Sqrt),
+            0xf0 => VFBin(F64x2, VFBinOp::Add),
+            0xf1 => VFBin(F64x2, VFBinOp::Sub),
+            0xf2 => VFBin(F64x2, VFBinOp::Mul),
+            0xf3 => VFBin(F64x2, VFBinOp::Div),
+            0xf4 => VFBin(F64x2, VFBinOp::Min),
+            0xf5 => VFBin(F64x2, VFBinOp::Max),
+            0xf6 => VFBin(F64x2, VFBinOp::PMin),
+            0xf7 => VFBin(F64x2, VFBinOp::PMax),
+
+            0xf8 => VConvert(VConvertOp::I32x4TruncSatF32x4(true)),
+            0xf9 => VConvert(VConvertOp::I32x4TruncSatF32x4(false)),
+            0xfa => VConvert(VConvertOp::F32x4ConvertI32x4(true)),
+            0xfb => VConvert(VConvertOp::F32x4ConvertI32x4(false)),
+            0xfc => VConvert(VConvertOp::I32x4TruncSatF64x2Zero(true)),
+            0xfd => VConvert(VConvertOp::I32x4TruncSatF64x2Zero(false)),
+            0xfe => VConvert(VConvertOp::F64x2ConvertLowI32x4(true)),
+            0xff => VConvert(VConvertOp::F64x2ConvertLowI32x4(false)),
+
+            _ => return Err(DecodeError::MalformedBinary),
+        })
+    }
+
     fn read_instr_block_with_delim(&mut self) -> DecodeResult<(Vec<Instr>, PseudoInstr)> {
         let mut res = Vec::new();
         loop {
@@ -587,25 +1031,138 @@ impl<R: Read> Decoder<R> {
         &mut self,
         type_: types::Value,
         opt: Option<(u32, bool)>,
+        kind: LoadKind,
     ) -> DecodeResult<Instr> {
         let align = self.read_vu32()?;
         let offset = self.read_vu32()?;
-        Ok(Instr::Load(LoadOp {
+        let op = LoadOp {
             align,
             offset,
             type_,
             opt,
-        }))
+        };
+        Ok(Instr::Load(op, kind))
     }
 
-    fn read_store_op(&mut self, type_: types::Value, opt: Option<u32>) -> DecodeResult<Instr> {
+    fn read_store_op(
+        &mut self,
+        type_: types::Value,
+        opt: Option<u32>,
+        kind: StoreKind,
+    ) -> DecodeResult<Instr> {
         let align = self.read_vu32()?;
         let offset = self.read_vu32()?;
-        Ok(Instr::Store(StoreOp {
+        let op = StoreOp {
             align,
             offset,
             type_,
             opt,
+        };
+        Ok(Instr::Store(op, kind))
+    }
+
+    fn read_atomic_op(&mut self, type_: types::Value, opt: Option<u32>) -> DecodeResult<AtomicOp> {
+        let align = self.read_vu32()?;
+        let offset = self.read_vu32()?;
+        Ok(AtomicOp {
+            align,
+            offset,
+            type_,
+            opt,
+        })
+    }
+
+    /// Read one of the seven variants of an atomic read-modify-write operation,
+    /// or of compare-exchange if `op` is `None`.
+    fn read_atomic_rmw(&mut self, variant: u32, op: Option<AtomicRmwOp>) -> DecodeResult<Instr> {
+        let (type_, opt) = ATOMIC_VARIANTS[variant as usize];
+        let memop = self.read_atomic_op(type_, opt)?;
+        Ok(match op {
+            Some(op) => Instr::AtomicRmw(op, memop),
+            None => Instr::AtomicCmpxchg(memop),
+        })
+    }
+
+    /// Read an instruction following the 0xfe atomic prefix
+    fn read_atomic_instr(&mut self) -> DecodeResult<Instr> {
+        use crate::ast::AtomicRmwOp::*;
+        use crate::ast::Instr::*;
+        use crate::types::Int::*;
+        use crate::types::Value::*;
+
+        let opcode = self.read_vu32()?;
+
+        Ok(match opcode {
+            0x00 => AtomicNotify(self.read_atomic_op(Int(I32), None)?),
+            0x01 => AtomicWait(self.read_atomic_op(Int(I32), None)?),
+            0x02 => AtomicWait(self.read_atomic_op(Int(I64), None)?),
+            0x03 => {
+                if self.read_byte()? != 0 {
+                    return Err(DecodeError::MalformedBinary);
+                }
+                AtomicFence
+            }
+
+            0x10..=0x16 => {
+                let (type_, opt) = ATOMIC_VARIANTS[(opcode - 0x10) as usize];
+                AtomicLoad(self.read_atomic_op(type_, opt)?)
+            }
+            0x17..=0x1d => {
+                let (type_, opt) = ATOMIC_VARIANTS[(opcode - 0x17) as usize];
+                AtomicStore(self.read_atomic_op(type_, opt)?)
+            }
+
+            0x1e..=0x24 => self.read_atomic_rmw(opcode - 0x1e, Some(Add))?,
+            0x25..=0x2b => self.read_atomic_rmw(opcode - 0x25, Some(Sub))?,
+            0x2c..=0x32 => self.read_atomic_rmw(opcode - 0x2c, Some(And))?,
+            0x33..=0x39 => self.read_atomic_rmw(opcode - 0x33, Some(Or))?,
+            0x3a..=0x40 => self.read_atomic_rmw(opcode - 0x3a, Some(Xor))?,
+            0x41..=0x47 => self.read_atomic_rmw(opcode - 0x41, Some(Xchg))?,
+            0x48..=0x4e => self.read_atomic_rmw(opcode - 0x48, None)?,
+
+            _ => return Err(DecodeError::MalformedBinary),
+        })
+    }
+
+    fn read_vload_op(&mut self, kind: VLoadKind) -> DecodeResult<Instr> {
+        let align = self.read_vu32()?;
+        let offset = self.read_vu32()?;
+        Ok(Instr::VLoad(VMemOp {
+            align,
+            offset,
+            kind,
+        }))
+    }
+
+    fn read_vstore_op(&mut self, kind: VStoreKind) -> DecodeResult<Instr> {
+        let align = self.read_vu32()?;
+        let offset = self.read_vu32()?;
+        Ok(Instr::VStore(VMemOp {
+            align,
+            offset,
+            kind,
+        }))
+    }
+
+    fn read_vload_lane_op(&mut self, shape: Shape) -> DecodeResult<Instr> {
+        let align = self.read_vu32()?;
+        let offset = self.read_vu32()?;
+        let lane = self.read_byte()?;
+        Ok(Instr::VLoad(VMemOp {
+            align,
+            offset,
+            kind: VLoadKind::Lane(shape, lane),
+        }))
+    }
+
+    fn read_vstore_lane_op(&mut self, shape: Shape) -> DecodeResult<Instr> {
+        let align = self.read_vu32()?;
+        let offset = self.read_vu32()?;
+        let lane = self.read_byte()?;
+        Ok(Instr::VStore(VMemOp {
+            align,
+            offset,
+            kind: VStoreKind::Lane(shape, lane),
         }))
     }
 
@@ -618,11 +1175,7 @@ impl<R: Read> Decoder<R> {
         if nread > size as usize {
             return Err(DecodeError::MalformedBinary);
         }
-        let nskip = size as usize - nread;
-
-        for _ in 0..nskip {
-            let _ = self.read_byte()?;
-        }
+        let _ = self.read_bytes(size as usize - nread)?;
 
         Ok(())
     }
@@ -637,18 +1190,19 @@ impl<R: Read> Decoder<R> {
             0x01 => Ok(ImportDesc::Table(self.read_table_type()?)),
             0x02 => Ok(ImportDesc::Memory(self.read_memory_type()?)),
             0x03 => Ok(ImportDesc::Global(self.read_global_type()?)),
+            0x04 => Ok(ImportDesc::Tag(self.read_tag_type()?)),
             _ => Err(DecodeError::MalformedBinary),
         }
     }
 
-    fn read_import(&mut self) -> DecodeResult<Import> {
+    fn read_import(&mut self) -> DecodeResult<Import<'a>> {
         let module = self.read_name()?;
         let name = self.read_name()?;
         let desc = self.read_import_desc()?;
         Ok(Import { module, name, desc })
     }
 
-    fn read_import_section(&mut self) -> DecodeResult<Vec<Import>> {
+    fn read_import_section(&mut self) -> DecodeResult<Vec<Import<'a>>> {
         self.read_vec(Decoder::read_import)
     }
 
@@ -686,23 +1240,42 @@ impl<R: Read> Decoder<R> {
         self.read_vec(Decoder::read_global)
     }
 
+    fn read_tag_type(&mut self) -> DecodeResult<Index> {
+        // Only exceptions (attribute 0) are defined
+        if self.read_byte()? != 0 {
+            return Err(DecodeError::MalformedBinary);
+        }
+        self.read_index()
+    }
+
+    fn read_tag(&mut self) -> DecodeResult<Tag> {
+        Ok(Tag {
+            type_index: self.read_tag_type()?,
+        })
+    }
+
+    fn read_tag_section(&mut self) -> DecodeResult<Vec<Tag>> {
+        self.read_vec(Decoder::read_tag)
+    }
+
     fn read_export_desc(&mut self) -> DecodeResult<ExportDesc> {
         match self.read_byte()? {
             0x00 => Ok(ExportDesc::Func(self.read_index()?)),
             0x01 => Ok(ExportDesc::Table(self.read_index()?)),
             0x02 => Ok(ExportDesc::Memory(self.read_index()?)),
             0x03 => Ok(ExportDesc::Global(self.read_index()?)),
+            0x04 => Ok(ExportDesc::Tag(self.read_index()?)),
             _ => Err(DecodeError::MalformedBinary),
         }
     }
 
-    fn read_export(&mut self) -> DecodeResult<Export> {
+    fn read_export(&mut self) -> DecodeResult<Export<'a>> {
         let name = self.read_name()?;
         let desc = self.read_export_desc()?;
         Ok(Export { name, desc })
     }
 
-    fn read_export_section(&mut self) -> DecodeResult<Vec<Export>> {
+    fn read_export_section(&mut self) -> DecodeResult<Vec<Export<'a>>> {
         self.read_vec(Decoder::read_export)
     }
 
@@ -712,7 +1285,7 @@ impl<R: Read> Decoder<R> {
 
     fn read_segment<T, F>(&mut self, read_elem: F) -> DecodeResult<Segment<T>>
     where
-        F: Fn(&mut Decoder<R>) -> DecodeResult<T>,
+        F: Fn(&mut Decoder<'a>) -> DecodeResult<T>,
     {
         let index = self.read_index()?;
         let offset = self.read_expr()?;
@@ -742,7 +1315,11 @@ impl<R: Read> Decoder<R> {
         let _size = self.read_vu32()?;
         // TODO: do not create intermediate vectors just to concatenate them
         let locals = self.read_vec(Decoder::read_locals)?.concat();
+        self.ranges.clear();
         let body = self.read_expr()?;
+        if let Some(code_ranges) = self.code_ranges.as_mut() {
+            code_ranges.push(mem::take(&mut self.ranges));
+        }
         Ok((locals, body))
     }
 
@@ -750,15 +1327,22 @@ impl<R: Read> Decoder<R> {
         self.read_vec(Decoder::read_code)
     }
 
-    fn read_data(&mut self) -> DecodeResult<Segment<u8>> {
-        self.read_segment(Decoder::read_byte)
+    fn read_data(&mut self) -> DecodeResult<Data<'a>> {
+        let index = self.read_index()?;
+        let offset = self.read_expr()?;
+        let init = self.read_byte_vec()?;
+        Ok(Data {
+            index,
+            offset,
+            init,
+        })
     }
 
-    fn read_data_section(&mut self) -> DecodeResult<Vec<Segment<u8>>> {
+    fn read_data_section(&mut self) -> DecodeResult<Vec<Data<'a>>> {
         self.read_vec(Decoder::read_data)
     }
 
-    fn read_module(&mut self) -> DecodeResult<Module> {
+    fn read_module(&mut self) -> DecodeResult<Module<'a>> {
         if self.read_u32()? != MAGIC {
             return Err(DecodeError::MalformedBinary);
         }
@@ -772,6 +1356,7 @@ impl<R: Read> Decoder<R> {
         let mut tables = Vec::new();
         let mut memories = Vec::new();
         let mut globals = Vec::new();
+        let mut tags = Vec::new();
         let mut exports = Vec::new();
         let mut start = None;
         let mut elems = Vec::new();
@@ -782,29 +1367,31 @@ impl<R: Read> Decoder<R> {
         // id, except for custom section (id=0) that may appear anywhere.
         // TODO: enforce sections ordering and uniqueness
 
-        loop {
-            match self.read_byte() {
-                Err(DecodeError::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
-                Err(e) => return Err(e),
-                Ok(id) => {
-                    let size = self.read_vu32()?;
-
-                    match id {
-                        0 => self.skip_custom_section(size)?, // ignore custom sections
-                        1 => types = self.read_type_section()?,
-                        2 => imports = self.read_import_section()?,
-                        3 => func_types = self.read_func_section()?,
-                        4 => tables = self.read_table_section()?,
-                        5 => memories = self.read_memory_section()?,
-                        6 => globals = self.read_global_section()?,
-                        7 => exports = self.read_export_section()?,
-                        8 => start = self.read_start_section()?,
-                        9 => elems = self.read_elem_section()?,
-                        10 => func_bodies = self.read_code_section()?,
-                        11 => data = self.read_data_section()?,
-                        _ => return Err(DecodeError::MalformedBinary),
-                    }
-                }
+        while self.pos < self.bytes.len() {
+            let id = self.read_byte()?;
+            let size = self.read_vu32()?;
+            let end = self.pos + size as usize;
+
+            match id {
+                0 => self.skip_custom_section(size)?, // ignore custom sections
+                1 => types = self.read_type_section()?,
+                2 => imports = self.read_import_section()?,
+                3 => func_types = self.read_func_section()?,
+                4 => tables = self.read_table_section()?,
+                5 => memories = self.read_memory_section()?,
+                6 => globals = self.read_global_section()?,
+                7 => exports = self.read_export_section()?,
+                8 => start = self.read_start_section()?,
+                9 => elems = self.read_elem_section()?,
+                10 => func_bodies = self.read_code_section()?,
+                11 => data = self.read_data_section()?,
+                13 => tags = self.read_tag_section()?,
+                _ => return Err(DecodeError::MalformedBinary),
+            }
+
+            // each section must span exactly its declared size
+            if self.pos != end {
+                return Err(DecodeError::MalformedBinary);
             }
         }
 
@@ -829,11 +1416,13 @@ impl<R: Read> Decoder<R> {
             tables,
             memories,
             globals,
+            tags,
             elems,
             data,
             start,
             imports,
             exports,
+            code: None,
         })
     }
 }
@@ -856,6 +1445,8 @@ fn decode_value_type(b: u8) -> DecodeResult<types::Value> {
         0x7e => Ok(Value::Int(Int::I64)),
         0x7d => Ok(Value::Float(Float::F32)),
         0x7c => Ok(Value::Float(Float::F64)),
+        0x7b => Ok(Value::V128),
+        0x69 => Ok(Value::ExnRef),
         _ => Err(DecodeError::MalformedBinary),
     }
 }
@@ -0,0 +1,374 @@
+//! Compact module format, produced ahead of time from validated wasm.
+//!
+//! Macro shims can ship their module in this format instead of raw wasm. A
+//! compact module is a header followed by a payload. The header holds:
+//!
+//! - the magic bytes `\0wtc`,
+//! - the version of the format (u32, little-endian),
+//! - a FNV-1a checksum of the payload (u32, little-endian).
+//!
+//! The payload holds the wasm module, stripped of its custom sections such as
+//! names and debug info and with empty function bodies, followed by the
+//! function bodies already validated and lowered to flat code, branch targets
+//! and exception handlers included. Instructions without structured control
+//! flow keep their wasm encoding.
+//!
+//! Modules are fully validated before being compacted, so that an invalid
+//! module is reported when the shim is published rather than when the macro
+//! runs. Loading a compact module checks its version and checksum, and then
+//! only the structure of the lowered code: indices, positions and locals must
+//! be in range, but the bodies are not validated again.
+
+use super::lower::{Code, Handler, Local, Op, Target};
+use super::valid::{self, ModContext};
+use super::{binary, lower};
+use crate::ast::{Catch, IBinOp, Instr, LoadKind, Module, StoreKind};
+use crate::error::E;
+use crate::types;
+use std::convert::TryInto;
+
+const MAGIC: &[u8; 4] = b"\0wtc";
+
+/// Version of the format, to bump whenever its meaning changes
+pub const VERSION: u32 = 2;
+
+const HEADER_SIZE: usize = 12;
+
+#[cfg(feature = "test")]
+pub mod encode;
+
+/// Decode a compact module, rejecting those produced for another version of
+/// the format or corrupted since
+pub fn decode(compact: &[u8]) -> Result<Module<'_>, E> {
+    if compact.len() < HEADER_SIZE || &compact[..4] != MAGIC {
+        return Err(E::DecodeModuleFailed);
+    }
+    let version = u32::from_le_bytes(compact[4..8].try_into().unwrap());
+    if version != VERSION {
+        return Err(E::CompactVersionMismatch(version));
+    }
+    let sum = u32::from_le_bytes(compact[8..12].try_into().unwrap());
+    let payload = &compact[HEADER_SIZE..];
+    if sum != checksum(payload) {
+        return Err(E::CompactChecksumMismatch);
+    }
+
+    let mut reader = Reader {
+        bytes: payload,
+        pos: 0,
+    };
+    let wasm = reader.read_bytes().ok_or(E::DecodeModuleFailed)?;
+    let mut module = binary::decode(wasm).map_err(|_| E::DecodeModuleFailed)?;
+    let ctx = valid::check_module(&module).ok_or(E::InvalidModule)?;
+
+    let mut code = Vec::with_capacity(module.funcs.len());
+    for func in &module.funcs {
+        let type_ = &ctx.types[func.type_index as usize];
+        let func_code = reader.read_code(type_).ok_or(E::DecodeModuleFailed)?;
+        check_code(&ctx, &func_code).ok_or(E::InvalidModule)?;
+        code.push(func_code);
+    }
+    if reader.pos != payload.len() {
+        return Err(E::DecodeModuleFailed);
+    }
+    module.code = Some(code);
+    Ok(module)
+}
+
+/// 32-bit FNV-1a hash
+fn checksum(bytes: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for &b in bytes {
+        hash ^= b as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+const LOAD_KINDS: [LoadKind; 12] = [
+    LoadKind::I32,
+    LoadKind::I64,
+    LoadKind::I32S8,
+    LoadKind::I32U8,
+    LoadKind::I32S16,
+    LoadKind::I32U16,
+    LoadKind::I64S8,
+    LoadKind::I64U8,
+    LoadKind::I64S16,
+    LoadKind::I64U16,
+    LoadKind::I64S32,
+    LoadKind::I64U32,
+];
+
+const STORE_KINDS: [StoreKind; 4] = [
+    StoreKind::Bits8,
+    StoreKind::Bits16,
+    StoreKind::Bits32,
+    StoreKind::Bits64,
+];
+
+const IBIN_OPS: [IBinOp; 15] = [
+    IBinOp::Add,
+    IBinOp::Sub,
+    IBinOp::Mul,
+    IBinOp::DivS,
+    IBinOp::DivU,
+    IBinOp::RemS,
+    IBinOp::RemU,
+    IBinOp::And,
+    IBinOp::Or,
+    IBinOp::Xor,
+    IBinOp::Shl,
+    IBinOp::ShrS,
+    IBinOp::ShrU,
+    IBinOp::Rotl,
+    IBinOp::Rotr,
+];
+
+/// Reader of the payload, returning `None` on malformed input
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_byte(&mut self) -> Option<u8> {
+        let b = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn read_u32(&mut self) -> Option<u32> {
+        let mut n = 0u32;
+        for shift in (0..35).step_by(7) {
+            let b = self.read_byte()?;
+            n |= ((b & 0x7f) as u32).checked_shl(shift)?;
+            if b & 0x80 == 0 {
+                return Some(n);
+            }
+        }
+        None
+    }
+
+    fn read(&mut self) -> Option<usize> {
+        self.read_u32().map(|n| n as usize)
+    }
+
+    fn read_bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.read()?;
+        let bytes = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn read_local(&mut self) -> Option<Local> {
+        Some(Local {
+            offset: self.read()?,
+            width: self.read()?,
+        })
+    }
+
+    fn read_target(&mut self) -> Option<Target> {
+        Some(Target {
+            pc: self.read()?,
+            keep: self.read()?,
+            drop: self.read()?,
+        })
+    }
+
+    fn read_load_kind(&mut self) -> Option<LoadKind> {
+        LOAD_KINDS.get(self.read_byte()? as usize).cloned()
+    }
+
+    fn read_instr(&mut self) -> Option<Instr> {
+        let (instr, len) = binary::decode_instr(&self.bytes[self.pos..]).ok()?;
+        self.pos += len;
+        Some(instr)
+    }
+
+    fn read_op(&mut self) -> Option<Op> {
+        Some(match self.read_byte()? {
+            0 => Op::Instr(self.read_instr()?),
+            1 => Op::Drop(self.read()?),
+            2 => Op::Select(self.read()?),
+            3 => Op::GetLocal(self.read_local()?),
+            4 => Op::SetLocal(self.read_local()?),
+            5 => Op::TeeLocal(self.read_local()?),
+            6 => Op::Load(self.read_load_kind()?, self.read_u32()?),
+            7 => {
+                let kind = STORE_KINDS.get(self.read_byte()? as usize).cloned()?;
+                Op::Store(kind, self.read_u32()?)
+            }
+            8 => Op::Br(self.read_target()?),
+            9 => Op::BrIf(self.read_target()?),
+            10 => Op::BrUnless(self.read()?),
+            11 => {
+                let len = self.read()?;
+                let mut targets = Vec::new();
+                for _ in 0..len {
+                    targets.push(self.read_target()?);
+                }
+                Op::BrTable(targets, self.read_target()?)
+            }
+            12 => Op::Jump(self.read()?),
+            13 => Op::Return,
+            14 => Op::LocalAddConst(self.read_local()?, self.read_u32()?),
+            15 => Op::LocalAddConstLoad(
+                self.read_local()?,
+                self.read_u32()?,
+                self.read_load_kind()?,
+                self.read_u32()?,
+            ),
+            16 => {
+                let (a, b) = (self.read_local()?, self.read_local()?);
+                let op = IBIN_OPS.get(self.read_byte()? as usize).cloned()?;
+                let dst = match self.read_byte()? {
+                    0 => None,
+                    1 => Some(self.read_local()?),
+                    _ => return None,
+                };
+                Op::LocalsIBin(a, b, op, dst)
+            }
+            _ => return None,
+        })
+    }
+
+    fn read_catch(&mut self) -> Option<Catch> {
+        Some(match self.read_byte()? {
+            0 => Catch::Tag(self.read_u32()?, self.read_u32()?),
+            1 => Catch::TagRef(self.read_u32()?, self.read_u32()?),
+            2 => Catch::All(self.read_u32()?),
+            3 => Catch::AllRef(self.read_u32()?),
+            _ => return None,
+        })
+    }
+
+    fn read_handler(&mut self) -> Option<Handler> {
+        let (start, end, height) = (self.read()?, self.read()?, self.read()?);
+        let len = self.read()?;
+        let mut catches = Vec::new();
+        for _ in 0..len {
+            catches.push((self.read_catch()?, self.read_target()?));
+        }
+        Some(Handler {
+            start,
+            end,
+            height,
+            catches,
+        })
+    }
+
+    /// Read the lowered code of a function of type `type_`
+    fn read_code(&mut self, type_: &types::Func) -> Option<Code> {
+        let local_slots = self.read()?;
+        let len = self.read()?;
+        let mut ops = Vec::new();
+        for _ in 0..len {
+            ops.push(self.read_op()?);
+        }
+        let len = self.read()?;
+        let mut handlers = Vec::new();
+        for _ in 0..len {
+            handlers.push(self.read_handler()?);
+        }
+        Some(Code {
+            arg_slots: lower::slots(&type_.args),
+            local_slots,
+            result_slots: lower::slots(&type_.result),
+            ops,
+            handlers,
+        })
+    }
+}
+
+fn require(b: bool) -> Option<()> {
+    if b {
+        Some(())
+    } else {
+        None
+    }
+}
+
+/// Check that the lowered code of a function only refers to operations,
+/// locals and module entities that exist, since it is not validated again
+fn check_code(ctx: &ModContext, code: &Code) -> Option<()> {
+    let end = code.ops.len();
+    let frame = code.arg_slots.checked_add(code.local_slots)?;
+    let local = |local: Local| {
+        require(local.width > 0 && local.offset < frame && local.width <= frame - local.offset)
+    };
+    let target = |target: Target| require(target.pc <= end);
+    let memory = || require(!ctx.memories.is_empty());
+
+    for op in &code.ops {
+        match *op {
+            Op::Instr(ref instr) => check_instr(ctx, instr)?,
+            Op::GetLocal(l) | Op::SetLocal(l) | Op::TeeLocal(l) | Op::LocalAddConst(l, _) => {
+                local(l)?
+            }
+            Op::Load(..) | Op::Store(..) => memory()?,
+            Op::LocalAddConstLoad(l, ..) => {
+                local(l)?;
+                memory()?;
+            }
+            Op::LocalsIBin(a, b, _, dst) => {
+                local(a)?;
+                local(b)?;
+                if let Some(dst) = dst {
+                    local(dst)?;
+                }
+            }
+            Op::Br(t) | Op::BrIf(t) => target(t)?,
+            Op::BrTable(ref targets, default) => {
+                targets.iter().try_for_each(|&t| target(t))?;
+                target(default)?;
+            }
+            Op::BrUnless(pc) | Op::Jump(pc) => require(pc <= end)?,
+            Op::Drop(_) | Op::Select(_) | Op::Return => {}
+        }
+    }
+
+    for handler in &code.handlers {
+        require(handler.start <= handler.end && handler.end <= end)?;
+        for &(ref catch, t) in &handler.catches {
+            match *catch {
+                Catch::Tag(tag, _) | Catch::TagRef(tag, _) => {
+                    require((tag as usize) < ctx.tags.len())?
+                }
+                Catch::All(_) | Catch::AllRef(_) => {}
+            }
+            target(t)?;
+        }
+    }
+    Some(())
+}
+
+/// Check that an instruction of an `Op::Instr` is one that lowering keeps,
+/// and that the module entities it refers to exist
+fn check_instr(ctx: &ModContext, instr: &Instr) -> Option<()> {
+    use crate::ast::Instr::*;
+
+    let index = |idx: u32, len: usize| require((idx as usize) < len);
+    match *instr {
+        Unreachable | ThrowRef | AtomicFence => Some(()),
+        Throw(idx) => index(idx, ctx.tags.len()),
+        Call(idx) | ReturnCall(idx) => index(idx, ctx.funcs.len()),
+        CallIndirect(idx) | ReturnCallIndirect(idx) => {
+            require(!ctx.tables.is_empty())?;
+            index(idx, ctx.types.len())
+        }
+        GetGlobal(idx) | SetGlobal(idx) => index(idx, ctx.globals.len()),
+        Const(_) | IUnary(..) | FUnary(..) | IBin(..) | FBin(..) | ITest(..) | IRel(..)
+        | FRel(..) | Convert(_) | ExtractLane(..) | AnyTrue | AllTrue(_) | Bitmask(_)
+        | Splat(_) | VNot | VIUnary(..) | VFUnary(..) | VConvert(_) | Shuffle(_) | Swizzle
+        | ReplaceLane(..) | VBit(_) | VIBin(..) | VFBin(..) | VShift(..) | VIRel(..)
+        | VFRel(..) | Bitselect => Some(()),
+        CurrentMemory | GrowMemory | VLoad(_) | VStore(_) | AtomicLoad(_) | AtomicStore(_)
+        | AtomicRmw(..) | AtomicCmpxchg(_) | AtomicWait(_) | AtomicNotify(_) => {
+            require(!ctx.memories.is_empty())
+        }
+        Nop | Block(..) | Loop(..) | If(..) | TryTable(..) | Br(_) | BrIf(_) | BrTable(..)
+        | Return | Drop_ | Select | GetLocal(_) | SetLocal(_) | TeeLocal(_) | Load(..)
+        | Store(..) => None,
+    }
+}
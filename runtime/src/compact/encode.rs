@@ -0,0 +1,185 @@
+//! Conversion of wasm modules to the compact format, done ahead of time by
+//! the watt-compact tool.
+
+use super::binary::compact::{decode_with_ranges, strip_bodies, write_vu32};
+use super::lower::{self, Code, Local, Op, Target};
+use super::{checksum, valid, HEADER_SIZE, MAGIC, VERSION};
+use crate::ast::Catch;
+use crate::error::E;
+use std::convert::TryInto;
+
+/// Compact a wasm module, failing if it is malformed or invalid
+pub fn encode(wasm: &[u8]) -> Result<Vec<u8>, E> {
+    let (module, ranges) = decode_with_ranges(wasm).map_err(|_| E::DecodeModuleFailed)?;
+    if !valid::is_valid(&module) {
+        return Err(E::InvalidModule);
+    }
+    let ctx = valid::check_module(&module).unwrap();
+    let stripped = strip_bodies(wasm).map_err(|_| E::DecodeModuleFailed)?;
+
+    let mut payload = Vec::new();
+    write(&mut payload, stripped.len());
+    payload.extend_from_slice(&stripped);
+    let lower_ctx = lower::Context {
+        types: &ctx.types,
+        funcs: &ctx.funcs,
+        globals: &ctx.globals,
+    };
+    for (func, ranges) in module.funcs.into_iter().zip(ranges) {
+        let type_ = &ctx.types[func.type_index as usize];
+        let (code, sources) = lower::lower_traced(&lower_ctx, type_, func);
+        let instrs = sources
+            .into_iter()
+            .map(|source| &wasm[ranges[source].clone()]);
+        write_code(&mut payload, &code, instrs);
+    }
+
+    let mut compact = Vec::with_capacity(HEADER_SIZE + payload.len());
+    compact.extend_from_slice(MAGIC);
+    compact.extend_from_slice(&VERSION.to_le_bytes());
+    compact.extend_from_slice(&checksum(&payload).to_le_bytes());
+    compact.extend_from_slice(&payload);
+    Ok(compact)
+}
+
+fn write(bytes: &mut Vec<u8>, n: usize) {
+    write_vu32(bytes, n.try_into().expect("lowered code too large"));
+}
+
+fn write_local(bytes: &mut Vec<u8>, local: Local) {
+    write(bytes, local.offset);
+    write(bytes, local.width);
+}
+
+fn write_target(bytes: &mut Vec<u8>, target: Target) {
+    write(bytes, target.pc);
+    write(bytes, target.keep);
+    write(bytes, target.drop);
+}
+
+/// Write the lowered code of a function, taking the encoding of the
+/// instructions of its `Op::Instr` from `instrs`
+fn write_code<'a>(bytes: &mut Vec<u8>, code: &Code, mut instrs: impl Iterator<Item = &'a [u8]>) {
+    write(bytes, code.local_slots);
+    write(bytes, code.ops.len());
+    for op in &code.ops {
+        match *op {
+            Op::Instr(_) => {
+                bytes.push(0);
+                bytes.extend_from_slice(instrs.next().unwrap());
+            }
+            Op::Drop(width) => {
+                bytes.push(1);
+                write(bytes, width);
+            }
+            Op::Select(width) => {
+                bytes.push(2);
+                write(bytes, width);
+            }
+            Op::GetLocal(local) => {
+                bytes.push(3);
+                write_local(bytes, local);
+            }
+            Op::SetLocal(local) => {
+                bytes.push(4);
+                write_local(bytes, local);
+            }
+            Op::TeeLocal(local) => {
+                bytes.push(5);
+                write_local(bytes, local);
+            }
+            Op::Load(kind, offset) => {
+                bytes.push(6);
+                bytes.push(kind as u8);
+                write(bytes, offset as usize);
+            }
+            Op::Store(kind, offset) => {
+                bytes.push(7);
+                bytes.push(kind as u8);
+                write(bytes, offset as usize);
+            }
+            Op::Br(target) => {
+                bytes.push(8);
+                write_target(bytes, target);
+            }
+            Op::BrIf(target) => {
+                bytes.push(9);
+                write_target(bytes, target);
+            }
+            Op::BrUnless(pc) => {
+                bytes.push(10);
+                write(bytes, pc);
+            }
+            Op::BrTable(ref targets, default) => {
+                bytes.push(11);
+                write(bytes, targets.len());
+                for &target in targets {
+                    write_target(bytes, target);
+                }
+                write_target(bytes, default);
+            }
+            Op::Jump(pc) => {
+                bytes.push(12);
+                write(bytes, pc);
+            }
+            Op::Return => bytes.push(13),
+            Op::LocalAddConst(local, c) => {
+                bytes.push(14);
+                write_local(bytes, local);
+                write(bytes, c as usize);
+            }
+            Op::LocalAddConstLoad(local, c, kind, offset) => {
+                bytes.push(15);
+                write_local(bytes, local);
+                write(bytes, c as usize);
+                bytes.push(kind as u8);
+                write(bytes, offset as usize);
+            }
+            Op::LocalsIBin(a, b, op, dst) => {
+                bytes.push(16);
+                write_local(bytes, a);
+                write_local(bytes, b);
+                bytes.push(op as u8);
+                match dst {
+                    Some(dst) => {
+                        bytes.push(1);
+                        write_local(bytes, dst);
+                    }
+                    None => bytes.push(0),
+                }
+            }
+        }
+    }
+
+    write(bytes, code.handlers.len());
+    for handler in &code.handlers {
+        write(bytes, handler.start);
+        write(bytes, handler.end);
+        write(bytes, handler.height);
+        write(bytes, handler.catches.len());
+        for &(ref catch, target) in &handler.catches {
+            // same encoding as in a `try_table`
+            match *catch {
+                Catch::Tag(tag, label) => {
+                    bytes.push(0);
+                    write(bytes, tag as usize);
+                    write(bytes, label as usize);
+                }
+                Catch::TagRef(tag, label) => {
+                    bytes.push(1);
+                    write(bytes, tag as usize);
+                    write(bytes, label as usize);
+                }
+                Catch::All(label) => {
+                    bytes.push(2);
+                    write(bytes, label as usize);
+                }
+                Catch::AllRef(label) => {
+                    bytes.push(3);
+                    write(bytes, label as usize);
+                }
+            }
+            write_target(bytes, target);
+        }
+    }
+}
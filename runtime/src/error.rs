@@ -23,8 +23,12 @@ pub enum E {
     DataOffsetTooLarge(usize),
     NotEnoughArgument,
     ArgumentTypeMismatch,
+    ForeignExnRef,
     CodeTrapped(Trap),
+    UncaughtException,
     InvalidModule,
+    CompactVersionMismatch(u32),
+    CompactChecksumMismatch,
     ExportNotFound,
     #[cfg(feature = "test")]
     InvalidTableRead,
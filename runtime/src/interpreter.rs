@@ -1,13 +1,26 @@
+use super::lower::{self, Code, Local, Op, Target};
+use super::simd;
 use crate::ast::*;
 use crate::ops::{FloatDemoteOp, FloatOp, FloatPromoteOp, IntOp};
 use crate::runtime::*;
 use crate::types;
 use crate::values::Value;
+use std::any::Any;
+use std::convert::TryInto;
+use std::mem;
 use std::rc::Rc;
 
 /// A struct storing the state of the current interpreted
 pub struct Interpreter {
-    pub stack: Vec<Value>,
+    /// Arguments, locals and operands as untagged 64-bit slots, their types
+    /// being known from validation. A `v128` takes two slots, low bits first.
+    pub stack: Vec<u64>,
+    /// Exceptions thrown during the invocation, referenced by `exnref` values
+    pub exns: ExnInstStore,
+    /// Maximum number of nested function calls
+    pub max_call_depth: usize,
+    /// User data of the store, handed to host functions
+    pub host_data: Option<Box<dyn Any>>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -21,6 +34,12 @@ pub enum TrapOrigin {
     LoadOutOfMemory,
     StoreOutOfMemory,
     StackOverflow,
+    /// The body of the called function failed validation
+    InvalidFunction,
+    ThrowRefNull,
+    UnalignedAtomic,
+    WaitOnUnsharedMemory,
+    WaitForever,
     HostFunction(HostFunctionError),
 }
 
@@ -34,61 +53,93 @@ pub struct Trap {
 pub enum Control {
     /// Continue the execution linearly
     Continue,
-    /// Branch to the `nesting_levels` outer scope, 0 being the innermost surrounding scope.
-    Branch { nesting_levels: u32 },
     /// Exit the function
     Return,
+    /// Call the given function
+    Call(FuncAddr),
+    /// Exit the function and replace its frame with a call to the given function
+    TailCall(FuncAddr),
+    /// Unwind to the innermost handler catching the given exception
+    Throw(ExnAddr),
 }
 
 use self::Control::*;
 
-type IntResult = Result<Control, Trap>;
-
-/// Stack frames tracks frame activation
-pub struct StackFrame {
-    module: Option<Rc<ModuleInst>>,
-    stack_idx: usize, // The size of the stack before pushing args & locals for the Frame
-    nested_levels: usize,
+/// A scalar stored untagged in a single stack slot
+trait Slot: Copy {
+    fn from_slot(slot: u64) -> Self;
+    fn to_slot(self) -> u64;
 }
 
-// The stack budget (how many nested levels)
-const STACK_BUDGET: usize = 300;
+impl Slot for u32 {
+    fn from_slot(slot: u64) -> u32 {
+        slot as u32
+    }
+    fn to_slot(self) -> u64 {
+        self as u64
+    }
+}
 
-impl StackFrame {
-    pub fn new(module: Option<Rc<ModuleInst>>) -> StackFrame {
-        StackFrame {
-            module: module,
-            stack_idx: 0,
-            nested_levels: STACK_BUDGET,
-        }
+impl Slot for u64 {
+    fn from_slot(slot: u64) -> u64 {
+        slot
+    }
+    fn to_slot(self) -> u64 {
+        self
     }
+}
 
-    pub fn push(&self, module: Option<Rc<ModuleInst>>, stack_idx: usize) -> Option<StackFrame> {
-        if self.nested_levels == 0 {
-            return None;
-        }
+impl Slot for f32 {
+    fn from_slot(slot: u64) -> f32 {
+        f32::from_bits(slot as u32)
+    }
+    fn to_slot(self) -> u64 {
+        self.to_bits() as u64
+    }
+}
 
-        Some(StackFrame {
-            module: module,
-            stack_idx: stack_idx,
-            nested_levels: self.nested_levels - 1,
-        })
+impl Slot for f64 {
+    fn from_slot(slot: u64) -> f64 {
+        f64::from_bits(slot)
+    }
+    fn to_slot(self) -> u64 {
+        self.to_bits()
     }
 }
 
+type IntResult = Result<Control, Trap>;
+
+/// The activation of a function. Frames are kept on the heap rather than on
+/// the native stack, so that the depth of wasm calls is not limited by the
+/// size of the host thread's stack.
+pub struct Frame<'a> {
+    func: &'a ModuleFuncInst,
+    code: Rc<Code>,
+    /// Position of the next operation to execute
+    pc: usize,
+    stack_idx: usize, // The size of the stack before pushing args & locals for the Frame
+}
+
+/// The default maximum number of nested function calls
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
 impl Interpreter {
     /// Instantiate a new interpreter
-    pub fn new() -> Interpreter {
-        Interpreter { stack: Vec::new() }
+    pub fn new(max_call_depth: usize) -> Interpreter {
+        Interpreter {
+            stack: Vec::new(),
+            exns: ExnInstStore::new(),
+            max_call_depth,
+            host_data: None,
+        }
     }
 
     /// Intrepret a single instruction.
     /// This is the main dispatching function of the interpreter.
     pub fn instr(
         &mut self,
-        sframe: &StackFrame,
+        frame: &Frame,
         instr: &Instr,
-        funcs: &FuncInstStore,
         tables: &TableInstStore,
         globals: &mut GlobalInstStore,
         mems: &mut MemInstStore,
@@ -99,56 +150,27 @@ impl Interpreter {
         match *instr {
             Unreachable => self.unreachable(),
             Nop => self.nop(),
-            Block(ref result_type, ref instrs) => {
-                self.block(sframe, result_type, instrs, funcs, tables, globals, mems)
-            }
-            Loop(_, ref instrs) => self.loop_(sframe, instrs, funcs, tables, globals, mems),
-            If(ref result_type, ref if_instrs, ref else_instrs) => self.if_(
-                sframe,
-                result_type,
-                if_instrs,
-                else_instrs,
-                funcs,
-                tables,
-                globals,
-                mems,
-            ),
-            Br(nesting_levels) => self.branch(nesting_levels),
-            BrIf(nesting_levels) => self.branch_cond(nesting_levels),
-            BrTable(ref all_levels, default_level) => self.branch_table(all_levels, default_level),
-            Return => self.return_(),
-            Call(idx) => {
-                let f_addr = sframe.module.as_ref().unwrap().func_addrs[idx as usize];
-                self.call(f_addr, sframe, funcs, tables, globals, mems)
+            Throw(idx) => self.throw(idx, &frame.func.module),
+            ThrowRef => self.throw_ref(),
+            Block(..) | Loop(..) | If(..) | TryTable(..) | Br(_) | BrIf(_) | BrTable(..)
+            | Return => unreachable!("structured control flow is lowered to jumps"),
+            Drop_ | Select | GetLocal(_) | SetLocal(_) | TeeLocal(_) | Load(..) | Store(..) => {
+                unreachable!("parametric, local and memory instructions are lowered to operations")
             }
+            Call(idx) => Ok(Control::Call(frame.func.module.func_addrs[idx as usize])),
             CallIndirect(idx) => {
-                let mod_inst = sframe.module.as_ref().unwrap().clone();
-                self.call_indirect(
-                    idx,
-                    sframe,
-                    funcs,
-                    tables,
-                    globals,
-                    mems,
-                    &mod_inst.table_addrs,
-                    &mod_inst.types,
-                )
-            }
-            Drop_ => self.drop(),
-            Select => self.select(),
-            GetLocal(idx) => self.get_local(idx, sframe.stack_idx),
-            SetLocal(idx) => self.set_local(idx, sframe.stack_idx),
-            TeeLocal(idx) => self.tee_local(idx, sframe.stack_idx),
-            GetGlobal(idx) => {
-                self.get_global(idx, globals, &sframe.module.as_ref().unwrap().global_addrs)
+                let mod_inst = &frame.func.module;
+                self.call_indirect(idx, tables, &mod_inst.table_addrs, &mod_inst.type_ids)
             }
-            SetGlobal(idx) => {
-                self.set_global(idx, globals, &sframe.module.as_ref().unwrap().global_addrs)
+            ReturnCall(idx) => self.return_call(frame.func.module.func_addrs[idx as usize]),
+            ReturnCallIndirect(idx) => {
+                let mod_inst = &frame.func.module;
+                self.return_call_indirect(idx, tables, &mod_inst.table_addrs, &mod_inst.type_ids)
             }
-            Load(ref memop) => self.load(memop, mems, &sframe.module.as_ref().unwrap().mem_addrs),
-            Store(ref memop) => self.store(memop, mems, &sframe.module.as_ref().unwrap().mem_addrs),
-            CurrentMemory => self.current_memory(mems, &sframe.module.as_ref().unwrap().mem_addrs),
-            GrowMemory => self.grow_memory(mems, &sframe.module.as_ref().unwrap().mem_addrs),
+            GetGlobal(idx) => self.get_global(idx, globals, &frame.func.module.global_addrs),
+            SetGlobal(idx) => self.set_global(idx, globals, &frame.func.module.global_addrs),
+            CurrentMemory => self.current_memory(mems, &frame.func.module.mem_addrs),
+            GrowMemory => self.grow_memory(mems, &frame.func.module.mem_addrs),
             Const(c) => self.const_(c),
             IUnary(ref t, ref op) => self.iunary(t, op),
             FUnary(ref t, ref op) => self.funary(t, op),
@@ -158,6 +180,40 @@ impl Interpreter {
             IRel(ref t, ref op) => self.irel(t, op),
             FRel(ref t, ref op) => self.frel(t, op),
             Convert(ref op) => self.cvtop(op),
+            VLoad(ref memop) => self.vload(memop, mems, &frame.func.module.mem_addrs),
+            VStore(ref memop) => self.vstore(memop, mems, &frame.func.module.mem_addrs),
+            Shuffle(ref lanes) => self.vbin(|a, b| simd::shuffle(a, b, lanes)),
+            Swizzle => self.vbin(simd::swizzle),
+            Splat(shape) => self.splat(shape),
+            ExtractLane(shape, lane, signed) => self.extract_lane(shape, lane, signed),
+            ReplaceLane(shape, lane) => self.replace_lane(shape, lane),
+            VNot => self.vunary(|v| !v),
+            VBit(ref op) => self.vbin(|a, b| simd::bit(op, a, b)),
+            Bitselect => self.bitselect(),
+            AnyTrue => self.vtest(|v| (v != 0) as u32),
+            AllTrue(shape) => self.vtest(|v| simd::all_true(shape, v) as u32),
+            Bitmask(shape) => self.vtest(|v| simd::bitmask(shape, v)),
+            VIUnary(shape, ref op) => self.vunary(|v| simd::iunary(shape, op, v)),
+            VFUnary(shape, ref op) => self.vunary(|v| simd::funary(shape, op, v)),
+            VIBin(shape, ref op) => self.vbin(|a, b| simd::ibin(shape, op, a, b)),
+            VFBin(shape, ref op) => self.vbin(|a, b| simd::fbin(shape, op, a, b)),
+            VShift(shape, ref op) => self.vshift(shape, op),
+            VIRel(shape, ref op) => self.vbin(|a, b| simd::irel(shape, op, a, b)),
+            VFRel(shape, ref op) => self.vbin(|a, b| simd::frel(shape, op, a, b)),
+            VConvert(ref op) => self.vunary(|v| simd::convert(op, v)),
+            AtomicLoad(ref memop) => self.atomic_load(memop, mems, &frame.func.module.mem_addrs),
+            AtomicStore(ref memop) => self.atomic_store(memop, mems, &frame.func.module.mem_addrs),
+            AtomicRmw(op, ref memop) => {
+                self.atomic_rmw(op, memop, mems, &frame.func.module.mem_addrs)
+            }
+            AtomicCmpxchg(ref memop) => {
+                self.atomic_cmpxchg(memop, mems, &frame.func.module.mem_addrs)
+            }
+            AtomicWait(ref memop) => self.atomic_wait(memop, mems, &frame.func.module.mem_addrs),
+            AtomicNotify(ref memop) => {
+                self.atomic_notify(memop, mems, &frame.func.module.mem_addrs)
+            }
+            AtomicFence => Ok(Continue),
         }
     }
 
@@ -173,180 +229,272 @@ impl Interpreter {
         Ok(Continue)
     }
 
-    /// Interpret a block
-    fn block(
+    /// Execute a function until it returns, along with the functions it calls.
+    /// The frames of the callers are kept in a vector instead of recursing.
+    fn run(
         &mut self,
-        sframe: &StackFrame,
-        result_type: &[types::Value],
-        instrs: &[Instr],
+        func: &ModuleFuncInst,
         funcs: &FuncInstStore,
         tables: &TableInstStore,
         globals: &mut GlobalInstStore,
         mems: &mut MemInstStore,
     ) -> IntResult {
-        let local_stack_begin = self.stack.len();
-
-        for instr in instrs {
-            match self.instr(sframe, instr, funcs, tables, globals, mems)? {
-                Branch { nesting_levels } => {
-                    // If the instruction caused a branch, we need to exit the block early on.
-                    // The way to do so depends if the current block is the target of the branch.
-                    return Ok(if nesting_levels == 0 {
-                        // We have reached the target block.
-                        // Unwind values that could be left on the stack, except for the result,
-                        // and resume normal execution.
-                        let junk_end = self.stack.len() - result_type.len();
-                        self.stack.drain(local_stack_begin..junk_end);
-                        Continue
-                    } else {
-                        // Keep traversing nesting levels
-                        Branch {
-                            nesting_levels: nesting_levels - 1,
-                        }
-                    });
+        let mut frames = Vec::new();
+        let mut frame = self.enter(func)?;
+
+        loop {
+            let code = &frame.code;
+            let op = match code.ops.get(frame.pc) {
+                Some(op) => op,
+                None => &Op::Return, // The end of the code returns
+            };
+            frame.pc += 1;
+            let res = match *op {
+                Op::Instr(ref instr) => self.instr(&frame, instr, tables, globals, mems)?,
+                Op::Drop(width) => {
+                    let len = self.stack.len();
+                    self.stack.truncate(len - width);
+                    continue;
                 }
-                Return => return Ok(Return), // Stack unwinding will be done by the caller
-                Continue => {}
-            }
-        }
-
-        Ok(Continue)
-    }
+                Op::Select(width) => {
+                    self.select(width);
+                    continue;
+                }
+                Op::GetLocal(local) => {
+                    self.get_local(local, frame.stack_idx);
+                    continue;
+                }
+                Op::SetLocal(local) => {
+                    self.set_local(local, frame.stack_idx);
+                    continue;
+                }
+                Op::TeeLocal(local) => {
+                    self.tee_local(local, frame.stack_idx);
+                    continue;
+                }
+                Op::LocalAddConst(local, c) => {
+                    let addr = self.local_add_const(local, c, frame.stack_idx);
+                    self.push(addr);
+                    continue;
+                }
+                Op::Load(kind, offset) => {
+                    self.load(kind, offset, &mems[frame.func.module.mem_addrs[0]])?
+                }
+                Op::Store(kind, offset) => {
+                    self.store(kind, offset, &mut mems[frame.func.module.mem_addrs[0]])?
+                }
+                Op::LocalAddConstLoad(local, c, kind, offset) => {
+                    let addr = self.local_add_const(local, c, frame.stack_idx);
+                    self.push(addr);
+                    self.load(kind, offset, &mems[frame.func.module.mem_addrs[0]])?
+                }
+                Op::LocalsIBin(a, b, ref op, dst) => {
+                    self.locals_ibin(a, b, op, dst, frame.stack_idx)?
+                }
+                Op::Br(ref target) => {
+                    frame.pc = self.branch(target);
+                    continue;
+                }
+                Op::BrIf(ref target) => {
+                    if self.pop_cond() {
+                        frame.pc = self.branch(target);
+                    }
+                    continue;
+                }
+                Op::BrUnless(dest) => {
+                    if !self.pop_cond() {
+                        frame.pc = dest;
+                    }
+                    continue;
+                }
+                Op::BrTable(ref targets, ref default) => {
+                    let c: u32 = self.pop();
+                    let target = targets.get(c as usize).unwrap_or(default);
+                    frame.pc = self.branch(target);
+                    continue;
+                }
+                Op::Jump(dest) => {
+                    frame.pc = dest;
+                    continue;
+                }
+                Op::Return => Return,
+            };
 
-    /// Interpret a loop
-    fn loop_(
-        &mut self,
-        sframe: &StackFrame,
-        instrs: &[Instr],
-        funcs: &FuncInstStore,
-        tables: &TableInstStore,
-        globals: &mut GlobalInstStore,
-        mems: &mut MemInstStore,
-    ) -> IntResult {
-        let local_stack_begin = self.stack.len();
-
-        'outer: loop {
-            for instr in instrs {
-                let res = self.instr(sframe, instr, funcs, tables, globals, mems)?;
-
-                match res {
-                    Branch { nesting_levels } => {
-                        // If the instruction caused a branch, we need to exit or restart the loop
-                        if nesting_levels == 0 {
-                            // We have reached the target loop.
-                            // Unwind all values that could be left on the stack and restart the loop
-                            self.stack.truncate(local_stack_begin);
-                            continue 'outer;
-                        } else {
-                            // Exit the loop and keep traversing nesting levels
-                            return Ok(Branch {
-                                nesting_levels: nesting_levels - 1,
+            match res {
+                Continue => {}
+                Return => {
+                    // Remove locals/args
+                    let drain_end = self.stack.len() - code.result_slots;
+                    self.stack.drain(frame.stack_idx..drain_end);
+                    match frames.pop() {
+                        Some(caller) => frame = caller,
+                        None => return Ok(Continue),
+                    }
+                }
+                Control::Call(f_addr) => match funcs[f_addr] {
+                    FuncInst::Module(ref f) => {
+                        if frames.len() + 1 >= self.max_call_depth {
+                            return Err(Trap {
+                                origin: TrapOrigin::StackOverflow,
                             });
                         }
+                        let callee = self.enter(f)?;
+                        frames.push(mem::replace(&mut frame, callee));
+                    }
+                    FuncInst::Host(ref f) => {
+                        self.call_host(f, Some(&frame.func.module), globals, mems)?;
+                    }
+                },
+                TailCall(f_addr) => {
+                    // Replace the current frame by the arguments of the callee
+                    let args_len = match funcs[f_addr] {
+                        FuncInst::Module(ref f) => &f.type_.args,
+                        FuncInst::Host(ref f) => &f.type_.args,
+                    };
+                    let args_len = lower::slots(args_len);
+                    let drain_end = self.stack.len() - args_len;
+                    self.stack.drain(frame.stack_idx..drain_end);
+                    match funcs[f_addr] {
+                        FuncInst::Module(ref f) => frame = self.enter(f)?,
+                        FuncInst::Host(ref f) => {
+                            // The results of the host function are those of the frame
+                            self.call_host(f, Some(&frame.func.module), globals, mems)?;
+                            match frames.pop() {
+                                Some(caller) => frame = caller,
+                                None => return Ok(Continue),
+                            }
+                        }
                     }
-                    Return => return Ok(Return),
-                    Continue => {}
                 }
+                Throw(exn_addr) => loop {
+                    // Unwind the frames up to the first one handling the exception
+                    if let Some(dest) = self.catch(&frame, frame.pc - 1, exn_addr) {
+                        frame.pc = dest;
+                        break;
+                    }
+                    match frames.pop() {
+                        Some(caller) => frame = caller,
+                        None => return Ok(Throw(exn_addr)),
+                    }
+                },
             }
-
-            // loops that reach the end of the instruction sequence without branching terminate
-            return Ok(Continue);
         }
     }
 
-    /// Perform a unconditional branch to the nesting_levels+1 surrouding block.
-    fn branch(&self, nesting_levels: u32) -> IntResult {
-        Ok(Branch { nesting_levels })
+    /// Push the locals of a function whose arguments are on top of the stack.
+    /// The body of the function is checked and lowered on its first call.
+    fn enter<'a>(&mut self, func: &'a ModuleFuncInst) -> Result<Frame<'a>, Trap> {
+        let code = func.code().ok_or(Trap {
+            origin: TrapOrigin::InvalidFunction,
+        })?;
+        let stack_idx = self.stack.len() - code.arg_slots;
+        // Zero is the default value of every type
+        self.stack
+            .resize(stack_idx + code.arg_slots + code.local_slots, 0);
+        Ok(Frame {
+            func,
+            code,
+            pc: 0,
+            stack_idx,
+        })
     }
 
-    /// Perform a branch if the top of the stack is not null
-    fn branch_cond(&mut self, nesting_levels: u32) -> IntResult {
-        match self.stack.pop().unwrap() {
-            Value::I32(c) => Ok(if c != 0 {
-                Branch { nesting_levels }
-            } else {
-                Continue
-            }),
-            _ => unreachable!(),
+    /// Perform a branch, returning the position of the next operation
+    fn branch(&mut self, target: &Target) -> usize {
+        if target.drop > 0 {
+            let junk_end = self.stack.len() - target.keep;
+            self.stack.drain(junk_end - target.drop..junk_end);
         }
+        target.pc
     }
 
-    /// Perform a branch using a vector of levels, or a default nesting level based on the top of the stack
-    fn branch_table(&mut self, all_levels: &[u32], default_level: u32) -> IntResult {
-        match self.stack.pop().unwrap() {
-            Value::I32(c) => Ok(Branch {
-                nesting_levels: *all_levels.get(c as usize).unwrap_or(&default_level),
-            }),
-            _ => unreachable!(),
-        }
+    /// Pop the condition of a branch
+    fn pop_cond(&mut self) -> bool {
+        self.pop::<u32>() != 0
     }
 
-    /// If/Else block (delegate to block)
-    fn if_(
-        &mut self,
-        sframe: &StackFrame,
-        result_type: &[types::Value],
-        if_instrs: &[Instr],
-        else_instrs: &[Instr],
-        funcs: &FuncInstStore,
-        tables: &TableInstStore,
-        globals: &mut GlobalInstStore,
-        mems: &mut MemInstStore,
-    ) -> IntResult {
-        let c = match self.stack.pop().unwrap() {
-            Value::I32(c) => c,
-            _ => unreachable!(),
-        };
+    /// Look for a handler catching an exception thrown by the operation at
+    /// `pc`, returning the position of the handler code
+    fn catch(&mut self, frame: &Frame, pc: usize, exn_addr: ExnAddr) -> Option<usize> {
+        let tag_addrs = &frame.func.module.tag_addrs;
+        let exn = &self.exns[exn_addr];
 
-        Ok(if c != 0 {
-            self.block(sframe, result_type, if_instrs, funcs, tables, globals, mems)?
-        } else {
-            self.block(
-                sframe,
-                result_type,
-                else_instrs,
-                funcs,
-                tables,
-                globals,
-                mems,
-            )?
-        })
-    }
+        // The innermost handlers come last
+        for handler in frame.code.handlers.iter().rev() {
+            if pc < handler.start || pc >= handler.end {
+                continue;
+            }
+            for (catch, target) in &handler.catches {
+                let (fields, ref_) = match *catch {
+                    Catch::Tag(x, _) if tag_addrs[x as usize] == exn.tag => (true, false),
+                    Catch::TagRef(x, _) if tag_addrs[x as usize] == exn.tag => (true, true),
+                    Catch::All(_) => (false, false),
+                    Catch::AllRef(_) => (false, true),
+                    _ => continue,
+                };
+
+                self.stack.truncate(frame.stack_idx + handler.height);
+                if fields {
+                    self.stack.extend_from_slice(&exn.fields);
+                }
+                if ref_ {
+                    self.push_value(Value::ExnRef(Some(exn_addr)));
+                }
+                return Some(self.branch(target));
+            }
+        }
 
-    /// Drop a value from the stack
-    fn drop(&mut self) -> IntResult {
-        self.stack.pop();
-        Ok(Continue)
+        None
     }
 
-    /// branchless conditional
-    fn select(&mut self) -> IntResult {
-        let b = self.stack.pop().unwrap();
-        let (v1, v2) = self.pop2();
+    /// Throw a new exception with the given tag and the arguments on top of the stack
+    fn throw(&mut self, idx: Index, module: &ModuleInst) -> IntResult {
+        let arity = lower::slots(&module.types[module.tag_types[idx as usize] as usize].args);
+        let fields = self.stack.split_off(self.stack.len() - arity);
+        let exn_addr = self.exns.alloc(module.tag_addrs[idx as usize], fields);
+        Ok(Throw(exn_addr))
+    }
 
-        match b {
-            Value::I32(c) => self.stack.push(if c != 0 { v1 } else { v2 }),
+    /// Rethrow the exception referenced on top of the stack
+    fn throw_ref(&mut self) -> IntResult {
+        match self.pop_value(types::EXNREF) {
+            Value::ExnRef(Some(exn_addr)) => Ok(Throw(exn_addr)),
+            Value::ExnRef(None) => Err(Trap {
+                origin: TrapOrigin::ThrowRefNull,
+            }),
             _ => unreachable!(),
         }
+    }
 
-        Ok(Continue)
+    /// branchless conditional between two values of `width` slots
+    fn select(&mut self, width: usize) {
+        let cond = self.pop_cond();
+        let top = self.stack.len() - width;
+        if !cond {
+            self.stack.copy_within(top.., top - width);
+        }
+        self.stack.truncate(top);
     }
 
     /// Push c to the stack
     fn const_(&mut self, c: Value) -> IntResult {
-        self.stack.push(c);
+        self.push_value(c);
         Ok(Continue)
     }
 
     /// Dispatch an IUnop
-    fn iunary(&mut self, _t: &types::Int, op: &IUnOp) -> IntResult {
+    fn iunary(&mut self, t: &types::Int, op: &IUnOp) -> IntResult {
         // Validation should assert that the top of the stack exists and has the type t
-        let v = match self.stack.pop().unwrap() {
-            Value::I32(c) => Value::I32(self.type_iunary(c, op)),
-            Value::I64(c) => Value::I64(self.type_iunary(c, op)),
-            _ => unreachable!(),
-        };
-        self.stack.push(v);
+        match *t {
+            types::Int::I32 => {
+                let c: u32 = self.pop();
+                self.push(self.type_iunary(c, op));
+            }
+            types::Int::I64 => {
+                let c: u64 = self.pop();
+                self.push(self.type_iunary(c, op));
+            }
+        }
         Ok(Continue)
     }
 
@@ -362,14 +510,18 @@ impl Interpreter {
     }
 
     /// Dispatch an FUnOp
-    fn funary(&mut self, _t: &types::Float, op: &FUnOp) -> IntResult {
+    fn funary(&mut self, t: &types::Float, op: &FUnOp) -> IntResult {
         // Validation should assert that the top of the stack exists and has the type t
-        let v = match self.stack.pop().unwrap() {
-            Value::F32(c) => Value::F32(self.type_funary(c, op)),
-            Value::F64(c) => Value::F64(self.type_funary(c, op)),
-            _ => unreachable!(),
-        };
-        self.stack.push(v);
+        match *t {
+            types::Float::F32 => {
+                let c: f32 = self.pop();
+                self.push(self.type_funary(c, op));
+            }
+            types::Float::F64 => {
+                let c: f64 = self.pop();
+                self.push(self.type_funary(c, op));
+            }
+        }
         Ok(Continue)
     }
 
@@ -389,13 +541,18 @@ impl Interpreter {
     }
 
     /// Dispatch an IBinOp
-    fn ibin(&mut self, _t: &types::Int, op: &IBinOp) -> IntResult {
+    fn ibin(&mut self, t: &types::Int, op: &IBinOp) -> IntResult {
         // Validation should assert that there are two values on top of the
         // stack having the same integer type t
-        let res = match self.pop2() {
-            (Value::I32(c1), Value::I32(c2)) => self.type_ibin(c1, c2, op).map(Value::I32),
-            (Value::I64(c1), Value::I64(c2)) => self.type_ibin(c1, c2, op).map(Value::I64),
-            _ => unreachable!(),
+        let res = match *t {
+            types::Int::I32 => {
+                let (c1, c2) = self.pop2::<u32>();
+                self.type_ibin(c1, c2, op).map(Slot::to_slot)
+            }
+            types::Int::I64 => {
+                let (c1, c2) = self.pop2::<u64>();
+                self.type_ibin(c1, c2, op).map(Slot::to_slot)
+            }
         };
 
         if let Some(v) = res {
@@ -434,15 +591,19 @@ impl Interpreter {
     }
 
     /// Dispatch an FBinOp
-    fn fbin(&mut self, _t: &types::Float, op: &FBinOp) -> IntResult {
+    fn fbin(&mut self, t: &types::Float, op: &FBinOp) -> IntResult {
         // Validation should assert that there are two values on top of the
         // stack having the same type t
-        let res = match self.pop2() {
-            (Value::F32(c1), Value::F32(c2)) => Value::F32(self.type_fbin(c1, c2, op)),
-            (Value::F64(c1), Value::F64(c2)) => Value::F64(self.type_fbin(c1, c2, op)),
-            _ => unreachable!(),
-        };
-        self.stack.push(res);
+        match *t {
+            types::Float::F32 => {
+                let (c1, c2) = self.pop2::<f32>();
+                self.push(self.type_fbin(c1, c2, op));
+            }
+            types::Float::F64 => {
+                let (c1, c2) = self.pop2::<f64>();
+                self.push(self.type_fbin(c1, c2, op));
+            }
+        }
         Ok(Continue)
     }
 
@@ -462,14 +623,19 @@ impl Interpreter {
     }
 
     /// Dispatch an ITestOp
-    fn itest(&mut self, _t: &types::Int, op: &ITestOp) -> IntResult {
+    fn itest(&mut self, t: &types::Int, op: &ITestOp) -> IntResult {
         // Validation should assert that the top of the stack exists and has the type t
-        let v = match self.stack.pop().unwrap() {
-            Value::I32(c) => Value::from_bool(self.type_itest(c, op)),
-            Value::I64(c) => Value::from_bool(self.type_itest(c, op)),
-            _ => unreachable!(),
+        let res = match *t {
+            types::Int::I32 => {
+                let c: u32 = self.pop();
+                self.type_itest(c, op)
+            }
+            types::Int::I64 => {
+                let c: u64 = self.pop();
+                self.type_itest(c, op)
+            }
         };
-        self.stack.push(v);
+        self.push(res as u32);
         Ok(Continue)
     }
 
@@ -483,15 +649,20 @@ impl Interpreter {
     }
 
     /// Dispatch an IRelOp
-    fn irel(&mut self, _t: &types::Int, op: &IRelOp) -> IntResult {
+    fn irel(&mut self, t: &types::Int, op: &IRelOp) -> IntResult {
         // Validation should assert that there are two values on top of the
         // stack having the same integer type t
-        let res = match self.pop2() {
-            (Value::I32(c1), Value::I32(c2)) => Value::from_bool(self.type_irel(c1, c2, op)),
-            (Value::I64(c1), Value::I64(c2)) => Value::from_bool(self.type_irel(c1, c2, op)),
-            _ => unreachable!(),
+        let res = match *t {
+            types::Int::I32 => {
+                let (c1, c2) = self.pop2::<u32>();
+                self.type_irel(c1, c2, op)
+            }
+            types::Int::I64 => {
+                let (c1, c2) = self.pop2::<u64>();
+                self.type_irel(c1, c2, op)
+            }
         };
-        self.stack.push(res);
+        self.push(res as u32);
         Ok(Continue)
     }
 
@@ -514,15 +685,20 @@ impl Interpreter {
     }
 
     /// Dispatch an FRelOp
-    fn frel(&mut self, _t: &types::Float, op: &FRelOp) -> IntResult {
+    fn frel(&mut self, t: &types::Float, op: &FRelOp) -> IntResult {
         // Validation should assert that there are two values on top of the
         // stack having the same integer type t
-        let res = match self.pop2() {
-            (Value::F32(c1), Value::F32(c2)) => Value::from_bool(self.type_frel(c1, c2, op)),
-            (Value::F64(c1), Value::F64(c2)) => Value::from_bool(self.type_frel(c1, c2, op)),
-            _ => unreachable!(),
+        let res = match *t {
+            types::Float::F32 => {
+                let (c1, c2) = self.pop2::<f32>();
+                self.type_frel(c1, c2, op)
+            }
+            types::Float::F64 => {
+                let (c1, c2) = self.pop2::<f64>();
+                self.type_frel(c1, c2, op)
+            }
         };
-        self.stack.push(res);
+        self.push(res as u32);
         Ok(Continue)
     }
 
@@ -545,7 +721,7 @@ impl Interpreter {
         use crate::types::Value as tv;
         use crate::types::{Float, Int};
 
-        let c = self.stack.pop().unwrap();
+        let c = self.pop_value(op.types().0);
         let cls = |&op, &c| {
             Some(match (op, c) {
                 (&ConvertOp::I32WrapI64, Value::I64(c)) => Value::I32(c.to_u32()),
@@ -718,7 +894,7 @@ impl Interpreter {
         };
 
         if let Some(v) = cls(&op, &c) {
-            self.stack.push(v);
+            self.push_value(v);
             Ok(Continue)
         } else {
             Err(Trap {
@@ -734,7 +910,7 @@ impl Interpreter {
         globals: &GlobalInstStore,
         frame_globals: &[GlobalAddr],
     ) -> IntResult {
-        self.stack.push(globals[frame_globals[idx as usize]].value);
+        self.push_value(globals[frame_globals[idx as usize]].value);
         Ok(Continue)
     }
 
@@ -746,94 +922,95 @@ impl Interpreter {
         frame_globals: &[GlobalAddr],
     ) -> IntResult {
         // "Validation ensures that the global is, in fact, marked as mutable."
-        let val = self.stack.pop().unwrap();
-        globals[frame_globals[idx as usize]].value = val;
+        let global = &mut globals[frame_globals[idx as usize]];
+        global.value = self.pop_value(global.value.type_());
         Ok(Continue)
     }
 
-    /// Push local idx on the Stack
-    fn get_local(&mut self, idx: Index, stack_frame_idx: usize) -> IntResult {
-        let val = self.stack[stack_frame_idx + (idx as usize)];
-        self.stack.push(val);
-        Ok(Continue)
+    /// Push a local on the Stack
+    fn get_local(&mut self, local: Local, stack_frame_idx: usize) {
+        let start = stack_frame_idx + local.offset;
+        self.stack.extend_from_within(start..start + local.width);
     }
 
-    /// Update local idx based on the value poped from the stack
-    fn set_local(&mut self, idx: Index, stack_frame_idx: usize) -> IntResult {
-        self.stack[stack_frame_idx + (idx as usize)] = self.stack.pop().unwrap();
-        Ok(Continue)
+    /// Update a local based on the value poped from the stack
+    fn set_local(&mut self, local: Local, stack_frame_idx: usize) {
+        self.tee_local(local, stack_frame_idx);
+        let len = self.stack.len();
+        self.stack.truncate(len - local.width);
     }
 
-    /// Update the local idx without poping the top of the stack
-    fn tee_local(&mut self, idx: Index, stack_frame_idx: usize) -> IntResult {
-        self.stack[stack_frame_idx + (idx as usize)] = *self.stack.last().unwrap();
-        Ok(Continue)
+    /// Update a local without poping the top of the stack
+    fn tee_local(&mut self, local: Local, stack_frame_idx: usize) {
+        let top = self.stack.len() - local.width;
+        self.stack
+            .copy_within(top.., stack_frame_idx + local.offset);
     }
 
-    fn call_module(
+    /// Add a constant to an i32 local
+    fn local_add_const(&self, local: Local, c: u32, stack_frame_idx: usize) -> u32 {
+        let v: u32 = Slot::from_slot(self.stack[stack_frame_idx + local.offset]);
+        v.wrapping_add(c)
+    }
+
+    /// Apply an IBinOp to two i32 locals, storing the result in `dst` if any
+    fn locals_ibin(
         &mut self,
-        f_inst: &ModuleFuncInst,
-        sframe: &StackFrame,
-        funcs: &FuncInstStore,
-        tables: &TableInstStore,
-        globals: &mut GlobalInstStore,
-        mems: &mut MemInstStore,
+        a: Local,
+        b: Local,
+        op: &IBinOp,
+        dst: Option<Local>,
+        stack_frame_idx: usize,
     ) -> IntResult {
-        // Push locals
-        for l in &f_inst.code.locals {
-            match *l {
-                types::Value::Int(types::Int::I32) => self.stack.push(Value::I32(0)),
-                types::Value::Int(types::Int::I64) => self.stack.push(Value::I64(0)),
-                types::Value::Float(types::Float::F32) => self.stack.push(Value::F32(0.0)),
-                types::Value::Float(types::Float::F64) => self.stack.push(Value::F64(0.0)),
-            }
+        let c1: u32 = Slot::from_slot(self.stack[stack_frame_idx + a.offset]);
+        let c2: u32 = Slot::from_slot(self.stack[stack_frame_idx + b.offset]);
+        let res = self.type_ibin(c1, c2, op).ok_or(Trap {
+            origin: TrapOrigin::UndefinedResult,
+        })?;
+        match dst {
+            Some(dst) => self.stack[stack_frame_idx + dst.offset] = res.to_slot(),
+            None => self.push(res),
         }
-
-        // Push the frame
-        let frame_begin = self.stack.len() - f_inst.type_.args.len() - f_inst.code.locals.len();
-        let new_frame = sframe
-            .push(Some(f_inst.module.clone()), frame_begin)
-            .ok_or(Trap {
-                origin: TrapOrigin::StackOverflow,
-            })?;
-
-        // Execute the function inside a block
-        self.block(
-            &new_frame,
-            &f_inst.type_.result,
-            &f_inst.code.body,
-            funcs,
-            tables,
-            globals,
-            mems,
-        )?;
-
-        // Remove locals/args
-        let drain_start = frame_begin;
-        let drain_end = self.stack.len() - f_inst.type_.result.len();
-        self.stack.drain(drain_start..drain_end);
         Ok(Continue)
     }
 
     fn call_host(
         &mut self,
         f_inst: &HostFuncInst,
-        _sframe: &StackFrame,
-        _funcs: &FuncInstStore,
-        _tables: &TableInstStore,
-        _globals: &mut GlobalInstStore,
-        _mems: &mut MemInstStore,
+        module: Option<&ModuleInst>,
+        globals: &mut GlobalInstStore,
+        mems: &mut MemInstStore,
     ) -> IntResult {
         /*
         let stack_before_call = self.stack.len();
         */
 
-        if let Some(err) = (f_inst.hostcode)(&mut self.stack) {
+        // Host functions work on tagged values
+        let mut stack: Vec<_> = f_inst
+            .type_
+            .args
+            .iter()
+            .rev()
+            .map(|&type_| self.pop_value(type_))
+            .collect();
+        stack.reverse();
+
+        let mut caller = Caller {
+            module,
+            globals,
+            mems,
+            data: &mut self.host_data,
+        };
+        if let Some(err) = (f_inst.hostcode)(&mut caller, &mut stack) {
             return Err(Trap {
                 origin: TrapOrigin::HostFunction(err),
             });
         }
 
+        for v in stack {
+            self.push_value(v);
+        }
+
         // Stack must be valid
         /*
         assert_eq!(self.stack.len(), stack_before_call);
@@ -862,50 +1039,45 @@ impl Interpreter {
         Ok(Continue)
     }
 
-    /// Call a function directly
+    /// Call a function whose arguments are on top of the stack
     pub fn call(
         &mut self,
         f_addr: FuncAddr,
-        sframe: &StackFrame,
         funcs: &FuncInstStore,
         tables: &TableInstStore,
         globals: &mut GlobalInstStore,
         mems: &mut MemInstStore,
     ) -> IntResult {
-        // Idea: the new stack_idx is the base frame pointer, which point to the
-        // first argument of the called function. When calling call, all
-        // arguments should already be on the stack (thanks to validation).
         match funcs[f_addr] {
-            FuncInst::Module(ref f_inst) => {
-                self.call_module(f_inst, sframe, funcs, tables, globals, mems)?
-            }
-            FuncInst::Host(ref f_inst) => {
-                self.call_host(f_inst, sframe, funcs, tables, globals, mems)?
-            }
-        };
-
-        Ok(Continue)
+            FuncInst::Module(ref f_inst) => self.run(f_inst, funcs, tables, globals, mems),
+            // There is no calling instance
+            FuncInst::Host(ref f_inst) => self.call_host(f_inst, None, globals, mems),
+        }
     }
 
     /// Call a function indirectly
     fn call_indirect(
         &mut self,
         idx: Index,
-        sframe: &StackFrame,
-        funcs: &FuncInstStore,
         tables: &TableInstStore,
-        globals: &mut GlobalInstStore,
-        mems: &mut MemInstStore,
         table_addrs: &[TableAddr],
-        types: &[types::Func],
+        type_ids: &[TypeId],
     ) -> IntResult {
+        let func_addr = self.indirect_callee(idx, tables, table_addrs, type_ids)?;
+        Ok(Control::Call(func_addr))
+    }
+
+    /// Look up the function targeted by an indirect call, checking its type
+    fn indirect_callee(
+        &mut self,
+        idx: Index,
+        tables: &TableInstStore,
+        table_addrs: &[TableAddr],
+        type_ids: &[TypeId],
+    ) -> Result<FuncAddr, Trap> {
         // For the MVP, only the table at index 0 exists and is implicitly refered
         let tab = &tables[table_addrs[0]];
-        let type_ = &types[idx as usize];
-        let indirect_idx = match self.stack.pop().unwrap() {
-            Value::I32(c) => c as usize,
-            _ => unreachable!(),
-        };
+        let indirect_idx = self.pop::<u32>() as usize;
 
         if indirect_idx >= tab.elem.len() {
             return Err(Trap {
@@ -913,7 +1085,7 @@ impl Interpreter {
             });
         }
 
-        let func_addr = match tab.elem[indirect_idx] {
+        let (func_addr, type_id) = match tab.elem[indirect_idx] {
             Some(c) => c,
             None => {
                 return Err(Trap {
@@ -922,28 +1094,35 @@ impl Interpreter {
             }
         };
 
-        let f = &funcs[func_addr];
-        let f_type_ = match *f {
-            FuncInst::Module(ref f) => &f.type_,
-            FuncInst::Host(ref f) => &f.type_,
-        };
-        if f_type_ != type_ {
+        // Types are interned by the store, so comparing their ids is enough
+        if type_id != type_ids[idx as usize] {
             return Err(Trap {
                 origin: TrapOrigin::CallIndirectTypesDiffer,
             });
         }
-        self.call(func_addr, sframe, funcs, tables, globals, mems)
+        Ok(func_addr)
     }
 
-    /// Return to the caller of the current function
-    fn return_(&self) -> IntResult {
-        Ok(Return)
+    /// Call a function in place of the current one
+    fn return_call(&self, f_addr: FuncAddr) -> IntResult {
+        Ok(TailCall(f_addr))
+    }
+
+    /// Call a function indirectly in place of the current one
+    fn return_call_indirect(
+        &mut self,
+        idx: Index,
+        tables: &TableInstStore,
+        table_addrs: &[TableAddr],
+        type_ids: &[TypeId],
+    ) -> IntResult {
+        let func_addr = self.indirect_callee(idx, tables, table_addrs, type_ids)?;
+        Ok(TailCall(func_addr))
     }
 
     /// Get the size of the current memory
     fn current_memory(&mut self, memories: &MemInstStore, frame_memories: &[MemAddr]) -> IntResult {
-        self.stack
-            .push(Value::I32(memories.size(frame_memories[0]) as u32));
+        self.push(memories.size(frame_memories[0]) as u32);
         Ok(Continue)
     }
 
@@ -953,189 +1132,438 @@ impl Interpreter {
         memories: &mut MemInstStore,
         frame_memories: &[MemAddr],
     ) -> IntResult {
-        let new_pages = match self.stack.pop().unwrap() {
-            Value::I32(c) => c as usize,
-            _ => unreachable!(),
-        };
+        let new_pages = self.pop::<u32>() as usize;
         if let Some(old_size) = memories.grow(frame_memories[0], new_pages) {
-            self.stack.push(Value::I32(old_size as u32));
+            self.push(old_size as u32);
         } else {
-            self.stack.push(Value::from_i32(-1));
+            self.push(-1i32 as u32);
+        }
+        Ok(Continue)
+    }
+
+    /// Pop the address of a scalar access, adding the static offset to it.
+    /// Both are 32 bits, so the sum cannot overflow.
+    fn pop_addr(&mut self, offset: u32) -> usize {
+        self.pop::<u32>() as usize + offset as usize
+    }
+
+    /// Load memory, with a single bounds check
+    fn load(&mut self, kind: LoadKind, offset: u32, mem: &MemInst) -> IntResult {
+        let addr = self.pop_addr(offset);
+
+        macro_rules! read {
+            ($ty:ty) => {
+                match mem.data.get(addr..addr + mem::size_of::<$ty>()) {
+                    Some(bytes) => <$ty>::from_le_bytes(bytes.try_into().unwrap()),
+                    None => {
+                        return Err(Trap {
+                            origin: TrapOrigin::LoadOutOfMemory,
+                        })
+                    }
+                }
+            };
         }
+
+        let bits = match kind {
+            LoadKind::I32 => read!(u32) as u64,
+            LoadKind::I64 => read!(u64),
+            LoadKind::I32S8 => read!(i8) as u32 as u64,
+            LoadKind::I32U8 => read!(u8) as u64,
+            LoadKind::I32S16 => read!(i16) as u32 as u64,
+            LoadKind::I32U16 => read!(u16) as u64,
+            LoadKind::I64S8 => read!(i8) as u64,
+            LoadKind::I64U8 => read!(u8) as u64,
+            LoadKind::I64S16 => read!(i16) as u64,
+            LoadKind::I64U16 => read!(u16) as u64,
+            LoadKind::I64S32 => read!(i32) as u64,
+            LoadKind::I64U32 => read!(u32) as u64,
+        };
+        self.stack.push(bits);
         Ok(Continue)
     }
 
-    /// Load memory (dispatcher)
-    fn load(
+    /// Store memory, with a single bounds check
+    fn store(&mut self, kind: StoreKind, offset: u32, mem: &mut MemInst) -> IntResult {
+        // The bits of a value are the low bits of its slot
+        let c = self.stack.pop().unwrap();
+        let addr = self.pop_addr(offset);
+
+        macro_rules! write {
+            ($ty:ty) => {{
+                let bytes = (c as $ty).to_le_bytes();
+                match mem.data.get_mut(addr..addr + bytes.len()) {
+                    Some(dst) => dst.copy_from_slice(&bytes),
+                    None => {
+                        return Err(Trap {
+                            origin: TrapOrigin::StoreOutOfMemory,
+                        })
+                    }
+                }
+            }};
+        }
+
+        match kind {
+            StoreKind::Bits8 => write!(u8),
+            StoreKind::Bits16 => write!(u16),
+            StoreKind::Bits32 => write!(u32),
+            StoreKind::Bits64 => write!(u64),
+        }
+        Ok(Continue)
+    }
+
+    /// Pop the address of an atomic access, returning the effective address
+    /// and the size of the access in bytes. Atomic accesses must be naturally
+    /// aligned.
+    fn pop_atomic_addr(
         &mut self,
-        memop: &LoadOp,
+        memop: &AtomicOp,
+        mem: &MemInst,
+        out_of_memory: TrapOrigin,
+    ) -> Result<(usize, usize), Trap> {
+        let size_in_bytes = memop.opt.unwrap_or_else(|| memop.type_.bit_width()) as usize / 8;
+        let offset = self.pop::<u32>() as usize + memop.offset as usize;
+
+        if offset + size_in_bytes > mem.data.len() {
+            return Err(Trap {
+                origin: out_of_memory,
+            });
+        }
+        // Access sizes are powers of two
+        if offset & (size_in_bytes - 1) != 0 {
+            return Err(Trap {
+                origin: TrapOrigin::UnalignedAtomic,
+            });
+        }
+        Ok((offset, size_in_bytes))
+    }
+
+    // The execution is single-threaded, so the atomic instructions below are
+    // implemented with plain sequential reads and writes.
+
+    fn atomic_load(
+        &mut self,
+        memop: &AtomicOp,
         memories: &MemInstStore,
         frame_memories: &[MemAddr],
     ) -> IntResult {
-        use crate::types::Value as Tv;
-        use crate::types::{Float, Int};
-
         let mem = &memories[frame_memories[0]];
-        let offset = match self.stack.pop().unwrap() {
-            Value::I32(c) => c as usize + memop.offset as usize,
-            _ => unreachable!(),
+        let (offset, size) = self.pop_atomic_addr(memop, mem, TrapOrigin::LoadOutOfMemory)?;
+        let bits = read_bits(mem, offset, size);
+        self.stack.push(bits);
+        Ok(Continue)
+    }
+
+    fn atomic_store(
+        &mut self,
+        memop: &AtomicOp,
+        memories: &mut MemInstStore,
+        frame_memories: &[MemAddr],
+    ) -> IntResult {
+        let c = self.stack.pop().unwrap();
+        let mem = &mut memories[frame_memories[0]];
+        let (offset, size) = self.pop_atomic_addr(memop, mem, TrapOrigin::StoreOutOfMemory)?;
+        write_bits(mem, offset, size, c);
+        Ok(Continue)
+    }
+
+    /// Apply `op` to the value in memory, pushing the value it replaced
+    fn atomic_rmw(
+        &mut self,
+        op: AtomicRmwOp,
+        memop: &AtomicOp,
+        memories: &mut MemInstStore,
+        frame_memories: &[MemAddr],
+    ) -> IntResult {
+        let c = self.stack.pop().unwrap();
+        let mem = &mut memories[frame_memories[0]];
+        let (offset, size) = self.pop_atomic_addr(memop, mem, TrapOrigin::StoreOutOfMemory)?;
+        let old = read_bits(mem, offset, size);
+        // Narrow results are truncated when written back
+        let new = match op {
+            AtomicRmwOp::Add => old.wrapping_add(c),
+            AtomicRmwOp::Sub => old.wrapping_sub(c),
+            AtomicRmwOp::And => old & c,
+            AtomicRmwOp::Or => old | c,
+            AtomicRmwOp::Xor => old ^ c,
+            AtomicRmwOp::Xchg => c,
         };
-        let (size_in_bits, signed) = memop.opt.unwrap_or((memop.type_.bit_width(), false));
-        let size_in_bytes: usize = (size_in_bits as usize) / 8;
+        write_bits(mem, offset, size, new);
+        self.stack.push(old);
+        Ok(Continue)
+    }
 
-        if offset + size_in_bytes > mem.data.len() {
+    /// Replace the value in memory if it equals the expected one, pushing the
+    /// value that was read
+    fn atomic_cmpxchg(
+        &mut self,
+        memop: &AtomicOp,
+        memories: &mut MemInstStore,
+        frame_memories: &[MemAddr],
+    ) -> IntResult {
+        let replacement = self.stack.pop().unwrap();
+        let expected = self.stack.pop().unwrap();
+        let mem = &mut memories[frame_memories[0]];
+        let (offset, size) = self.pop_atomic_addr(memop, mem, TrapOrigin::StoreOutOfMemory)?;
+        let old = read_bits(mem, offset, size);
+        // The expected value is wrapped to the size of the access
+        if expected.to_le_bytes()[..size] == old.to_le_bytes()[..size] {
+            write_bits(mem, offset, size, replacement);
+        }
+        self.stack.push(old);
+        Ok(Continue)
+    }
+
+    /// Wait for a notification. No other thread can ever notify a macro, so
+    /// a wait either returns right away or would block forever.
+    fn atomic_wait(
+        &mut self,
+        memop: &AtomicOp,
+        memories: &MemInstStore,
+        frame_memories: &[MemAddr],
+    ) -> IntResult {
+        let timeout = self.pop::<u64>() as i64;
+        let expected = self.stack.pop().unwrap();
+        let mem = &memories[frame_memories[0]];
+        let (offset, size) = self.pop_atomic_addr(memop, mem, TrapOrigin::LoadOutOfMemory)?;
+
+        if !mem.shared {
             return Err(Trap {
-                origin: TrapOrigin::LoadOutOfMemory,
+                origin: TrapOrigin::WaitOnUnsharedMemory,
             });
         }
-        let bits: &[u8] = &mem.data[offset..(offset + size_in_bytes)];
+        let res = if read_bits(mem, offset, size) != expected {
+            1 // "not-equal"
+        } else if timeout >= 0 {
+            2 // "timed-out"
+        } else {
+            return Err(Trap {
+                origin: TrapOrigin::WaitForever,
+            });
+        };
+        self.push::<u32>(res);
+        Ok(Continue)
+    }
 
-        let res = match (size_in_bits, signed, memop.type_) {
-            (8, false, Tv::Int(Int::I32)) => Value::I32(bits[0] as u32),
-            (8, true, Tv::Int(Int::I32)) => Value::I32(bits[0] as i8 as u32),
-            (16, false, Tv::Int(Int::I32)) => {
-                Value::I32(u16::from_le_bytes([bits[0], bits[1]]) as u32)
-            }
-            (16, true, Tv::Int(Int::I32)) => {
-                Value::I32(i16::from_le_bytes([bits[0], bits[1]]) as u32)
-            }
-            (32, false, Tv::Int(Int::I32)) => {
-                Value::I32(u32::from_le_bytes([bits[0], bits[1], bits[2], bits[3]]) as u32)
-            }
-            (32, true, Tv::Int(Int::I32)) => {
-                Value::I32(i32::from_le_bytes([bits[0], bits[1], bits[2], bits[3]]) as u32)
-            }
+    /// Wake up waiting threads, of which there are none
+    fn atomic_notify(
+        &mut self,
+        memop: &AtomicOp,
+        memories: &MemInstStore,
+        frame_memories: &[MemAddr],
+    ) -> IntResult {
+        let _count = self.stack.pop().unwrap();
+        let mem = &memories[frame_memories[0]];
+        self.pop_atomic_addr(memop, mem, TrapOrigin::LoadOutOfMemory)?;
+        self.push(0u32);
+        Ok(Continue)
+    }
 
-            (8, false, Tv::Int(Int::I64)) => Value::I64(bits[0] as u64),
-            (8, true, Tv::Int(Int::I64)) => Value::I64(bits[0] as i8 as u64),
-            (16, false, Tv::Int(Int::I64)) => {
-                Value::I64(u16::from_le_bytes([bits[0], bits[1]]) as u64)
-            }
-            (16, true, Tv::Int(Int::I64)) => {
-                Value::I64(i16::from_le_bytes([bits[0], bits[1]]) as u64)
-            }
-            (32, false, Tv::Int(Int::I64)) => {
-                Value::I64(u32::from_le_bytes([bits[0], bits[1], bits[2], bits[3]]) as u64)
+    /// Load a vector from memory
+    fn vload(
+        &mut self,
+        memop: &VMemOp<VLoadKind>,
+        memories: &MemInstStore,
+        frame_memories: &[MemAddr],
+    ) -> IntResult {
+        // The vector to insert a lane into sits above the address
+        let v = match memop.kind {
+            VLoadKind::Lane(..) => self.pop_v128(),
+            _ => 0,
+        };
+        let mem = &memories[frame_memories[0]];
+        let offset = self.pop::<u32>() as usize + memop.offset as usize;
+        let size_in_bytes = match memop.kind {
+            VLoadKind::Full => 16,
+            VLoadKind::Extend(..) => 8,
+            VLoadKind::Splat(shape) | VLoadKind::Zero(shape) | VLoadKind::Lane(shape, _) => {
+                shape.lane_bits() as usize / 8
             }
-            (32, true, Tv::Int(Int::I64)) => {
-                Value::I64(i32::from_le_bytes([bits[0], bits[1], bits[2], bits[3]]) as u64)
+        };
+
+        if offset + size_in_bytes > mem.data.len() {
+            return Err(Trap {
+                origin: TrapOrigin::LoadOutOfMemory,
+            });
+        }
+        let mut bytes = [0; 16];
+        bytes[..size_in_bytes].copy_from_slice(&mem.data[offset..(offset + size_in_bytes)]);
+        let bits = u128::from_le_bytes(bytes);
+
+        let res = match memop.kind {
+            VLoadKind::Full | VLoadKind::Zero(_) => bits,
+            VLoadKind::Extend(shape, signed) => {
+                simd::iunary(shape, &VIUnOp::ExtendLow(signed), bits)
             }
-            (64, false, Tv::Int(Int::I64)) => Value::I64(u64::from_le_bytes([
-                bits[0], bits[1], bits[2], bits[3], bits[4], bits[5], bits[6], bits[7],
-            ]) as u64),
-            (64, true, Tv::Int(Int::I64)) => Value::I64(i64::from_le_bytes([
-                bits[0], bits[1], bits[2], bits[3], bits[4], bits[5], bits[6], bits[7],
-            ]) as u64),
-
-            (32, false, Tv::Float(Float::F32)) => Value::F32(f32::from_bits(u32::from_le_bytes([
-                bits[0], bits[1], bits[2], bits[3],
-            ]))),
-            (64, false, Tv::Float(Float::F64)) => Value::F64(f64::from_bits(u64::from_le_bytes([
-                bits[0], bits[1], bits[2], bits[3], bits[4], bits[5], bits[6], bits[7],
-            ]))),
-            _ => unreachable!(),
+            VLoadKind::Splat(shape) => simd::splat(shape, bits),
+            VLoadKind::Lane(shape, lane) => simd::replace_lane(shape, v, lane, bits),
         };
-        self.stack.push(res);
+        self.push_v128(res);
         Ok(Continue)
     }
 
-    /// Store memory (dispatcher)
-    fn store(
+    /// Store a vector to memory
+    fn vstore(
         &mut self,
-        memop: &StoreOp,
+        memop: &VMemOp<VStoreKind>,
         memories: &mut MemInstStore,
         frame_memories: &[MemAddr],
     ) -> IntResult {
-        use crate::types::Value as Tv;
-        use crate::types::{Float, Int};
-
+        let v = self.pop_v128();
         let mem = &mut memories[frame_memories[0]];
-        let c = self.stack.pop().unwrap();
-        let offset = match self.stack.pop().unwrap() {
-            Value::I32(c) => c as usize + memop.offset as usize,
-            _ => unreachable!(),
+        let offset = self.pop::<u32>() as usize + memop.offset as usize;
+        let (bits, size_in_bytes) = match memop.kind {
+            VStoreKind::Full => (v, 16),
+            VStoreKind::Lane(shape, lane) => {
+                (simd::lane(shape, v, lane), shape.lane_bits() as usize / 8)
+            }
         };
-        let size_in_bits = memop.opt.unwrap_or(memop.type_.bit_width());
-        let size_in_bytes: usize = (size_in_bits as usize) / 8;
 
         if offset + size_in_bytes > mem.data.len() {
             return Err(Trap {
                 origin: TrapOrigin::StoreOutOfMemory,
             });
         }
-        let bits = &mut mem.data[offset..(offset + size_in_bytes)];
-        match (size_in_bits, memop.type_, c) {
-            (8, Tv::Int(Int::I32), Value::I32(c)) => bits[0] = c as u8,
-            (16, Tv::Int(Int::I32), Value::I32(c)) => {
-                let b = (c as u16).to_le_bytes();
-                bits[0] = b[0];
-                bits[1] = b[1];
-            }
-            (32, Tv::Int(Int::I32), Value::I32(c)) => {
-                let b = (c as u32).to_le_bytes();
-                bits[0] = b[0];
-                bits[1] = b[1];
-                bits[2] = b[2];
-                bits[3] = b[3];
-            }
+        mem.data[offset..(offset + size_in_bytes)]
+            .copy_from_slice(&bits.to_le_bytes()[..size_in_bytes]);
+        Ok(Continue)
+    }
 
-            (8, Tv::Int(Int::I64), Value::I64(c)) => bits[0] = c as u8,
-            (16, Tv::Int(Int::I64), Value::I64(c)) => {
-                let b = (c as u16).to_le_bytes();
-                bits[0] = b[0];
-                bits[1] = b[1];
-            }
-            (32, Tv::Int(Int::I64), Value::I64(c)) => {
-                let b = (c as u32).to_le_bytes();
-                bits[0] = b[0];
-                bits[1] = b[1];
-                bits[2] = b[2];
-                bits[3] = b[3];
-            }
-            (64, Tv::Int(Int::I64), Value::I64(c)) => {
-                let b = (c as u64).to_le_bytes();
-                bits[0] = b[0];
-                bits[1] = b[1];
-                bits[2] = b[2];
-                bits[3] = b[3];
-                bits[4] = b[4];
-                bits[5] = b[5];
-                bits[6] = b[6];
-                bits[7] = b[7];
-            }
+    /// Replicate a scalar to all the lanes of a vector
+    fn splat(&mut self, shape: Shape) -> IntResult {
+        let bits = self.stack.pop().unwrap() as u128;
+        self.push_v128(simd::splat(shape, bits));
+        Ok(Continue)
+    }
 
-            (32, Tv::Float(Float::F32), Value::F32(c)) => {
-                let b = (c as f32).to_bits().to_le_bytes();
-                bits[0] = b[0];
-                bits[1] = b[1];
-                bits[2] = b[2];
-                bits[3] = b[3];
-            }
-            (64, Tv::Float(Float::F64), Value::F64(c)) => {
-                let b = (c as f64).to_bits().to_le_bytes();
-                bits[0] = b[0];
-                bits[1] = b[1];
-                bits[2] = b[2];
-                bits[3] = b[3];
-                bits[4] = b[4];
-                bits[5] = b[5];
-                bits[6] = b[6];
-                bits[7] = b[7];
-            }
-            _ => unreachable!(),
+    fn extract_lane(&mut self, shape: Shape, lane: u8, signed: bool) -> IntResult {
+        let bits = simd::lane(shape, self.pop_v128(), lane);
+        // Unsigned lanes are already zero-extended
+        let res = match shape {
+            Shape::I8x16 if signed => bits as i8 as u32 as u64,
+            Shape::I16x8 if signed => bits as i16 as u32 as u64,
+            _ => bits as u64,
         };
+        self.stack.push(res);
+        Ok(Continue)
+    }
+
+    fn replace_lane(&mut self, shape: Shape, lane: u8) -> IntResult {
+        let bits = self.stack.pop().unwrap() as u128;
+        let v = self.pop_v128();
+        self.push_v128(simd::replace_lane(shape, v, lane, bits));
+        Ok(Continue)
+    }
+
+    /// Dispatch a vector operation of type [v128] -> [v128]
+    fn vunary<F: Fn(u128) -> u128>(&mut self, f: F) -> IntResult {
+        let v = self.pop_v128();
+        self.push_v128(f(v));
+        Ok(Continue)
+    }
+
+    /// Dispatch a vector operation of type [v128 v128] -> [v128]
+    fn vbin<F: Fn(u128, u128) -> u128>(&mut self, f: F) -> IntResult {
+        let b = self.pop_v128();
+        let a = self.pop_v128();
+        self.push_v128(f(a, b));
+        Ok(Continue)
+    }
+
+    /// Dispatch a vector operation of type [v128] -> [i32]
+    fn vtest<F: Fn(u128) -> u32>(&mut self, f: F) -> IntResult {
+        let v = self.pop_v128();
+        self.push(f(v));
+        Ok(Continue)
+    }
+
+    fn vshift(&mut self, shape: Shape, op: &VShiftOp) -> IntResult {
+        let n = self.pop::<u32>();
+        let v = self.pop_v128();
+        self.push_v128(simd::shift(shape, op, v, n));
+        Ok(Continue)
+    }
+
+    fn bitselect(&mut self) -> IntResult {
+        let c = self.pop_v128();
+        let b = self.pop_v128();
+        let a = self.pop_v128();
+        self.push_v128((a & c) | (b & !c));
         Ok(Continue)
     }
 
-    /// Pops two values from the stack, assuming that the stack is large enough to do so.
-    fn pop2(&mut self) -> (Value, Value) {
-        let b = self.stack.pop().unwrap();
-        let a = self.stack.pop().unwrap();
+    /// Pushes a scalar to the stack
+    fn push<T: Slot>(&mut self, v: T) {
+        self.stack.push(v.to_slot());
+    }
+
+    /// Pops a scalar from the stack, validation ensures it has the right type.
+    fn pop<T: Slot>(&mut self) -> T {
+        T::from_slot(self.stack.pop().unwrap())
+    }
+
+    /// Pops two scalars from the stack, assuming that the stack is large enough to do so.
+    fn pop2<T: Slot>(&mut self) -> (T, T) {
+        let b = self.pop();
+        let a = self.pop();
         (a, b)
     }
+
+    fn push_v128(&mut self, v: u128) {
+        self.stack.push(v as u64);
+        self.stack.push((v >> 64) as u64);
+    }
+
+    /// Pops a vector from the stack, validation ensures it has the right type.
+    fn pop_v128(&mut self) -> u128 {
+        let high = self.stack.pop().unwrap() as u128;
+        let low = self.stack.pop().unwrap() as u128;
+        (high << 64) | low
+    }
+
+    /// Push a tagged value, in its untagged representation
+    pub fn push_value(&mut self, v: Value) {
+        match v {
+            Value::I32(c) => self.push(c),
+            Value::I64(c) => self.push(c),
+            Value::F32(c) => self.push(c),
+            Value::F64(c) => self.push(c),
+            Value::V128(c) => self.push_v128(c),
+            // Null is zero so that it is the default value of locals
+            Value::ExnRef(exn_addr) => self
+                .stack
+                .push(exn_addr.map_or(0, |addr| addr.index() as u64 + 1)),
+        }
+    }
+
+    /// Pop a value of the given type, tagging it
+    pub fn pop_value(&mut self, type_: types::Value) -> Value {
+        match type_ {
+            types::Value::Int(types::Int::I32) => Value::I32(self.pop()),
+            types::Value::Int(types::Int::I64) => Value::I64(self.pop()),
+            types::Value::Float(types::Float::F32) => Value::F32(self.pop()),
+            types::Value::Float(types::Float::F64) => Value::F64(self.pop()),
+            types::Value::V128 => Value::V128(self.pop_v128()),
+            types::Value::ExnRef => match self.stack.pop().unwrap() {
+                0 => Value::ExnRef(None),
+                slot => Value::ExnRef(Some(ExnAddr::new(slot as usize - 1))),
+            },
+        }
+    }
+}
+
+/// Read `size` bytes of memory as a zero-extended little-endian integer
+fn read_bits(mem: &MemInst, offset: usize, size: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes[..size].copy_from_slice(&mem.data[offset..(offset + size)]);
+    u64::from_le_bytes(bytes)
 }
 
-/// Evaluate a constant expression (sequence of a single instruction) and return its value
+/// Write the `size` low bytes of `bits` to memory
+fn write_bits(mem: &mut MemInst, offset: usize, size: usize, bits: u64) {
+    mem.data[offset..(offset + size)].copy_from_slice(&bits.to_le_bytes()[..size]);
+}
+
+/// Evaluate a constant expression and return its value
 ///
 /// While this functionality is already provided by the default interpreter
 /// mode, this version works with a more limited context and less allocations.
@@ -1146,13 +1574,32 @@ pub fn eval_const_expr(
     mod_globals: &[GlobalAddr],
     expr: &[Instr],
 ) -> Value {
-    if expr.len() != 1 {
-        panic!("contant expressions must have exactly only one instruction");
+    let mut stack = Vec::new();
+
+    for instr in expr {
+        let v = match *instr {
+            Instr::Const(c) => c,
+            Instr::GetGlobal(idx) => globals[mod_globals[idx as usize]].value,
+            Instr::IBin(_, ref op) => {
+                let b = stack.pop().expect("constant expression stack underflow");
+                let a = stack.pop().expect("constant expression stack underflow");
+                match (a, b, op) {
+                    (Value::I32(a), Value::I32(b), IBinOp::Add) => Value::I32(a.add(b)),
+                    (Value::I32(a), Value::I32(b), IBinOp::Sub) => Value::I32(a.sub(b)),
+                    (Value::I32(a), Value::I32(b), IBinOp::Mul) => Value::I32(a.mul(b)),
+                    (Value::I64(a), Value::I64(b), IBinOp::Add) => Value::I64(a.add(b)),
+                    (Value::I64(a), Value::I64(b), IBinOp::Sub) => Value::I64(a.sub(b)),
+                    (Value::I64(a), Value::I64(b), IBinOp::Mul) => Value::I64(a.mul(b)),
+                    _ => panic!("not a constant expression"),
+                }
+            }
+            _ => panic!("not a constant expression"),
+        };
+        stack.push(v);
     }
 
-    match expr[0] {
-        Instr::Const(c) => c,
-        Instr::GetGlobal(idx) => globals[mod_globals[idx as usize]].value,
-        _ => panic!("not a constant expression"),
+    if stack.len() != 1 {
+        panic!("contant expressions must leave exactly one value");
     }
+    stack[0]
 }
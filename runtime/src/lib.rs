@@ -2,16 +2,19 @@
 pub mod interpreter;
 pub mod ast;
 mod binary;
+mod compact;
 pub mod error;
+mod lower;
 pub mod ops;
 pub mod runtime;
+mod simd;
 pub mod types;
 mod valid;
 pub mod values;
 
 pub use crate::ast::Module;
 pub use crate::error::Error;
-pub use crate::runtime::{ExternVal, HostFunc};
+pub use crate::runtime::{Caller, ExternVal, HostFunc};
 pub use crate::types::Extern;
 pub use crate::values::Value;
 
@@ -21,8 +24,8 @@ pub use crate::runtime::{FuncAddr, GlobalAddr, MemAddr, ModuleInst, TableAddr, P
 use crate::error::E;
 use crate::interpreter::{eval_const_expr, Trap, TrapOrigin};
 use crate::runtime::*;
+use std::any::Any;
 use std::collections::HashMap;
-use std::io::{Read, Seek};
 use std::rc::Rc;
 
 // Do not publish internal fields of the Store struct
@@ -31,8 +34,12 @@ pub struct Store {
     tables: TableInstStore,
     mems: MemInstStore,
     globals: GlobalInstStore,
+    tags: TagInstStore,
 
     types_map: TypeHashMap,
+    type_ids: TypeIds,
+    max_call_depth: usize,
+    host_data: Option<Box<dyn Any>>,
 }
 
 /// Return the empty store
@@ -42,14 +49,48 @@ pub fn init_store() -> Store {
         tables: TableInstStore::new(),
         mems: MemInstStore::new(),
         globals: GlobalInstStore::new(),
+        tags: TagInstStore::new(),
 
         types_map: HashMap::new(),
+        type_ids: TypeIds::new(),
+        max_call_depth: interpreter::DEFAULT_MAX_CALL_DEPTH,
+        host_data: None,
     }
 }
 
-/// Decode a binary module
-pub fn decode_module<R: Read + Seek>(reader: R) -> Result<ast::Module, Error> {
-    binary::decode(reader).map_err(|_| Error(E::DecodeModuleFailed))
+/// Set the maximum number of nested function calls, beyond which invocations
+/// fail with a stack overflow
+pub fn set_max_call_depth(store: &mut Store, depth: usize) {
+    store.max_call_depth = depth;
+}
+
+/// Set the user data handed to host functions through `Caller::data`
+#[cfg(feature = "test")]
+pub fn set_host_data<T: Any>(store: &mut Store, data: T) {
+    store.host_data = Some(Box::new(data));
+}
+
+/// Decode a binary module held in memory
+pub fn decode_module(bytes: &[u8]) -> Result<ast::Module<'_>, Error> {
+    binary::decode(bytes).map_err(|_| Error(E::DecodeModuleFailed))
+}
+
+/// Convert a binary module to the compact format, failing if it is invalid
+#[cfg(feature = "test")]
+pub fn compact_module(wasm: &[u8]) -> Result<Vec<u8>, Error> {
+    compact::encode::encode(wasm).map_err(Error)
+}
+
+/// Decode a module in the compact format. Its function bodies come already
+/// lowered, and are not validated again.
+pub fn decode_compact_module(bytes: &[u8]) -> Result<ast::Module<'_>, Error> {
+    compact::decode(bytes).map_err(Error)
+}
+
+/// Number of function bodies validated on their first call by this thread
+#[cfg(feature = "test")]
+pub fn validated_funcs() -> usize {
+    VALIDATED_FUNCS.with(|count| count.get())
 }
 
 /// Validate a module
@@ -66,22 +107,19 @@ pub fn validate_module(module: &ast::Module) -> Option<Error> {
 pub fn module_imports<'a>(
     module: &'a ast::Module,
 ) -> impl Iterator<Item = (&'a str, &'a str, types::Extern)> + 'a {
-    assert!(valid::is_valid(module));
-
-    module.imports.iter().map(move |import| {
-        (
-            import.module.as_str(),
-            import.name.as_str(),
-            import.type_(module),
-        )
-    })
+    assert!(valid::check_module(module).is_some());
+
+    module
+        .imports
+        .iter()
+        .map(move |import| (import.module, import.name, import.type_(module)))
 }
 
 /// List module exports with their types
 pub fn module_exports<'a>(
     module: &'a ast::Module,
 ) -> impl Iterator<Item = (&'a str, types::Extern)> + 'a {
-    assert!(valid::is_valid(module));
+    assert!(valid::check_module(module).is_some());
 
     // Imports can be exported
     // "The index space for functions, tables, memories and globals includes respective imports declared in the same module."
@@ -90,6 +128,7 @@ pub fn module_exports<'a>(
     let mut table_import_types = Vec::new();
     let mut mem_import_types = Vec::new();
     let mut global_import_types = Vec::new();
+    let mut tag_import_types = Vec::new();
     for import in &module.imports {
         use crate::ast::*;
         match import.desc {
@@ -97,6 +136,7 @@ pub fn module_exports<'a>(
             ImportDesc::Table(ref type_) => table_import_types.push(type_.clone()),
             ImportDesc::Memory(ref type_) => mem_import_types.push(type_.clone()),
             ImportDesc::Global(ref type_) => global_import_types.push(type_.clone()),
+            ImportDesc::Tag(idx) => tag_import_types.push(module.types[idx as usize].clone()),
         };
     }
 
@@ -142,8 +182,17 @@ pub fn module_exports<'a>(
                     Extern::Global(module.globals[idx - len].type_.clone())
                 }
             }
+            ExportDesc::Tag(idx) => {
+                let len = tag_import_types.len();
+                let idx = idx as usize;
+                if idx < len {
+                    Extern::Tag(tag_import_types[idx].clone())
+                } else {
+                    Extern::Tag(module.types[module.tags[idx - len].type_index as usize].clone())
+                }
+            }
         };
-        (export.name.as_ref(), export_type)
+        (export.name, export_type)
     })
 }
 
@@ -159,9 +208,21 @@ pub fn get_export(inst: &ModuleInst, name: &str) -> Result<ExternVal, Error> {
 
 /// Allocate a host function
 pub fn alloc_func(store: &mut Store, functype: &types::Func, hostfunc: HostFunc) -> FuncAddr {
+    let type_id = store.type_ids.intern(functype);
     store
         .funcs
-        .alloc_host(&mut store.types_map, functype, hostfunc)
+        .alloc_host(&mut store.types_map, functype, type_id, hostfunc)
+}
+
+/// Allocate a host exception tag
+pub fn alloc_tag(store: &mut Store, tagtype: &types::Func) -> TagAddr {
+    store.tags.alloc(&mut store.types_map, tagtype)
+}
+
+/// Get the type of an exception tag
+pub fn type_tag(store: &Store, tagaddr: TagAddr) -> types::Func {
+    assert!(store.tags.contains(tagaddr));
+    store.tags[tagaddr].type_.clone()
 }
 
 /// Get the type of a function
@@ -202,30 +263,74 @@ pub fn invoke_func(
         return Err(Error(E::ArgumentTypeMismatch));
     }
 
-    let mut int = interpreter::Interpreter::new();
-    int.stack.extend(args);
+    // Exceptions live only as long as the invocation which threw them, so an
+    // exnref from elsewhere refers to nothing in the new one
+    if args
+        .iter()
+        .any(|val| matches!(val, values::Value::ExnRef(Some(_))))
+    {
+        return Err(Error(E::ForeignExnRef));
+    }
+
+    let mut int = interpreter::Interpreter::new(store.max_call_depth);
+    for arg in args {
+        int.push_value(arg);
+    }
 
-    let sframe = interpreter::StackFrame::new(None);
-    match int.call(
+    // Host functions reach the user data through the interpreter for the
+    // duration of the call
+    int.host_data = store.host_data.take();
+    let res = int.call(
         funcaddr,
-        &sframe,
         &store.funcs,
         &store.tables,
         &mut store.globals,
         &mut store.mems,
-    ) {
+    );
+    store.host_data = int.host_data.take();
+
+    match res {
         Err(Trap {
             origin: TrapOrigin::StackOverflow,
         }) => Err(Error(E::StackOverflow)),
         Err(err) => Err(Error(E::CodeTrapped(err))),
+        Ok(interpreter::Control::Throw(_)) => Err(Error(E::UncaughtException)),
         _ => {
-            let end_drain = int.stack.len() - functype.result.len();
-            int.stack.drain(0..end_drain);
-            Ok(int.stack)
+            let mut results: Vec<_> = functype
+                .result
+                .iter()
+                .rev()
+                .map(|&type_| int.pop_value(type_))
+                .collect();
+            results.reverse();
+            Ok(results)
         }
     }
 }
 
+/// Count the sites where a superinstruction was emitted in the functions of
+/// the store that have been called, by the pattern of instructions it replaces,
+/// the most frequent first. This is a static count over the lowered code, not
+/// how many times each superinstruction was executed.
+pub fn fusion_sites(store: &Store) -> Vec<(&'static str, usize)> {
+    let mut counts = HashMap::new();
+    for addr in 0..store.funcs.len() {
+        if let FuncInst::Module(ref f) = store.funcs[FuncAddr::new(addr)] {
+            let code = match f.lowered() {
+                Some(code) => code,
+                None => continue,
+            };
+            for fusion in code.ops.iter().filter_map(lower::Op::fusion) {
+                *counts.entry(fusion).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    counts
+}
+
 /// Allocate a table
 #[cfg(feature = "test")]
 pub fn alloc_table(store: &mut Store, tabletype: &types::Table) -> TableAddr {
@@ -256,7 +361,7 @@ pub fn read_table(
     if addr >= ti.elem.len() {
         Err(Error::InvalidTableRead)
     } else {
-        Ok(ti.elem[addr])
+        Ok(ti.elem[addr].map(|(funcaddr, _)| funcaddr))
     }
 }
 
@@ -269,11 +374,12 @@ pub fn write_table(
     funcaddr: Option<FuncAddr>,
 ) -> Option<Error> {
     assert!(store.tables.contains(tableaddr));
+    let elem = funcaddr.map(|funcaddr| (funcaddr, store.funcs[funcaddr].type_id()));
     let ti = &mut store.tables[tableaddr];
     if addr >= ti.elem.len() {
         Some(Error::InvalidTableWrite)
     } else {
-        ti.elem[addr] = funcaddr;
+        ti.elem[addr] = elem;
         None
     }
 }
@@ -356,7 +462,6 @@ pub fn grow_mem(store: &mut Store, memaddr: MemAddr, new: usize) -> Option<Error
 }
 
 /// Allocate a new global
-#[cfg(feature = "test")]
 pub fn alloc_global(
     store: &mut Store,
     globaltype: &types::Global,
@@ -408,10 +513,11 @@ pub fn instantiate_module(
     module: ast::Module,
     extern_vals: &[ExternVal],
 ) -> Result<Rc<ModuleInst>, Error> {
-    // fail if module is invalid
-    if !valid::is_valid(&module) {
-        return Err(Error(E::InvalidModule));
-    }
+    // fail if module is invalid; function bodies are checked when first called
+    let context = match valid::check_module(&module) {
+        Some(context) => context,
+        None => return Err(Error(E::InvalidModule)),
+    };
 
     // ensure that the number of provided exports matches the number of imports
     if extern_vals.len() != module.imports.len() {
@@ -419,10 +525,7 @@ pub fn instantiate_module(
     }
 
     // resolve imports, type-cheking them in the process
-    let mut imported_funcs = Vec::new();
-    let mut imported_tables = Vec::new();
-    let mut imported_memories = Vec::new();
-    let mut imported_globals = Vec::new();
+    let mut imported = Imports::default();
 
     for (&extern_val, import) in extern_vals.iter().zip(module.imports.iter()) {
         let ext_type = store
@@ -435,10 +538,11 @@ pub fn instantiate_module(
             return Err(Error(E::ImportTypeMismatch));
         }
         match extern_val {
-            ExternVal::Func(addr) => imported_funcs.push(addr),
-            ExternVal::Table(addr) => imported_tables.push(addr),
-            ExternVal::Memory(addr) => imported_memories.push(addr),
-            ExternVal::Global(addr) => imported_globals.push(addr),
+            ExternVal::Func(addr) => imported.funcs.push(addr),
+            ExternVal::Table(addr) => imported.tables.push(addr),
+            ExternVal::Memory(addr) => imported.memories.push(addr),
+            ExternVal::Global(addr) => imported.globals.push(addr),
+            ExternVal::Tag(addr) => imported.tags.push(addr),
         }
     }
 
@@ -446,26 +550,26 @@ pub fn instantiate_module(
     let global_vals = module
         .globals
         .iter()
-        .map(|g| eval_const_expr(&store.globals, &imported_globals, &g.value))
+        .map(|g| eval_const_expr(&store.globals, &imported.globals, &g.value))
         .collect();
 
     // check that the module does not try to init too many elements
     let mut elem_offsets = Vec::new();
     for elem in &module.elems {
-        let offset = match eval_const_expr(&store.globals, &imported_globals, &elem.offset) {
+        let offset = match eval_const_expr(&store.globals, &imported.globals, &elem.offset) {
             values::Value::I32(c) => c as usize,
             _ => unreachable!(),
         };
         elem_offsets.push(offset);
 
         let table_size = {
-            let is_imported = (elem.index as usize) < imported_tables.len();
+            let is_imported = (elem.index as usize) < imported.tables.len();
             if is_imported {
-                store.tables[imported_tables[elem.index as usize]]
+                store.tables[imported.tables[elem.index as usize]]
                     .elem
                     .len()
             } else {
-                let module_index = elem.index as usize - imported_tables.len();
+                let module_index = elem.index as usize - imported.tables.len();
                 module.tables[module_index].type_.limits.min as usize
             }
         };
@@ -478,20 +582,20 @@ pub fn instantiate_module(
     // check that the module does not try to init too much memory
     let mut data_offsets = Vec::new();
     for data in &module.data {
-        let offset = match eval_const_expr(&store.globals, &imported_globals, &data.offset) {
+        let offset = match eval_const_expr(&store.globals, &imported.globals, &data.offset) {
             values::Value::I32(c) => c as usize,
             _ => unreachable!(),
         };
         data_offsets.push(offset);
 
         let memory_size = {
-            let is_imported = (data.index as usize) < imported_memories.len();
+            let is_imported = (data.index as usize) < imported.memories.len();
             if is_imported {
-                store.mems[imported_memories[data.index as usize]]
+                store.mems[imported.memories[data.index as usize]]
                     .data
                     .len()
             } else {
-                let module_index = data.index as usize - imported_memories.len();
+                let module_index = data.index as usize - imported.memories.len();
                 module.memories[module_index].type_.limits.min as usize * PAGE_SIZE
             }
         };
@@ -505,45 +609,73 @@ pub fn instantiate_module(
     allocate_and_init_module(
         store,
         module,
-        imported_funcs,
-        imported_tables,
-        imported_memories,
-        imported_globals,
+        context,
+        imported,
         global_vals,
         elem_offsets,
         data_offsets,
     )
 }
 
+// Addresses of the imports of a module, by kind
+#[derive(Default)]
+struct Imports {
+    funcs: Vec<FuncAddr>,
+    tables: Vec<TableAddr>,
+    memories: Vec<MemAddr>,
+    globals: Vec<GlobalAddr>,
+    tags: Vec<TagAddr>,
+}
+
 fn allocate_and_init_module(
     store: &mut Store,
     module: ast::Module,
-    extern_funcs: Vec<FuncAddr>,
-    extern_tables: Vec<TableAddr>,
-    extern_memories: Vec<MemAddr>,
-    extern_globals: Vec<GlobalAddr>,
+    context: valid::ModContext,
+    imported: Imports,
     vals: Vec<values::Value>,
     elem_offsets: Vec<usize>,
     data_offsets: Vec<usize>,
 ) -> Result<Rc<ModuleInst>, Error> {
-    let mut inst = ModuleInst::new();
+    let mut inst = ModuleInst::new(context);
 
-    // init types
+    // init types, interning them for indirect calls
+    inst.type_ids = module
+        .types
+        .iter()
+        .map(|type_| store.type_ids.intern(type_))
+        .collect();
     inst.types = module.types;
 
     // init imports
-    inst.func_addrs.extend(extern_funcs);
-    inst.table_addrs.extend(extern_tables);
-    inst.mem_addrs.extend(extern_memories);
-    inst.global_addrs.extend(extern_globals);
+    inst.func_addrs.extend(imported.funcs);
+    inst.table_addrs.extend(imported.tables);
+    inst.mem_addrs.extend(imported.memories);
+    inst.global_addrs.extend(imported.globals);
+    inst.tag_addrs.extend(imported.tags);
+    for import in &module.imports {
+        if let ast::ImportDesc::Tag(idx) = import.desc {
+            inst.tag_types.push(idx);
+        }
+    }
 
     // functions allocation
     // only allocate indices; initialization comes when the module is fully instantiated
+    let mut func_type_ids: Vec<_> = inst
+        .func_addrs
+        .iter()
+        .map(|&addr| store.funcs[addr].type_id())
+        .collect();
     let fsi_min = store.funcs.len();
     let fsi_max = fsi_min + module.funcs.len();
     for addr in fsi_min..fsi_max {
         inst.func_addrs.push(FuncAddr::new(addr));
     }
+    func_type_ids.extend(
+        module
+            .funcs
+            .iter()
+            .map(|func| inst.type_ids[func.type_index as usize]),
+    );
 
     // tables allocation
     for tab in module.tables {
@@ -557,7 +689,8 @@ fn allocate_and_init_module(
         for i in 0..elem.init.len() {
             let funcidx = elem.init[i] as usize;
             let funcaddr = inst.func_addrs[funcidx];
-            store.tables[inst.table_addrs[elem.index as usize]].elem[offset + i] = Some(funcaddr);
+            store.tables[inst.table_addrs[elem.index as usize]].elem[offset + i] =
+                Some((funcaddr, func_type_ids[funcidx]));
         }
     }
 
@@ -571,7 +704,7 @@ fn allocate_and_init_module(
     assert_eq!(module.data.len(), data_offsets.len());
     for (data, offset) in module.data.iter().zip(data_offsets.into_iter()) {
         let mem = &mut store.mems[inst.mem_addrs[data.index as usize]];
-        mem.data[offset..offset + data.init.len()].copy_from_slice(&data.init);
+        mem.data[offset..offset + data.init.len()].copy_from_slice(data.init);
     }
 
     // globals allocation
@@ -584,6 +717,14 @@ fn allocate_and_init_module(
         );
     }
 
+    // tags allocation
+    for tag in &module.tags {
+        let type_ = &inst.types[tag.type_index as usize];
+        inst.tag_addrs
+            .push(store.tags.alloc(&mut store.types_map, type_));
+        inst.tag_types.push(tag.type_index);
+    }
+
     // init exports
     for export in module.exports {
         let extern_val = match export.desc {
@@ -591,9 +732,10 @@ fn allocate_and_init_module(
             ast::ExportDesc::Table(idx) => ExternVal::Table(inst.table_addrs[idx as usize]),
             ast::ExportDesc::Memory(idx) => ExternVal::Memory(inst.mem_addrs[idx as usize]),
             ast::ExportDesc::Global(idx) => ExternVal::Global(inst.global_addrs[idx as usize]),
+            ast::ExportDesc::Tag(idx) => ExternVal::Tag(inst.tag_addrs[idx as usize]),
         };
         inst.exports.push(ExportInst {
-            name: export.name,
+            name: export.name.to_owned(),
             value: extern_val,
         });
     }
@@ -601,11 +743,17 @@ fn allocate_and_init_module(
     // now that the module is fully instantiated, we can initialize the functions and put
     // them into the store
     let inst = Rc::new(inst);
+    let mut lowered = module.code.map(Vec::into_iter);
     for func in module.funcs {
         let type_ = &inst.types[func.type_index as usize];
+        let type_id = inst.type_ids[func.type_index as usize];
+        let body = match lowered.as_mut() {
+            Some(code) => FuncBody::Lowered(Rc::new(code.next().unwrap())),
+            None => FuncBody::Pending(func),
+        };
         let _ = store
             .funcs
-            .alloc_module(&mut store.types_map, type_, &inst, func);
+            .alloc_module(&mut store.types_map, type_, type_id, &inst, body);
     }
 
     // call the start function if it exists
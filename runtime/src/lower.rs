@@ -0,0 +1,626 @@
+//! Lowering of structured function bodies to flat code.
+//!
+//! Blocks, loops and ifs are replaced by jumps to precomputed positions in a
+//! single array of operations, so that the interpreter executes a function in
+//! a loop instead of recursing on every nested block. Since the module has
+//! been validated, the types on the operand stack are known statically at
+//! every reachable instruction. The interpreter stores values untagged in
+//! 64-bit slots, so heights, branch arities and locals are measured in slots.
+//!
+//! Frequent sequences of instructions in the output of LLVM, such as address
+//! computations from a local, are fused into superinstructions on the way.
+
+use crate::ast::*;
+use crate::types;
+use crate::values::Value;
+use std::vec;
+
+/// Types of the module entities referenced by function bodies
+pub struct Context<'a> {
+    pub types: &'a [types::Func],
+    /// Type of every function of the module, imports included
+    pub funcs: &'a [types::Func],
+    /// Type of every global of the module, imports included
+    pub globals: &'a [types::Global],
+}
+
+/// The destination of a branch
+#[derive(Debug, Clone, Copy)]
+pub struct Target {
+    /// Position of the next operation to execute
+    pub pc: usize,
+    /// Number of slots carried over to the destination
+    pub keep: usize,
+    /// Number of slots to discard from below the carried ones
+    pub drop: usize,
+}
+
+/// The slots of a local variable, relative to the first argument
+#[derive(Debug, Clone, Copy)]
+pub struct Local {
+    pub offset: usize,
+    pub width: usize,
+}
+
+#[derive(Debug)]
+pub enum Op {
+    Instr(Instr),                 // instruction without structured control flow
+    Drop(usize),                  // discard a value of the given width
+    Select(usize),                // choose between two values of the given width
+    GetLocal(Local),              // push a local
+    SetLocal(Local),              // pop into a local
+    TeeLocal(Local),              // copy the top of the stack into a local
+    Load(LoadKind, u32),          // load from memory with the given offset
+    Store(StoreKind, u32),        // store to memory with the given offset
+    Br(Target),                   // unconditional branch
+    BrIf(Target),                 // branch if the top of the stack is not zero
+    BrUnless(usize),              // jump if the top of the stack is zero
+    BrTable(Vec<Target>, Target), // indexed branch
+    Jump(usize),                  // jump without touching the stack
+    Return,                       // return from the function
+
+    // Superinstructions
+    /// `local.get; i32.const; i32.add`
+    LocalAddConst(Local, u32),
+    /// `local.get; i32.const; i32.add` followed by a load with the given offset
+    LocalAddConstLoad(Local, u32, LoadKind, u32),
+    /// `local.get; local.get` followed by an i32 IBinOp and optionally a `local.set`
+    LocalsIBin(Local, Local, IBinOp, Option<Local>),
+}
+
+impl Op {
+    /// The pattern of instructions replaced by a superinstruction
+    pub fn fusion(&self) -> Option<&'static str> {
+        match *self {
+            Op::LocalAddConst(..) => Some("local.get i32.const i32.add"),
+            Op::LocalAddConstLoad(..) => Some("local.get i32.const i32.add load"),
+            Op::LocalsIBin(_, _, _, None) => Some("local.get local.get i32.binop"),
+            Op::LocalsIBin(_, _, _, Some(_)) => Some("local.get local.get i32.binop local.set"),
+            _ => None,
+        }
+    }
+}
+
+/// The exception handlers of a `try_table`
+#[derive(Debug)]
+pub struct Handler {
+    /// Range of operations covered by the handlers
+    pub start: usize,
+    pub end: usize,
+    /// Height of the operand stack when entering the `try_table`
+    pub height: usize,
+    pub catches: Vec<(Catch, Target)>,
+}
+
+/// The flat code of a function
+#[derive(Debug)]
+pub struct Code {
+    /// Number of slots taken by the arguments, the locals and the results
+    pub arg_slots: usize,
+    pub local_slots: usize,
+    pub result_slots: usize,
+    pub ops: Vec<Op>,
+    /// Handlers in the order of the `try_table` they come from, so that
+    /// nested ones come after the ones enclosing them
+    pub handlers: Vec<Handler>,
+}
+
+/// Number of slots taken by a sequence of values
+pub fn slots(types: &[types::Value]) -> usize {
+    types.iter().map(types::Value::slots).sum()
+}
+
+/// Lower the body of a function of type `type_`
+pub fn lower(ctx: &Context, type_: &types::Func, func: Func) -> Code {
+    lower_traced(ctx, type_, func).0
+}
+
+/// Lower the body of a function, also returning for every `Op::Instr` the
+/// position of its instruction in a pre-order traversal of the body
+pub fn lower_traced(ctx: &Context, type_: &types::Func, func: Func) -> (Code, Vec<usize>) {
+    let mut locals = Vec::new();
+    let mut offset = 0;
+    for &local in type_.args.iter().chain(&func.locals) {
+        locals.push((
+            local,
+            Local {
+                offset,
+                width: local.slots(),
+            },
+        ));
+        offset += local.slots();
+    }
+
+    // Heights are relative to the first argument of the function
+    let mut lowering = Lowering {
+        ctx,
+        locals,
+        ops: Vec::new(),
+        handlers: Vec::new(),
+        labels: Vec::new(),
+        label_pcs: Vec::new(),
+        types: Vec::new(),
+        height: offset,
+        next: 0,
+        sources: Vec::new(),
+    };
+
+    // The body of the function is a block whose end is the end of the code
+    let id = lowering.open(type_.result.clone());
+    lowering.seq(func.body);
+    lowering.close(id);
+    lowering.resolve();
+
+    let code = Code {
+        arg_slots: slots(&type_.args),
+        local_slots: slots(&func.locals),
+        result_slots: slots(&type_.result),
+        ops: lowering.ops,
+        handlers: lowering.handlers,
+    };
+    (code, lowering.sources)
+}
+
+/// Number of instructions in the tree of `instr`
+fn size(instr: &Instr) -> usize {
+    use crate::ast::Instr::*;
+
+    let nested: &[&[Instr]] = match *instr {
+        Block(_, ref instrs) | Loop(_, ref instrs) | TryTable(_, _, ref instrs) => &[instrs],
+        If(_, ref instrs_then, ref instrs_else) => &[instrs_then, instrs_else],
+        _ => &[],
+    };
+    1 + nested
+        .iter()
+        .flat_map(|instrs| instrs.iter())
+        .map(size)
+        .sum::<usize>()
+}
+
+struct Label {
+    id: usize,
+    /// Height of the stack, in slots and in values, when entering the block
+    height: usize,
+    depth: usize,
+    /// Types of the values expected by a branch to the label
+    types: Vec<types::Value>,
+    arity: usize,
+}
+
+struct Lowering<'a> {
+    ctx: &'a Context<'a>,
+    /// Type and slots of every argument and local
+    locals: Vec<(types::Value, Local)>,
+    ops: Vec<Op>,
+    handlers: Vec<Handler>,
+    /// Labels of the enclosing blocks, the innermost being the last one
+    labels: Vec<Label>,
+    /// Position of every label, by id. Until `resolve` is called, targets
+    /// refer to a label id instead of a position.
+    label_pcs: Vec<usize>,
+    /// Types of the operands pushed since the start of the function
+    types: Vec<types::Value>,
+    /// Number of slots taken by the arguments, locals and operands
+    height: usize,
+    /// Position of the next instruction in a pre-order traversal of the body
+    next: usize,
+    /// Position of the instruction of every `Op::Instr`
+    sources: Vec<usize>,
+}
+
+impl<'a> Lowering<'a> {
+    fn push(&mut self, type_: types::Value) {
+        self.types.push(type_);
+        self.height += type_.slots();
+    }
+
+    fn pop(&mut self) -> types::Value {
+        let type_ = self.types.pop().unwrap();
+        self.height -= type_.slots();
+        type_
+    }
+
+    fn pop_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.pop();
+        }
+    }
+
+    /// Reset the operand stack to `depth` values followed by `results`
+    fn reset(&mut self, depth: usize, height: usize, results: &[types::Value]) {
+        self.types.truncate(depth);
+        self.height = height;
+        for &type_ in results {
+            self.push(type_);
+        }
+    }
+
+    /// Allocate a new label id
+    fn label(&mut self) -> usize {
+        self.label_pcs.push(0);
+        self.label_pcs.len() - 1
+    }
+
+    /// Enter a block whose label expects values of the given types
+    fn open(&mut self, types: Vec<types::Value>) -> usize {
+        let id = self.label();
+        self.labels.push(Label {
+            id,
+            height: self.height,
+            depth: self.types.len(),
+            arity: slots(&types),
+            types,
+        });
+        id
+    }
+
+    /// Exit a block, its label designating the next operation
+    fn close(&mut self, id: usize) {
+        let label = self.labels.pop().unwrap();
+        self.label_pcs[id] = self.ops.len();
+        self.reset(label.depth, label.height, &label.types);
+    }
+
+    fn label_at(&self, nesting_levels: Index) -> &Label {
+        &self.labels[self.labels.len() - nesting_levels as usize - 1]
+    }
+
+    /// The destination of a branch taken from the current height
+    fn target(&self, nesting_levels: Index) -> Target {
+        let label = self.label_at(nesting_levels);
+        Target {
+            pc: label.id,
+            keep: label.arity,
+            drop: self.height - label.arity - label.height,
+        }
+    }
+
+    /// Lower a sequence of instructions, ignoring the unreachable ones.
+    /// Return whether the end of the sequence is reachable.
+    fn seq(&mut self, instrs: Vec<Instr>) -> bool {
+        let mut instrs = instrs.into_iter();
+        loop {
+            if self.fuse(&mut instrs) {
+                continue;
+            }
+            match instrs.next() {
+                Some(instr) => {
+                    if !self.instr(instr) {
+                        self.next += instrs.as_slice().iter().map(size).sum::<usize>();
+                        return false;
+                    }
+                }
+                None => return true,
+            }
+        }
+    }
+
+    /// Replace the instructions at the start of `instrs` by a superinstruction,
+    /// returning false if they do not match any of the fused patterns.
+    /// Validation ensures that the locals of these patterns are i32.
+    fn fuse(&mut self, instrs: &mut vec::IntoIter<Instr>) -> bool {
+        use crate::ast::Instr::*;
+        use crate::types::Int::I32;
+
+        let op = match *instrs.as_slice() {
+            [GetLocal(x), Const(Value::I32(c)), IBin(I32, IBinOp::Add), Load(ref memop, kind), ..] =>
+            {
+                self.push(memop.type_);
+                let op = Op::LocalAddConstLoad(self.locals[x as usize].1, c, kind, memop.offset);
+                instrs.nth(3);
+                self.next += 4;
+                op
+            }
+            [GetLocal(x), Const(Value::I32(c)), IBin(I32, IBinOp::Add), ..] => {
+                instrs.nth(2);
+                self.next += 3;
+                self.push(types::I32);
+                Op::LocalAddConst(self.locals[x as usize].1, c)
+            }
+            [GetLocal(a), GetLocal(b), IBin(I32, _), ..] => {
+                let op = match instrs.nth(2) {
+                    Some(IBin(_, op)) => op,
+                    _ => unreachable!(),
+                };
+                self.next += 3;
+                let dst = match instrs.as_slice().first() {
+                    Some(&SetLocal(y)) => {
+                        instrs.next();
+                        self.next += 1;
+                        Some(self.locals[y as usize].1)
+                    }
+                    _ => {
+                        self.push(types::I32);
+                        None
+                    }
+                };
+                let (a, b) = (self.locals[a as usize].1, self.locals[b as usize].1);
+                Op::LocalsIBin(a, b, op, dst)
+            }
+            _ => return false,
+        };
+        self.ops.push(op);
+        true
+    }
+
+    /// Lower an instruction, returning false if the next one is unreachable
+    fn instr(&mut self, instr: Instr) -> bool {
+        use crate::ast::Instr::*;
+
+        let source = self.next;
+        self.next += 1;
+
+        match instr {
+            Nop => {}
+
+            Block(result_type, instrs) => {
+                let id = self.open(result_type);
+                self.seq(instrs);
+                self.close(id);
+            }
+
+            Loop(result_type, instrs) => {
+                // Branching to a loop restarts it without any value
+                let (depth, height) = (self.types.len(), self.height);
+                let id = self.open(Vec::new());
+                self.label_pcs[id] = self.ops.len();
+                self.seq(instrs);
+                self.labels.pop();
+                self.reset(depth, height, &result_type);
+            }
+
+            If(result_type, instrs_then, instrs_else) => {
+                self.pop();
+                let (depth, height) = (self.types.len(), self.height);
+                let else_id = self.label();
+                self.ops.push(Op::BrUnless(else_id));
+
+                let id = self.open(result_type);
+                let reachable = self.seq(instrs_then);
+                if !instrs_else.is_empty() {
+                    if reachable {
+                        self.ops.push(Op::Jump(id));
+                    }
+                    self.label_pcs[else_id] = self.ops.len();
+                    self.reset(depth, height, &[]);
+                    self.seq(instrs_else);
+                } else {
+                    self.label_pcs[else_id] = self.ops.len();
+                }
+                self.close(id);
+            }
+
+            TryTable(result_type, catches, instrs) => {
+                // Handlers branch to their label as if from outside of the try_table
+                let catches = catches
+                    .into_iter()
+                    .map(|catch| {
+                        let label = match catch {
+                            Catch::Tag(_, label)
+                            | Catch::TagRef(_, label)
+                            | Catch::All(label)
+                            | Catch::AllRef(label) => label,
+                        };
+                        // The caught values replace everything above the entry height
+                        let label = self.label_at(label);
+                        let target = Target {
+                            pc: label.id,
+                            keep: label.arity,
+                            drop: self.height - label.height,
+                        };
+                        (catch, target)
+                    })
+                    .collect();
+                let handler = self.handlers.len();
+                self.handlers.push(Handler {
+                    start: self.ops.len(),
+                    end: 0,
+                    height: self.height,
+                    catches,
+                });
+
+                let id = self.open(result_type);
+                self.seq(instrs);
+                self.handlers[handler].end = self.ops.len();
+                self.close(id);
+            }
+
+            Br(nesting_levels) => {
+                let target = self.target(nesting_levels);
+                self.ops.push(Op::Br(target));
+                return false;
+            }
+
+            BrIf(nesting_levels) => {
+                self.pop();
+                let target = self.target(nesting_levels);
+                self.ops.push(Op::BrIf(target));
+            }
+
+            BrTable(choices, default) => {
+                self.pop();
+                let targets = choices
+                    .into_iter()
+                    .map(|choice| self.target(choice))
+                    .collect();
+                let default = self.target(default);
+                self.ops.push(Op::BrTable(targets, default));
+                return false;
+            }
+
+            Return => {
+                self.ops.push(Op::Return);
+                return false;
+            }
+
+            Unreachable | Throw(_) | ThrowRef | ReturnCall(_) | ReturnCallIndirect(_) => {
+                self.sources.push(source);
+                self.ops.push(Op::Instr(instr));
+                return false;
+            }
+
+            Drop_ => {
+                let type_ = self.pop();
+                self.ops.push(Op::Drop(type_.slots()));
+            }
+
+            Select => {
+                self.pop_n(2);
+                let type_ = self.pop();
+                self.push(type_);
+                self.ops.push(Op::Select(type_.slots()));
+            }
+
+            GetLocal(idx) => {
+                let (type_, local) = self.locals[idx as usize];
+                self.push(type_);
+                self.ops.push(Op::GetLocal(local));
+            }
+
+            SetLocal(idx) => {
+                self.pop();
+                self.ops.push(Op::SetLocal(self.locals[idx as usize].1));
+            }
+
+            TeeLocal(idx) => {
+                self.ops.push(Op::TeeLocal(self.locals[idx as usize].1));
+            }
+
+            Load(op, kind) => {
+                self.pop();
+                self.push(op.type_);
+                self.ops.push(Op::Load(kind, op.offset));
+            }
+
+            Store(op, kind) => {
+                self.pop_n(2);
+                self.ops.push(Op::Store(kind, op.offset));
+            }
+
+            instr => {
+                self.effect(&instr);
+                self.sources.push(source);
+                self.ops.push(Op::Instr(instr));
+            }
+        }
+        true
+    }
+
+    /// Apply to the operand stack the effect of an instruction without control flow
+    fn effect(&mut self, instr: &Instr) {
+        use crate::ast::Instr::*;
+        use crate::types::{I32, V128};
+
+        let (pops, push) = match *instr {
+            Call(idx) | CallIndirect(idx) => {
+                let ctx = self.ctx;
+                let type_ = match *instr {
+                    Call(_) => &ctx.funcs[idx as usize],
+                    _ => {
+                        self.pop();
+                        &ctx.types[idx as usize]
+                    }
+                };
+                self.pop_n(type_.args.len());
+                for &result in &type_.result {
+                    self.push(result);
+                }
+                return;
+            }
+
+            GetGlobal(idx) => (0, Some(self.ctx.globals[idx as usize].value)),
+            SetGlobal(_) => (1, None),
+            Const(c) => (0, Some(c.type_())),
+            CurrentMemory => (0, Some(I32)),
+            GrowMemory => (1, Some(I32)),
+
+            IUnary(t, _) => (1, Some(types::Value::Int(t))),
+            FUnary(t, _) => (1, Some(types::Value::Float(t))),
+            IBin(t, _) => (2, Some(types::Value::Int(t))),
+            FBin(t, _) => (2, Some(types::Value::Float(t))),
+            ITest(..) => (1, Some(I32)),
+            IRel(..) | FRel(..) => (2, Some(I32)),
+            Convert(ref op) => (1, Some(op.types().1)),
+
+            VLoad(ref op) => match op.kind {
+                VLoadKind::Lane(..) => (2, Some(V128)),
+                _ => (1, Some(V128)),
+            },
+            VStore(_) => (2, None),
+            ExtractLane(shape, ..) => (1, Some(shape.lane_type())),
+            AnyTrue | AllTrue(_) | Bitmask(_) => (1, Some(I32)),
+            Splat(_) | VNot | VIUnary(..) | VFUnary(..) | VConvert(_) => (1, Some(V128)),
+            Shuffle(_) | Swizzle | ReplaceLane(..) | VBit(_) | VIBin(..) | VFBin(..)
+            | VShift(..) | VIRel(..) | VFRel(..) => (2, Some(V128)),
+            Bitselect => (3, Some(V128)),
+
+            AtomicLoad(ref op) => (1, Some(op.type_)),
+            AtomicStore(_) => (2, None),
+            AtomicRmw(_, ref op) => (2, Some(op.type_)),
+            AtomicCmpxchg(ref op) => (3, Some(op.type_)),
+            AtomicWait(_) => (3, Some(I32)),
+            AtomicNotify(_) => (2, Some(I32)),
+            AtomicFence => (0, None),
+
+            Unreachable
+            | Nop
+            | Block(..)
+            | Loop(..)
+            | If(..)
+            | TryTable(..)
+            | Throw(_)
+            | ThrowRef
+            | Br(_)
+            | BrIf(_)
+            | BrTable(..)
+            | Return
+            | ReturnCall(_)
+            | ReturnCallIndirect(_)
+            | Drop_
+            | Select
+            | GetLocal(_)
+            | SetLocal(_)
+            | TeeLocal(_)
+            | Load(..)
+            | Store(..) => unreachable!("not a plain instruction"),
+        };
+        self.pop_n(pops);
+        if let Some(type_) = push {
+            self.push(type_);
+        }
+    }
+
+    /// Replace label ids by the position of the labels
+    fn resolve(&mut self) {
+        let label_pcs = &self.label_pcs;
+        let resolve = |target: &mut Target| target.pc = label_pcs[target.pc];
+
+        for op in &mut self.ops {
+            match *op {
+                Op::Br(ref mut target) | Op::BrIf(ref mut target) => resolve(target),
+                Op::BrTable(ref mut targets, ref mut default) => {
+                    targets.iter_mut().for_each(resolve);
+                    resolve(default);
+                }
+                Op::BrUnless(ref mut pc) | Op::Jump(ref mut pc) => *pc = label_pcs[*pc],
+                Op::Instr(_)
+                | Op::Drop(_)
+                | Op::Select(_)
+                | Op::GetLocal(_)
+                | Op::SetLocal(_)
+                | Op::TeeLocal(_)
+                | Op::Load(..)
+                | Op::Store(..)
+                | Op::LocalAddConst(..)
+                | Op::LocalAddConstLoad(..)
+                | Op::LocalsIBin(..)
+                | Op::Return => {}
+            }
+        }
+        for handler in &mut self.handlers {
+            for (_, target) in &mut handler.catches {
+                resolve(target);
+            }
+        }
+    }
+}
@@ -1,5 +1,10 @@
+use super::lower::{self, Code};
+use super::valid::{self, ModContext};
 use crate::{ast, types, values};
+use std::any::Any;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::mem;
 use std::ops::{Index, IndexMut};
 use std::rc::Rc;
 
@@ -11,19 +16,45 @@ pub struct TypeKey {
 
 pub type TypeHashMap = HashMap<TypeKey, types::Extern>;
 
-// Instances of a Module/Func/Table/Memory/Global
+/// Canonical id of a function type within a store: two functions have the same
+/// type if and only if their type ids are equal
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct TypeId(u32);
+
+/// Interner of the function types of a store
+#[derive(Default)]
+pub struct TypeIds(HashMap<types::Func, TypeId>);
+
+impl TypeIds {
+    pub fn new() -> TypeIds {
+        TypeIds(HashMap::new())
+    }
+
+    pub fn intern(&mut self, type_: &types::Func) -> TypeId {
+        let next = TypeId(self.0.len() as u32);
+        *self.0.entry(type_.clone()).or_insert(next)
+    }
+}
+
+// Instances of a Module/Func/Table/Memory/Global/Tag
 pub struct ModuleInst {
     pub(crate) types: Vec<types::Func>,
+    pub(crate) type_ids: Vec<TypeId>, // canonical id of each type, for indirect calls
     pub(crate) func_addrs: Vec<FuncAddr>,
     pub(crate) table_addrs: Vec<TableAddr>,
     pub(crate) mem_addrs: Vec<MemAddr>,
     pub(crate) global_addrs: Vec<GlobalAddr>,
+    pub(crate) tag_addrs: Vec<TagAddr>,
+    pub(crate) tag_types: Vec<ast::Index>, // type index of each tag, giving its arity
     pub(crate) exports: Vec<ExportInst>,
+    /// Typing context of the module, to prepare its functions when first called
+    pub(crate) context: ModContext,
 }
 
 pub struct MemInst {
     pub data: Vec<u8>,
     pub max: Option<u32>,
+    pub shared: bool,
 }
 
 pub struct GlobalInst {
@@ -31,18 +62,143 @@ pub struct GlobalInst {
     pub mutable: bool,
 }
 
+pub struct TagInst {
+    pub type_: types::Func,
+}
+
+/// A thrown exception, only alive for the duration of an invocation
+pub struct ExnInst {
+    pub tag: TagAddr,
+    /// The arguments of the exception, in their untagged stack representation
+    pub fields: Vec<u64>,
+}
+
 pub type HostFunctionError = String;
-pub type HostFunc = Box<dyn Fn(&mut Vec<values::Value>) -> Option<HostFunctionError>>;
+/// A host function, given the context of its call and its arguments, which it
+/// replaces by its results
+pub type HostFunc = Box<dyn Fn(&mut Caller, &mut Vec<values::Value>) -> Option<HostFunctionError>>;
+
+/// Context of a host function call: the instance calling it, if any, and the
+/// user data of the store
+pub struct Caller<'a> {
+    pub(crate) module: Option<&'a ModuleInst>,
+    pub(crate) globals: &'a mut GlobalInstStore,
+    pub(crate) mems: &'a mut MemInstStore,
+    pub(crate) data: &'a mut Option<Box<dyn Any>>,
+}
+
+impl<'a> Caller<'a> {
+    /// The linear memory of the calling instance, whether exported or not
+    pub fn memory(&mut self) -> Option<&mut [u8]> {
+        let addr = *self.module?.mem_addrs.first()?;
+        Some(&mut self.mems[addr].data)
+    }
+}
+
+// Only the memory is used by watt's own imports
+#[cfg(feature = "test")]
+impl<'a> Caller<'a> {
+    /// The value of a global exported by the calling instance
+    pub fn global(&self, name: &str) -> Option<values::Value> {
+        match self.export(name)? {
+            ExternVal::Global(addr) => Some(self.globals[addr].value),
+            _ => None,
+        }
+    }
+
+    /// Set a mutable global exported by the calling instance. Fails if there
+    /// is no such global or if the value has the wrong type.
+    pub fn set_global(&mut self, name: &str, value: values::Value) -> bool {
+        let addr = match self.export(name) {
+            Some(ExternVal::Global(addr)) => addr,
+            _ => return false,
+        };
+        let global = &mut self.globals[addr];
+        if !global.mutable || global.value.type_() != value.type_() {
+            return false;
+        }
+        global.value = value;
+        true
+    }
+
+    /// The user data of the store, if it is of type `T`
+    pub fn data<T: Any>(&mut self) -> Option<&mut T> {
+        self.data.as_mut()?.downcast_mut()
+    }
+
+    fn export(&self, name: &str) -> Option<ExternVal> {
+        let module = self.module?;
+        module
+            .exports
+            .iter()
+            .find(|e| e.name == name)
+            .map(|e| e.value)
+    }
+}
 
 pub struct HostFuncInst {
     pub type_: types::Func,
+    pub type_id: TypeId,
     pub hostcode: HostFunc,
 }
 
 pub struct ModuleFuncInst {
     pub type_: types::Func,
+    pub type_id: TypeId,
     pub module: Rc<ModuleInst>,
-    pub code: ast::Func,
+    pub(crate) body: RefCell<FuncBody>,
+}
+
+/// Body of a module function. Most functions linked into a module are never
+/// called, so bodies are only validated and lowered on their first call,
+/// unless the module comes lowered in the compact format.
+pub(crate) enum FuncBody {
+    Pending(ast::Func),
+    Lowered(Rc<Code>),
+    Invalid,
+}
+
+#[cfg(feature = "test")]
+thread_local! {
+    /// Number of function bodies validated on their first call
+    pub(crate) static VALIDATED_FUNCS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+impl ModuleFuncInst {
+    /// Return the lowered code of the function, preparing it if this is the
+    /// first call, or `None` if its body is invalid
+    pub(crate) fn code(&self) -> Option<Rc<Code>> {
+        let mut body = self.body.borrow_mut();
+        let func = match mem::replace(&mut *body, FuncBody::Invalid) {
+            FuncBody::Lowered(code) => {
+                *body = FuncBody::Lowered(Rc::clone(&code));
+                return Some(code);
+            }
+            FuncBody::Pending(func) => func,
+            FuncBody::Invalid => return None,
+        };
+
+        let mod_ctx = &self.module.context;
+        #[cfg(feature = "test")]
+        VALIDATED_FUNCS.with(|count| count.set(count.get() + 1));
+        valid::check_func(mod_ctx, &func)?;
+        let ctx = lower::Context {
+            types: &mod_ctx.types,
+            funcs: &mod_ctx.funcs,
+            globals: &mod_ctx.globals,
+        };
+        let code = Rc::new(lower::lower(&ctx, &self.type_, func));
+        *body = FuncBody::Lowered(Rc::clone(&code));
+        Some(code)
+    }
+
+    /// Return the code of the function if it has already been lowered
+    pub(crate) fn lowered(&self) -> Option<Rc<Code>> {
+        match *self.body.borrow() {
+            FuncBody::Lowered(ref code) => Some(Rc::clone(code)),
+            _ => None,
+        }
+    }
 }
 
 pub enum FuncInst {
@@ -50,7 +206,17 @@ pub enum FuncInst {
     Host(HostFuncInst),
 }
 
-type FuncElem = Option<FuncAddr>;
+impl FuncInst {
+    pub fn type_id(&self) -> TypeId {
+        match *self {
+            FuncInst::Module(ref f) => f.type_id,
+            FuncInst::Host(ref f) => f.type_id,
+        }
+    }
+}
+
+/// A table element, along with the type id of the function for indirect calls
+type FuncElem = Option<(FuncAddr, TypeId)>;
 
 pub struct TableInst {
     pub elem: Vec<FuncElem>,
@@ -66,6 +232,8 @@ pub struct FuncInstStore(Vec<FuncInst>);
 pub struct MemInstStore(Vec<MemInst>);
 pub struct TableInstStore(Vec<TableInst>);
 pub struct GlobalInstStore(Vec<GlobalInst>);
+pub struct TagInstStore(Vec<TagInst>);
+pub struct ExnInstStore(Vec<ExnInst>);
 
 // Addrs and extern valus exported to the user
 type Addr = usize;
@@ -77,6 +245,10 @@ pub struct TableAddr(Addr);
 pub struct MemAddr(Addr);
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub struct GlobalAddr(Addr);
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct TagAddr(Addr);
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct ExnAddr(Addr);
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum ExternVal {
@@ -84,6 +256,7 @@ pub enum ExternVal {
     Table(TableAddr),
     Memory(MemAddr),
     Global(GlobalAddr),
+    Tag(TagAddr),
 }
 
 // Constants
@@ -91,14 +264,18 @@ pub const PAGE_SIZE: usize = 65536;
 
 // Traits
 impl ModuleInst {
-    pub fn new() -> ModuleInst {
+    pub(crate) fn new(context: ModContext) -> ModuleInst {
         ModuleInst {
             types: Vec::new(),
+            type_ids: Vec::new(),
             func_addrs: Vec::new(),
             table_addrs: Vec::new(),
             mem_addrs: Vec::new(),
             global_addrs: Vec::new(),
+            tag_addrs: Vec::new(),
+            tag_types: Vec::new(),
             exports: Vec::new(),
+            context,
         }
     }
 }
@@ -120,6 +297,12 @@ macro_rules! impl_inst_store {
             }
         }
 
+        impl Default for $StoreType {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
         impl Index<$AddrType> for $StoreType {
             type Output = $InnerType;
             fn index(&self, idx: $AddrType) -> &$InnerType {
@@ -145,6 +328,8 @@ impl_inst_store!(FuncInstStore, FuncInst, FuncAddr);
 impl_inst_store!(TableInstStore, TableInst, TableAddr);
 impl_inst_store!(GlobalInstStore, GlobalInst, GlobalAddr);
 impl_inst_store!(MemInstStore, MemInst, MemAddr);
+impl_inst_store!(TagInstStore, TagInst, TagAddr);
+impl_inst_store!(ExnInstStore, ExnInst, ExnAddr);
 
 // Per trait functions
 impl FuncInstStore {
@@ -152,15 +337,17 @@ impl FuncInstStore {
         &mut self,
         types_map: &mut TypeHashMap,
         functype: &types::Func,
+        type_id: TypeId,
         minst: &Rc<ModuleInst>,
-        code: ast::Func,
+        body: FuncBody,
     ) -> FuncAddr {
         self.alloc(
             types_map,
             FuncInst::Module(ModuleFuncInst {
                 type_: functype.clone(),
+                type_id,
                 module: Rc::clone(minst),
-                code: code,
+                body: RefCell::new(body),
             }),
             functype,
         )
@@ -170,12 +357,14 @@ impl FuncInstStore {
         &mut self,
         types_map: &mut TypeHashMap,
         functype: &types::Func,
+        type_id: TypeId,
         hostfunc: HostFunc,
     ) -> FuncAddr {
         self.alloc(
             types_map,
             FuncInst::Host(HostFuncInst {
                 type_: functype.clone(),
+                type_id,
                 hostcode: hostfunc,
             }),
             functype,
@@ -209,6 +398,7 @@ impl MemInstStore {
         self.0.push(MemInst {
             data: vec![0; (memtype.limits.min as usize) * PAGE_SIZE],
             max: memtype.limits.max,
+            shared: memtype.shared,
         });
         let addr = MemAddr::new(self.len() - 1);
         types_map.insert(
@@ -283,3 +473,33 @@ impl GlobalInstStore {
         addr
     }
 }
+
+impl TagInstStore {
+    pub(crate) fn alloc(&mut self, types_map: &mut TypeHashMap, tagtype: &types::Func) -> TagAddr {
+        self.0.push(TagInst {
+            type_: tagtype.clone(),
+        });
+        let addr = TagAddr::new(self.len() - 1);
+        types_map.insert(
+            TypeKey {
+                extern_val: ExternVal::Tag(addr),
+            },
+            types::Extern::Tag(tagtype.clone()),
+        );
+        addr
+    }
+}
+
+impl ExnInstStore {
+    pub(crate) fn alloc(&mut self, tag: TagAddr, fields: Vec<u64>) -> ExnAddr {
+        self.0.push(ExnInst { tag, fields });
+        ExnAddr::new(self.len() - 1)
+    }
+}
+
+impl ExnAddr {
+    /// Position of the exception in its store
+    pub(crate) fn index(self) -> usize {
+        self.0
+    }
+}
@@ -0,0 +1,415 @@
+//! Portable implementation of the 128-bit vector instructions.
+//!
+//! Vectors are carried as a `u128` with lane 0 in the least significant bits,
+//! which matches their little-endian layout in linear memory. Every operation
+//! is performed lane by lane on plain scalars.
+
+use crate::ast::*;
+use crate::ops::{FloatDemoteOp, FloatOp, FloatPromoteOp};
+
+/// A scalar which can be stored in a vector lane
+trait Lane: Copy {
+    const BITS: u32;
+
+    fn get(v: u128, i: u32) -> Self;
+    fn put(self, i: u32) -> u128;
+}
+
+macro_rules! impl_int_lane {
+    ($T:ty, $U:ty) => {
+        impl Lane for $T {
+            const BITS: u32 = <$U>::BITS;
+
+            #[inline]
+            fn get(v: u128, i: u32) -> $T {
+                (v >> (i * Self::BITS)) as $T
+            }
+
+            #[inline]
+            fn put(self, i: u32) -> u128 {
+                (self as $U as u128) << (i * Self::BITS)
+            }
+        }
+    };
+}
+impl_int_lane!(u8, u8);
+impl_int_lane!(i8, u8);
+impl_int_lane!(u16, u16);
+impl_int_lane!(i16, u16);
+impl_int_lane!(u32, u32);
+impl_int_lane!(i32, u32);
+impl_int_lane!(u64, u64);
+impl_int_lane!(i64, u64);
+
+macro_rules! impl_float_lane {
+    ($T:ty, $U:ty) => {
+        impl Lane for $T {
+            const BITS: u32 = <$U>::BITS;
+
+            #[inline]
+            fn get(v: u128, i: u32) -> $T {
+                <$T>::from_bits(<$U>::get(v, i))
+            }
+
+            #[inline]
+            fn put(self, i: u32) -> u128 {
+                self.to_bits().put(i)
+            }
+        }
+    };
+}
+impl_float_lane!(f32, u32);
+impl_float_lane!(f64, u64);
+
+#[inline]
+fn lanes<T: Lane>() -> u32 {
+    128 / T::BITS
+}
+
+/// Apply `f` to every lane of `T`, narrower results are packed in the low lanes
+fn map<T: Lane, R: Lane>(v: u128, f: impl Fn(T) -> R) -> u128 {
+    (0..lanes::<T>()).fold(0, |res, i| res | f(T::get(v, i)).put(i))
+}
+
+fn zip<T: Lane, R: Lane>(a: u128, b: u128, f: impl Fn(T, T) -> R) -> u128 {
+    (0..lanes::<T>()).fold(0, |res, i| res | f(T::get(a, i), T::get(b, i)).put(i))
+}
+
+/// Widen the lanes of `T` starting at index `from` to the lanes of `R`
+fn widen<T: Lane, R: Lane>(v: u128, from: u32, f: impl Fn(T) -> R) -> u128 {
+    (0..lanes::<R>()).fold(0, |res, i| res | f(T::get(v, from + i)).put(i))
+}
+
+/// Comparison result of a lane: all ones if true, all zeros otherwise
+#[inline]
+fn mask(b: bool) -> i64 {
+    -(b as i64)
+}
+
+/// Raw bits of lane `i`, zero-extended
+pub fn lane(shape: Shape, v: u128, i: u8) -> u128 {
+    let bits = shape.lane_bits();
+    (v >> (i as u32 * bits)) & (!0 >> (128 - bits))
+}
+
+/// Replace the raw bits of lane `i`
+pub fn replace_lane(shape: Shape, v: u128, i: u8, x: u128) -> u128 {
+    let bits = shape.lane_bits();
+    let m = (!0 >> (128 - bits)) << (i as u32 * bits);
+    (v & !m) | ((x << (i as u32 * bits)) & m)
+}
+
+/// Replicate the raw bits of a lane to all lanes
+pub fn splat(shape: Shape, x: u128) -> u128 {
+    (0..shape.lanes()).fold(0, |res, i| replace_lane(shape, res, i, x))
+}
+
+pub fn shuffle(a: u128, b: u128, indices: &[u8; 16]) -> u128 {
+    let bytes = [a.to_le_bytes(), b.to_le_bytes()];
+    let mut res = [0; 16];
+    for (r, &i) in res.iter_mut().zip(indices) {
+        *r = bytes[i as usize / 16][i as usize % 16];
+    }
+    u128::from_le_bytes(res)
+}
+
+pub fn swizzle(a: u128, s: u128) -> u128 {
+    let bytes = a.to_le_bytes();
+    map::<u8, u8>(s, |i| *bytes.get(i as usize).unwrap_or(&0))
+}
+
+pub fn bit(op: &VBitOp, a: u128, b: u128) -> u128 {
+    match *op {
+        VBitOp::And => a & b,
+        VBitOp::AndNot => a & !b,
+        VBitOp::Or => a | b,
+        VBitOp::Xor => a ^ b,
+    }
+}
+
+pub fn all_true(shape: Shape, v: u128) -> bool {
+    (0..shape.lanes()).all(|i| lane(shape, v, i) != 0)
+}
+
+pub fn bitmask(shape: Shape, v: u128) -> u32 {
+    let top = shape.lane_bits() - 1;
+    (0..shape.lanes()).fold(0, |res, i| res | ((lane(shape, v, i) >> top) as u32) << i)
+}
+
+/// Widen the low (`high == false`) or high half of the lanes
+fn extend(shape: Shape, v: u128, high: bool, signed: bool) -> u128 {
+    let from = if high { shape.lanes() as u32 } else { 0 };
+    match (shape, signed) {
+        (Shape::I16x8, true) => widen::<i8, i16>(v, from, |x| x as i16),
+        (Shape::I16x8, false) => widen::<u8, u16>(v, from, |x| x as u16),
+        (Shape::I32x4, true) => widen::<i16, i32>(v, from, |x| x as i32),
+        (Shape::I32x4, false) => widen::<u16, u32>(v, from, |x| x as u32),
+        (Shape::I64x2, true) => widen::<i32, i64>(v, from, |x| x as i64),
+        (Shape::I64x2, false) => widen::<u32, u64>(v, from, |x| x as u64),
+        _ => unreachable!(),
+    }
+}
+
+pub fn iunary(shape: Shape, op: &VIUnOp, v: u128) -> u128 {
+    match (op, shape) {
+        (VIUnOp::Abs, Shape::I8x16) => map::<i8, i8>(v, i8::wrapping_abs),
+        (VIUnOp::Abs, Shape::I16x8) => map::<i16, i16>(v, i16::wrapping_abs),
+        (VIUnOp::Abs, Shape::I32x4) => map::<i32, i32>(v, i32::wrapping_abs),
+        (VIUnOp::Abs, Shape::I64x2) => map::<i64, i64>(v, i64::wrapping_abs),
+        (VIUnOp::Neg, Shape::I8x16) => map::<i8, i8>(v, i8::wrapping_neg),
+        (VIUnOp::Neg, Shape::I16x8) => map::<i16, i16>(v, i16::wrapping_neg),
+        (VIUnOp::Neg, Shape::I32x4) => map::<i32, i32>(v, i32::wrapping_neg),
+        (VIUnOp::Neg, Shape::I64x2) => map::<i64, i64>(v, i64::wrapping_neg),
+        (VIUnOp::Popcnt, Shape::I8x16) => map::<u8, u8>(v, |x| x.count_ones() as u8),
+        (VIUnOp::ExtendLow(signed), _) => extend(shape, v, false, *signed),
+        (VIUnOp::ExtendHigh(signed), _) => extend(shape, v, true, *signed),
+        (VIUnOp::ExtAddPairwise(signed), _) => {
+            let lo = extend(
+                shape,
+                shuffle(v, 0, &EVEN_BYTES[shape_idx(shape)]),
+                false,
+                *signed,
+            );
+            let hi = extend(
+                shape,
+                shuffle(v, 0, &ODD_BYTES[shape_idx(shape)]),
+                false,
+                *signed,
+            );
+            ibin(shape, &VIBinOp::Add, lo, hi)
+        }
+        _ => unreachable!(),
+    }
+}
+
+// Byte selectors gathering the even (resp. odd) half-width lanes of a vector in
+// its low half, for the 16-bit and 32-bit pairwise additions.
+fn shape_idx(shape: Shape) -> usize {
+    match shape {
+        Shape::I16x8 => 0,
+        Shape::I32x4 => 1,
+        _ => unreachable!(),
+    }
+}
+
+static EVEN_BYTES: [[u8; 16]; 2] = [
+    [0, 2, 4, 6, 8, 10, 12, 14, 16, 16, 16, 16, 16, 16, 16, 16],
+    [0, 1, 4, 5, 8, 9, 12, 13, 16, 16, 16, 16, 16, 16, 16, 16],
+];
+
+static ODD_BYTES: [[u8; 16]; 2] = [
+    [1, 3, 5, 7, 9, 11, 13, 15, 16, 16, 16, 16, 16, 16, 16, 16],
+    [2, 3, 6, 7, 10, 11, 14, 15, 16, 16, 16, 16, 16, 16, 16, 16],
+];
+
+macro_rules! int_bin {
+    ($S:ty, $U:ty, $op:expr, $a:expr, $b:expr) => {
+        match *$op {
+            VIBinOp::Add => zip::<$U, $U>($a, $b, <$U>::wrapping_add),
+            VIBinOp::Sub => zip::<$U, $U>($a, $b, <$U>::wrapping_sub),
+            VIBinOp::Mul => zip::<$U, $U>($a, $b, <$U>::wrapping_mul),
+            VIBinOp::AddSat(true) => zip::<$S, $S>($a, $b, <$S>::saturating_add),
+            VIBinOp::AddSat(false) => zip::<$U, $U>($a, $b, <$U>::saturating_add),
+            VIBinOp::SubSat(true) => zip::<$S, $S>($a, $b, <$S>::saturating_sub),
+            VIBinOp::SubSat(false) => zip::<$U, $U>($a, $b, <$U>::saturating_sub),
+            VIBinOp::Min(true) => zip::<$S, $S>($a, $b, Ord::min),
+            VIBinOp::Min(false) => zip::<$U, $U>($a, $b, Ord::min),
+            VIBinOp::Max(true) => zip::<$S, $S>($a, $b, Ord::max),
+            VIBinOp::Max(false) => zip::<$U, $U>($a, $b, Ord::max),
+            VIBinOp::AvgrU => zip::<$U, $U>($a, $b, |x, y| (x as u64 + y as u64).div_ceil(2) as $U),
+            _ => unreachable!(),
+        }
+    };
+}
+
+pub fn ibin(shape: Shape, op: &VIBinOp, a: u128, b: u128) -> u128 {
+    match (op, shape) {
+        (VIBinOp::Q15MulrSatS, Shape::I16x8) => zip::<i16, i16>(a, b, |x, y| {
+            let r = (x as i32 * y as i32 + 0x4000) >> 15;
+            r.max(i16::MIN as i32).min(i16::MAX as i32) as i16
+        }),
+        (VIBinOp::Dot, Shape::I32x4) => {
+            let (lo, hi) = (
+                ibin(shape, &VIBinOp::ExtMulLow(true), a, b),
+                ibin(shape, &VIBinOp::ExtMulHigh(true), a, b),
+            );
+            // lanes of the products are pairwise adjacent in the two halves
+            let even = shuffle(lo, hi, &DOT_EVEN);
+            let odd = shuffle(lo, hi, &DOT_ODD);
+            zip::<u32, u32>(even, odd, u32::wrapping_add)
+        }
+        (VIBinOp::ExtMulLow(signed), _) | (VIBinOp::ExtMulHigh(signed), _) => {
+            let high = matches!(op, VIBinOp::ExtMulHigh(_));
+            let (a, b) = (
+                extend(shape, a, high, *signed),
+                extend(shape, b, high, *signed),
+            );
+            ibin(shape, &VIBinOp::Mul, a, b)
+        }
+        (VIBinOp::Narrow(signed), Shape::I8x16) => {
+            let narrow = |x: i16| {
+                if *signed {
+                    x.max(i8::MIN as i16).min(i8::MAX as i16) as u8
+                } else {
+                    x.max(0).min(u8::MAX as i16) as u8
+                }
+            };
+            map::<i16, u8>(a, narrow) | map::<i16, u8>(b, narrow) << 64
+        }
+        (VIBinOp::Narrow(signed), Shape::I16x8) => {
+            let narrow = |x: i32| {
+                if *signed {
+                    x.max(i16::MIN as i32).min(i16::MAX as i32) as u16
+                } else {
+                    x.max(0).min(u16::MAX as i32) as u16
+                }
+            };
+            map::<i32, u16>(a, narrow) | map::<i32, u16>(b, narrow) << 64
+        }
+        (_, Shape::I8x16) => int_bin!(i8, u8, op, a, b),
+        (_, Shape::I16x8) => int_bin!(i16, u16, op, a, b),
+        (_, Shape::I32x4) => int_bin!(i32, u32, op, a, b),
+        (_, Shape::I64x2) => int_bin!(i64, u64, op, a, b),
+        _ => unreachable!(),
+    }
+}
+
+// Gather the products of lanes (0, 2, 4, 6) and (1, 3, 5, 7) of the 32-bit
+// extended multiplications.
+static DOT_EVEN: [u8; 16] = [0, 1, 2, 3, 8, 9, 10, 11, 16, 17, 18, 19, 24, 25, 26, 27];
+static DOT_ODD: [u8; 16] = [4, 5, 6, 7, 12, 13, 14, 15, 20, 21, 22, 23, 28, 29, 30, 31];
+
+macro_rules! shift {
+    ($S:ty, $U:ty, $op:expr, $v:expr, $n:expr) => {{
+        let n = $n % <$U>::BITS;
+        match *$op {
+            VShiftOp::Shl => map::<$U, $U>($v, |x| x << n),
+            VShiftOp::ShrS => map::<$S, $S>($v, |x| x >> n),
+            VShiftOp::ShrU => map::<$U, $U>($v, |x| x >> n),
+        }
+    }};
+}
+
+pub fn shift(shape: Shape, op: &VShiftOp, v: u128, n: u32) -> u128 {
+    match shape {
+        Shape::I8x16 => shift!(i8, u8, op, v, n),
+        Shape::I16x8 => shift!(i16, u16, op, v, n),
+        Shape::I32x4 => shift!(i32, u32, op, v, n),
+        Shape::I64x2 => shift!(i64, u64, op, v, n),
+        _ => unreachable!(),
+    }
+}
+
+macro_rules! int_rel {
+    ($S:ty, $U:ty, $op:expr, $a:expr, $b:expr) => {{
+        let s = |f: fn(&$S, &$S) -> bool| zip::<$S, $S>($a, $b, |x, y| mask(f(&x, &y)) as $S);
+        let u = |f: fn(&$U, &$U) -> bool| zip::<$U, $S>($a, $b, |x, y| mask(f(&x, &y)) as $S);
+        match *$op {
+            IRelOp::Eq_ => u(PartialEq::eq),
+            IRelOp::Ne => u(PartialEq::ne),
+            IRelOp::LtS => s(PartialOrd::lt),
+            IRelOp::LtU => u(PartialOrd::lt),
+            IRelOp::GtS => s(PartialOrd::gt),
+            IRelOp::GtU => u(PartialOrd::gt),
+            IRelOp::LeS => s(PartialOrd::le),
+            IRelOp::LeU => u(PartialOrd::le),
+            IRelOp::GeS => s(PartialOrd::ge),
+            IRelOp::GeU => u(PartialOrd::ge),
+        }
+    }};
+}
+
+pub fn irel(shape: Shape, op: &IRelOp, a: u128, b: u128) -> u128 {
+    match shape {
+        Shape::I8x16 => int_rel!(i8, u8, op, a, b),
+        Shape::I16x8 => int_rel!(i16, u16, op, a, b),
+        Shape::I32x4 => int_rel!(i32, u32, op, a, b),
+        Shape::I64x2 => int_rel!(i64, u64, op, a, b),
+        _ => unreachable!(),
+    }
+}
+
+macro_rules! float_unary {
+    ($T:ty, $op:expr, $v:expr) => {
+        match *$op {
+            FUnOp::Neg => map::<$T, $T>($v, FloatOp::neg),
+            FUnOp::Abs => map::<$T, $T>($v, FloatOp::abs),
+            FUnOp::Ceil => map::<$T, $T>($v, FloatOp::ceil),
+            FUnOp::Floor => map::<$T, $T>($v, FloatOp::floor),
+            FUnOp::Trunc => map::<$T, $T>($v, FloatOp::trunc),
+            FUnOp::Nearest => map::<$T, $T>($v, FloatOp::nearest),
+            FUnOp::Sqrt => map::<$T, $T>($v, FloatOp::sqrt),
+        }
+    };
+}
+
+pub fn funary(shape: Shape, op: &FUnOp, v: u128) -> u128 {
+    match shape {
+        Shape::F32x4 => float_unary!(f32, op, v),
+        Shape::F64x2 => float_unary!(f64, op, v),
+        _ => unreachable!(),
+    }
+}
+
+macro_rules! float_bin {
+    ($T:ty, $op:expr, $a:expr, $b:expr) => {
+        match *$op {
+            VFBinOp::Add => zip::<$T, $T>($a, $b, FloatOp::add),
+            VFBinOp::Sub => zip::<$T, $T>($a, $b, FloatOp::sub),
+            VFBinOp::Mul => zip::<$T, $T>($a, $b, FloatOp::mul),
+            VFBinOp::Div => zip::<$T, $T>($a, $b, FloatOp::div),
+            VFBinOp::Min => zip::<$T, $T>($a, $b, FloatOp::min),
+            VFBinOp::Max => zip::<$T, $T>($a, $b, FloatOp::max),
+            VFBinOp::PMin => zip::<$T, $T>($a, $b, |x, y| if y < x { y } else { x }),
+            VFBinOp::PMax => zip::<$T, $T>($a, $b, |x, y| if x < y { y } else { x }),
+        }
+    };
+}
+
+pub fn fbin(shape: Shape, op: &VFBinOp, a: u128, b: u128) -> u128 {
+    match shape {
+        Shape::F32x4 => float_bin!(f32, op, a, b),
+        Shape::F64x2 => float_bin!(f64, op, a, b),
+        _ => unreachable!(),
+    }
+}
+
+macro_rules! float_rel {
+    ($T:ty, $S:ty, $op:expr, $a:expr, $b:expr) => {{
+        let f = |f: fn($T, $T) -> bool| zip::<$T, $S>($a, $b, |x, y| mask(f(x, y)) as $S);
+        match *$op {
+            FRelOp::Eq_ => f(FloatOp::eq),
+            FRelOp::Ne => f(FloatOp::ne),
+            FRelOp::Lt => f(FloatOp::lt),
+            FRelOp::Gt => f(FloatOp::gt),
+            FRelOp::Le => f(FloatOp::le),
+            FRelOp::Ge => f(FloatOp::ge),
+        }
+    }};
+}
+
+pub fn frel(shape: Shape, op: &FRelOp, a: u128, b: u128) -> u128 {
+    match shape {
+        Shape::F32x4 => float_rel!(f32, i32, op, a, b),
+        Shape::F64x2 => float_rel!(f64, i64, op, a, b),
+        _ => unreachable!(),
+    }
+}
+
+pub fn convert(op: &VConvertOp, v: u128) -> u128 {
+    use crate::ast::VConvertOp::*;
+
+    // NB: float to integer `as` casts saturate and map NaN to 0
+    match *op {
+        F32x4DemoteF64x2Zero => map::<f64, f32>(v, FloatDemoteOp::demote),
+        F64x2PromoteLowF32x4 => widen::<f32, f64>(v, 0, FloatPromoteOp::promote),
+        I32x4TruncSatF32x4(true) => map::<f32, i32>(v, |x| x as i32),
+        I32x4TruncSatF32x4(false) => map::<f32, u32>(v, |x| x as u32),
+        I32x4TruncSatF64x2Zero(true) => map::<f64, i32>(v, |x| x as i32),
+        I32x4TruncSatF64x2Zero(false) => map::<f64, u32>(v, |x| x as u32),
+        F32x4ConvertI32x4(true) => map::<i32, f32>(v, |x| x as f32),
+        F32x4ConvertI32x4(false) => map::<u32, f32>(v, |x| x as f32),
+        F64x2ConvertLowI32x4(true) => widen::<i32, f64>(v, 0, |x| x as f64),
+        F64x2ConvertLowI32x4(false) => widen::<u32, f64>(v, 0, |x| x as f64),
+    }
+}
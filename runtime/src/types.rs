@@ -1,19 +1,21 @@
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Float {
     F32,
     F64,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Int {
     I32,
     I64,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Value {
     Int(Int),
     Float(Float),
+    V128,
+    ExnRef,
 }
 
 impl Value {
@@ -21,6 +23,16 @@ impl Value {
         match *self {
             Value::Int(Int::I32) | Value::Float(Float::F32) => 32,
             Value::Int(Int::I64) | Value::Float(Float::F64) => 64,
+            Value::V128 => 128,
+            Value::ExnRef => unreachable!("references have no bit representation"),
+        }
+    }
+
+    /// Number of 64-bit slots taken by a value of this type on the interpreter stack
+    pub fn slots(&self) -> usize {
+        match *self {
+            Value::V128 => 2,
+            _ => 1,
         }
     }
 }
@@ -30,13 +42,15 @@ pub static I32: Value = Value::Int(Int::I32);
 pub static I64: Value = Value::Int(Int::I64);
 pub static F32: Value = Value::Float(Float::F32);
 pub static F64: Value = Value::Float(Float::F64);
+pub static V128: Value = Value::V128;
+pub static EXNREF: Value = Value::ExnRef;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Elem {
     AnyFunc,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Func {
     pub args: Vec<Value>,
     pub result: Vec<Value>,
@@ -66,6 +80,7 @@ pub struct Table {
 #[derive(Debug, Clone)]
 pub struct Memory {
     pub limits: Limits,
+    pub shared: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -81,6 +96,7 @@ pub enum Extern {
     Table(Table),
     Memory(Memory),
     Global(Global),
+    Tag(Func),
 }
 
 impl Extern {
@@ -100,8 +116,9 @@ impl Extern {
         match (self, other) {
             (Func(f1), Func(f2)) => f1 == f2,
             (Table(t1), Table(t2)) => t1.elem == t2.elem && t1.limits.matches(&t2.limits),
-            (Memory(m1), Memory(m2)) => m1.limits.matches(&m2.limits),
+            (Memory(m1), Memory(m2)) => m1.shared == m2.shared && m1.limits.matches(&m2.limits),
             (Global(g1), Global(g2)) => g1 == g2,
+            (Tag(t1), Tag(t2)) => t1 == t2,
             _ => false,
         }
     }
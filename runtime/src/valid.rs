@@ -6,8 +6,16 @@ use std::collections::HashSet;
 
 static EMPTY_TYPE: [types::Value; 0] = [];
 
+/// Check a whole module, function bodies included
+#[cfg(feature = "test")]
 pub fn is_valid(module: &ast::Module) -> bool {
-    check_module(module).is_some()
+    match check_module(module) {
+        Some(mod_ctx) => module
+            .funcs
+            .iter()
+            .all(|func| check_func(&mod_ctx, func).is_some()),
+        None => false,
+    }
 }
 
 #[derive(PartialEq, Clone, Copy)]
@@ -32,13 +40,15 @@ struct Frame<'a> {
     unreachable: bool,
 }
 
-/// A typing context for a module
-struct ModContext<'a> {
-    types: &'a [types::Func],
-    funcs: Vec<&'a types::Func>,
-    tables: Vec<&'a types::Table>,
-    memories: Vec<&'a types::Memory>,
-    globals: Vec<&'a types::Global>,
+/// A typing context for a module. It outlives the module so that function
+/// bodies can be checked after instantiation.
+pub struct ModContext {
+    pub types: Vec<types::Func>,
+    pub funcs: Vec<types::Func>,
+    pub tables: Vec<types::Table>,
+    pub memories: Vec<types::Memory>,
+    pub globals: Vec<types::Global>,
+    pub tags: Vec<types::Func>,
 }
 
 /// A typing context for a function
@@ -170,21 +180,33 @@ fn check_const_expr(
     instrs: &[ast::Instr],
     result: types::Value,
 ) -> Option<()> {
-    // Right now constant expressions are limited to Const and GetGlobal. A
-    // direct consequence is that to be valid with type `result`, a constant
-    // expression must have a single instruction that leaves a value of type
-    // `result` on the operand stack.
+    // Constant expressions are limited to constants, immutable global reads
+    // and integer addition, subtraction and multiplication. There is no control
+    // flow, so tracking the type of each operand is enough: the expression
+    // must end with a single value of type `result` on the stack.
+    use crate::ast::IBinOp::{Add, Mul, Sub};
+    use crate::ast::Instr::*;
 
-    require(instrs.len() == 1)?;
+    let mut operands = Vec::new();
 
-    match instrs[0] {
-        ast::Instr::Const(v) => require(v.type_() == result),
-        ast::Instr::GetGlobal(x) => {
-            let global = mod_ctx.globals.get(x as usize)?;
-            require(!global.mutable && global.value == result)
+    for instr in instrs {
+        match *instr {
+            Const(v) => operands.push(v.type_()),
+            GetGlobal(x) => {
+                let global = mod_ctx.globals.get(x as usize)?;
+                require(!global.mutable)?;
+                operands.push(global.value);
+            }
+            IBin(t, Add) | IBin(t, Sub) | IBin(t, Mul) => {
+                require(operands.pop()? == Int(t))?;
+                require(operands.pop()? == Int(t))?;
+                operands.push(Int(t));
+            }
+            _ => return None,
         }
-        _ => None,
     }
+
+    require(operands == [result])
 }
 
 /// Check that the instruction sequence `instrs` is valid and has type `end_type`.
@@ -207,6 +229,25 @@ fn check_expr<'a>(
     pop_frame(frames, operands)
 }
 
+/// Check that the label of a handler accepts the values it pushes.
+fn check_catch(mod_ctx: &ModContext, frames: &Vec<Frame>, catch: &ast::Catch) -> Option<()> {
+    use crate::ast::Catch::*;
+
+    let (args, label, ref_): (&[types::Value], _, _) = match *catch {
+        Tag(x, label) => (&mod_ctx.tags.get(x as usize)?.args[..], label, false),
+        TagRef(x, label) => (&mod_ctx.tags.get(x as usize)?.args[..], label, true),
+        All(label) => (&[], label, false),
+        AllRef(label) => (&[], label, true),
+    };
+    let label_type = get_label(frames, label)?;
+
+    let mut expected = args.to_vec();
+    if ref_ {
+        expected.push(ExnRef);
+    }
+    require(label_type == &expected[..])
+}
+
 fn check_instr<'a>(
     mod_ctx: &ModContext,
     func_ctx: &FuncContext,
@@ -276,6 +317,33 @@ fn check_instr<'a>(
             }
         }
 
+        TryTable(ref result_type, ref catches, ref instrs) => {
+            // labels of handlers are relative to the frames outside the try_table
+            for catch in catches {
+                check_catch(mod_ctx, frames, catch)?;
+            }
+            check_expr(
+                mod_ctx,
+                func_ctx,
+                operands,
+                frames,
+                &result_type[..],
+                &result_type[..],
+                instrs,
+            )?;
+        }
+
+        Throw(x) => {
+            let tag = mod_ctx.tags.get(x as usize)?;
+            exact_step(operands, frames, &tag.args[..], &[])?;
+            unreachable(frames, operands);
+        }
+
+        ThrowRef => {
+            pop_expected(operands, frames, Operand::Exact(ExnRef))?;
+            unreachable(frames, operands);
+        }
+
         Br(nesting_levels) => {
             {
                 let label_type = get_label(frames, nesting_levels)?;
@@ -320,6 +388,27 @@ fn check_instr<'a>(
             exact_step(operands, frames, &func.args[..], &func.result[..])?;
         }
 
+        ReturnCall(func_index) => {
+            {
+                // the callee's results become the results of the current function
+                let func = mod_ctx.funcs.get(func_index as usize)?;
+                require(func.result == func_ctx.return_type)?;
+                exact_step(operands, frames, &func.args[..], &[])?;
+            }
+            unreachable(frames, operands);
+        }
+
+        ReturnCallIndirect(index) => {
+            {
+                let _ = mod_ctx.tables.first()?;
+                let func = mod_ctx.types.get(index as usize)?;
+                require(func.result == func_ctx.return_type)?;
+                pop_expected(operands, frames, Operand::Exact(Int(I32)))?;
+                exact_step(operands, frames, &func.args[..], &[])?;
+            }
+            unreachable(frames, operands);
+        }
+
         Drop_ => {
             let _ = pop_operand(operands, frames)?;
         }
@@ -360,12 +449,12 @@ fn check_instr<'a>(
             exact_step(operands, frames, &[t], &[])?;
         }
 
-        Load(ref load_op) => {
+        Load(ref load_op, _) => {
             check_mem_op(mod_ctx, load_op, |&(size, _)| size)?;
             exact_step(operands, frames, &[Int(I32)], &[load_op.type_])?;
         }
 
-        Store(ref store_op) => {
+        Store(ref store_op, _) => {
             check_mem_op(mod_ctx, store_op, |&size| size)?;
             exact_step(operands, frames, &[Int(I32), store_op.type_], &[])?;
         }
@@ -413,6 +502,114 @@ fn check_instr<'a>(
         Convert(ref convert_op) => {
             check_convert_op(operands, frames, convert_op)?;
         }
+
+        VLoad(ref load_op) => {
+            use crate::ast::VLoadKind::*;
+            let (size, args): (u32, &[types::Value]) = match load_op.kind {
+                Full => (128, &[Int(I32)]),
+                Extend(..) => (64, &[Int(I32)]),
+                Splat(shape) | Zero(shape) => (shape.lane_bits(), &[Int(I32)]),
+                Lane(shape, lane) => {
+                    require(lane < shape.lanes())?;
+                    (shape.lane_bits(), &[Int(I32), V128])
+                }
+            };
+            check_vmem_op(mod_ctx, load_op, size)?;
+            exact_step(operands, frames, args, &[V128])?;
+        }
+
+        VStore(ref store_op) => {
+            let size = match store_op.kind {
+                ast::VStoreKind::Full => 128,
+                ast::VStoreKind::Lane(shape, lane) => {
+                    require(lane < shape.lanes())?;
+                    shape.lane_bits()
+                }
+            };
+            check_vmem_op(mod_ctx, store_op, size)?;
+            exact_step(operands, frames, &[Int(I32), V128], &[])?;
+        }
+
+        Shuffle(ref lanes) => {
+            require(lanes.iter().all(|&lane| lane < 32))?;
+            exact_step(operands, frames, &[V128, V128], &[V128])?;
+        }
+
+        Splat(shape) => {
+            exact_step(operands, frames, &[shape.lane_type()], &[V128])?;
+        }
+
+        ExtractLane(shape, lane, _) => {
+            require(lane < shape.lanes())?;
+            exact_step(operands, frames, &[V128], &[shape.lane_type()])?;
+        }
+
+        ReplaceLane(shape, lane) => {
+            require(lane < shape.lanes())?;
+            exact_step(operands, frames, &[V128, shape.lane_type()], &[V128])?;
+        }
+
+        VNot | VIUnary(..) | VFUnary(..) | VConvert(_) => {
+            exact_step(operands, frames, &[V128], &[V128])?;
+        }
+
+        Swizzle | VBit(_) | VIBin(..) | VFBin(..) | VIRel(..) | VFRel(..) => {
+            exact_step(operands, frames, &[V128, V128], &[V128])?;
+        }
+
+        Bitselect => {
+            exact_step(operands, frames, &[V128, V128, V128], &[V128])?;
+        }
+
+        AnyTrue | AllTrue(_) | Bitmask(_) => {
+            exact_step(operands, frames, &[V128], &[Int(I32)])?;
+        }
+
+        VShift(..) => {
+            exact_step(operands, frames, &[V128, Int(I32)], &[V128])?;
+        }
+
+        AtomicLoad(ref op) => {
+            check_atomic_op(mod_ctx, op)?;
+            exact_step(operands, frames, &[Int(I32)], &[op.type_])?;
+        }
+
+        AtomicStore(ref op) => {
+            check_atomic_op(mod_ctx, op)?;
+            exact_step(operands, frames, &[Int(I32), op.type_], &[])?;
+        }
+
+        AtomicRmw(_, ref op) => {
+            check_atomic_op(mod_ctx, op)?;
+            exact_step(operands, frames, &[Int(I32), op.type_], &[op.type_])?;
+        }
+
+        AtomicCmpxchg(ref op) => {
+            check_atomic_op(mod_ctx, op)?;
+            exact_step(
+                operands,
+                frames,
+                &[Int(I32), op.type_, op.type_],
+                &[op.type_],
+            )?;
+        }
+
+        AtomicWait(ref op) => {
+            check_atomic_op(mod_ctx, op)?;
+            exact_step(
+                operands,
+                frames,
+                &[Int(I32), op.type_, Int(I64)],
+                &[Int(I32)],
+            )?;
+        }
+
+        AtomicNotify(ref op) => {
+            check_atomic_op(mod_ctx, op)?;
+            exact_step(operands, frames, &[Int(I32), Int(I32)], &[Int(I32)])?;
+        }
+
+        AtomicFence => {}
     }
 
     Some(())
@@ -436,6 +633,19 @@ where
     Some(())
 }
 
+/// Check a vector memory access of `size` bits
+fn check_vmem_op<T>(mod_ctx: &ModContext, op: &ast::VMemOp<T>, size: u32) -> Option<()> {
+    require(!mod_ctx.memories.is_empty())?;
+    require(op.align < 32 && 1 << op.align <= size / 8)
+}
+
+/// Check an atomic memory access, which must be naturally aligned
+fn check_atomic_op(mod_ctx: &ModContext, op: &ast::AtomicOp) -> Option<()> {
+    require(!mod_ctx.memories.is_empty())?;
+    let size = op.opt.unwrap_or_else(|| op.type_.bit_width());
+    require(op.align < 32 && 1 << op.align == size / 8)
+}
+
 fn check_convert_op(
     operands: &mut Vec<Operand>,
     frames: &mut Vec<Frame>,
@@ -454,7 +664,8 @@ fn check_convert_op(
     }
 }
 
-fn check_func(mod_ctx: &ModContext, func: &ast::Func) -> Option<()> {
+/// Check the body of a function of a module checked by `check_module`
+pub fn check_func(mod_ctx: &ModContext, func: &ast::Func) -> Option<()> {
     // TODO: cache those vectors to reuse allocated memory
     let mut frames = Vec::new();
     let mut operands = Vec::new();
@@ -519,11 +730,18 @@ fn check_elem(mod_ctx: &ModContext, elem: &ast::Segment<ast::Index>) -> Option<(
     check_const_expr(mod_ctx, &elem.offset, Int(I32))
 }
 
-fn check_data(mod_ctx: &ModContext, data: &ast::Segment<u8>) -> Option<()> {
+fn check_data(mod_ctx: &ModContext, data: &ast::Data) -> Option<()> {
     let _ = mod_ctx.memories.get(data.index as usize)?;
     check_const_expr(mod_ctx, &data.offset, Int(I32))
 }
 
+/// Resolve the function type of an exception tag, which cannot have results
+fn check_tag_type(types: &[types::Func], type_index: ast::Index) -> Option<&types::Func> {
+    let type_ = types.get(type_index as usize)?;
+    require(type_.result.is_empty())?;
+    Some(type_)
+}
+
 fn check_start(mod_ctx: &ModContext, start: &ast::Index) -> Option<()> {
     let func = mod_ctx.funcs.get(*start as usize)?;
     require(func.args.is_empty() && func.result.is_empty())
@@ -536,43 +754,51 @@ fn check_export(mod_ctx: &ModContext, export: &ast::Export) -> Option<()> {
         Func(x) => require((x as usize) < mod_ctx.funcs.len()),
         Table(x) => require((x as usize) < mod_ctx.tables.len()),
         Memory(x) => require((x as usize) < mod_ctx.memories.len()),
-        Global(x) => require(!mod_ctx.globals.get(x as usize)?.mutable),
+        Global(x) => require((x as usize) < mod_ctx.globals.len()),
+        Tag(x) => require((x as usize) < mod_ctx.tags.len()),
     }
 }
 
 /// Validate an import and insert it into the context of the module
-fn check_import<'a>(ctx: &mut ModContext<'a>, import: &'a ast::Import) -> Option<()> {
+fn check_import(ctx: &mut ModContext, import: &ast::Import) -> Option<()> {
     use crate::ast::ImportDesc::*;
 
     match import.desc {
         Func(x) => {
-            ctx.funcs.push(ctx.types.get(x as usize)?);
+            let type_ = ctx.types.get(x as usize)?.clone();
+            ctx.funcs.push(type_);
         }
         Table(ref table_type) => {
             check_limits(&table_type.limits)?;
-            ctx.tables.push(table_type);
+            ctx.tables.push(table_type.clone());
         }
         Memory(ref mem_type) => {
             check_limits(&mem_type.limits)?;
-            ctx.memories.push(mem_type);
+            ctx.memories.push(mem_type.clone());
         }
         Global(ref global_type) => {
-            require(!global_type.mutable)?;
-            ctx.globals.push(global_type);
+            ctx.globals.push(global_type.clone());
+        }
+        Tag(x) => {
+            let type_ = check_tag_type(&ctx.types, x)?.clone();
+            ctx.tags.push(type_);
         }
     }
 
     Some(())
 }
 
-fn check_module(module: &ast::Module) -> Option<()> {
+/// Check everything in a module but the bodies of its functions, returning
+/// the context needed to check them with `check_func`
+pub fn check_module(module: &ast::Module) -> Option<ModContext> {
     // create an empty context with only the types defined in the module
     let mut mod_ctx = ModContext {
-        types: &module.types,
+        types: module.types.clone(),
         funcs: Vec::new(),
         tables: Vec::new(),
         memories: Vec::new(),
         globals: Vec::new(),
+        tags: Vec::new(),
     };
 
     // first resolve imports from the module
@@ -582,16 +808,19 @@ fn check_module(module: &ast::Module) -> Option<()> {
 
     // then extend the context with funcs, tables and memories from the module
     for func in &module.funcs {
-        mod_ctx
-            .funcs
-            .push(mod_ctx.types.get(func.type_index as usize)?);
+        let type_ = mod_ctx.types.get(func.type_index as usize)?.clone();
+        mod_ctx.funcs.push(type_);
     }
     mod_ctx
         .tables
-        .extend(module.tables.iter().map(|table| &table.type_));
+        .extend(module.tables.iter().map(|table| table.type_.clone()));
     mod_ctx
         .memories
-        .extend(module.memories.iter().map(|mem| &mem.type_));
+        .extend(module.memories.iter().map(|mem| mem.type_.clone()));
+    for tag in &module.tags {
+        let type_ = check_tag_type(&mod_ctx.types, tag.type_index)?.clone();
+        mod_ctx.tags.push(type_);
+    }
 
     // check globals before adding them to the context to prevent recursivity
     for global in &module.globals {
@@ -599,15 +828,12 @@ fn check_module(module: &ast::Module) -> Option<()> {
     }
     mod_ctx
         .globals
-        .extend(module.globals.iter().map(|global| &global.type_));
+        .extend(module.globals.iter().map(|global| global.type_.clone()));
 
     // finaly check everything else
     for type_ in &module.types {
         check_type(type_)?;
     }
-    for func in &module.funcs {
-        check_func(&mod_ctx, func)?;
-    }
     for table in &module.tables {
         check_table(table)?;
     }
@@ -630,5 +856,7 @@ fn check_module(module: &ast::Module) -> Option<()> {
         unique_exports.insert(&export.name);
     }
 
-    require(mod_ctx.tables.len() <= 1 && mod_ctx.memories.len() <= 1)
+    require(mod_ctx.tables.len() <= 1 && mod_ctx.memories.len() <= 1)?;
+
+    Some(mod_ctx)
 }
@@ -1,3 +1,4 @@
+use crate::runtime::ExnAddr;
 use crate::types;
 
 #[derive(Debug, Clone, Copy)]
@@ -6,6 +7,8 @@ pub enum Value {
     I64(u64),
     F32(f32),
     F64(f64),
+    V128(u128),
+    ExnRef(Option<ExnAddr>),
 }
 
 impl PartialEq for Value {
@@ -18,6 +21,8 @@ impl PartialEq for Value {
             (I64(a), I64(b)) => a == b,
             (F32(a), F32(b)) => a.to_bits() == b.to_bits(),
             (F64(a), F64(b)) => a.to_bits() == b.to_bits(),
+            (V128(a), V128(b)) => a == b,
+            (ExnRef(a), ExnRef(b)) => a == b,
             _ => false,
         }
     }
@@ -32,28 +37,14 @@ impl Value {
         Value::I64(v as u64)
     }
 
-    pub fn from_bool(v: bool) -> Value {
-        if v {
-            Value::true_()
-        } else {
-            Value::false_()
-        }
-    }
-
-    pub fn false_() -> Value {
-        Value::I32(0)
-    }
-
-    pub fn true_() -> Value {
-        Value::I32(1)
-    }
-
     pub fn type_(&self) -> types::Value {
         match *self {
             Value::I32(_) => types::I32,
             Value::I64(_) => types::I64,
             Value::F32(_) => types::F32,
             Value::F64(_) => types::F64,
+            Value::V128(_) => types::V128,
+            Value::ExnRef(_) => types::EXNREF,
         }
     }
 }
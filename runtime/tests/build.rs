@@ -26,9 +26,16 @@ fn main() {
             "#[test]
 fn run_{}() {{
 	script::run(\"{}\");
+}}
+
+#[test]
+fn compact_{}() {{
+	script::run_compact(\"{}\");
 }}\n",
             name,
             path.display(),
+            name,
+            path.display(),
         )
         .unwrap();
     }
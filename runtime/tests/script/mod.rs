@@ -30,6 +30,7 @@ pub enum Assertion {
     TrapAction(Action, String),
     TrapInstantiate(ModuleSource, String),
     Exhaustion(Action, String),
+    Exception(Action),
     Invalid(ModuleSource, String),
     Malformed(ModuleSource, String),
     Unlinkable(ModuleSource, String),
@@ -118,6 +118,7 @@ impl<'a> Parser<'a> {
             "assert_return_canonical_nan" => Cmd::Assertion(ReturnCanonicalNan(self.action())),
             "assert_return_arithmetic_nan" => Cmd::Assertion(ReturnArithmeticNan(self.action())),
             "assert_exhaustion" => Cmd::Assertion(Exhaustion(self.action(), self.string())),
+            "assert_exception" => Cmd::Assertion(Exception(self.action())),
             "assert_invalid" => Cmd::Assertion(Invalid(self.module(), self.string())),
             "assert_malformed" => Cmd::Assertion(Malformed(self.module(), self.string())),
             "assert_unlinkable" => Cmd::Assertion(Unlinkable(self.module(), self.string())),
@@ -224,15 +225,46 @@ impl<'a> Parser<'a> {
                 ),
                 "f32.const" => Value::F32(parse_f32(self.next_token())),
                 "f64.const" => Value::F64(parse_f64(self.next_token())),
+                "v128.const" => Value::V128(self.v128()),
                 s => panic!(expected(
                     s,
-                    &["i32.const", "i64.const", "f32.const", "f64.const"]
+                    &[
+                        "i32.const",
+                        "i64.const",
+                        "f32.const",
+                        "f64.const",
+                        "v128.const"
+                    ]
                 )),
             });
             self.close();
         }
     }
 
+    fn v128(&mut self) -> u128 {
+        let shape = self.next_token();
+        let (lanes, bits) = match shape {
+            "i8x16" => (16, 8),
+            "i16x8" => (8, 16),
+            "i32x4" | "f32x4" => (4, 32),
+            "i64x2" | "f64x2" => (2, 64),
+            s => panic!(
+                "{}",
+                expected(s, &["i8x16", "i16x8", "i32x4", "i64x2", "f32x4", "f64x2"])
+            ),
+        };
+
+        (0..lanes).fold(0, |v, i| {
+            let s = self.next_token();
+            let lane = match shape {
+                "f32x4" => parse_f32(s).to_bits() as u128,
+                "f64x2" => parse_f64(s).to_bits() as u128,
+                _ => parse_int_lane(s) as u128,
+            };
+            v | (lane & (!0 >> (128 - bits))) << (i * bits)
+        })
+    }
+
     fn opt_id(&mut self) -> Option<String> {
         if &self.lexer.peek()?[0..1] != "$" {
             return None;
@@ -348,6 +380,26 @@ fn parse_hex_digit(c: u8) -> u8 {
     }
 }
 
+/// Parse a vector lane literal, either signed or unsigned, decimal or hexadecimal
+fn parse_int_lane(s: &str) -> i128 {
+    let s = s.replace('_', "");
+    let (negate, s) = match &s[0..1] {
+        "-" => (true, &s[1..]),
+        "+" => (false, &s[1..]),
+        _ => (false, &s[..]),
+    };
+    let z = match s.get(..2) {
+        Some("0x") => i128::from_str_radix(&s[2..], 16),
+        _ => i128::from_str(s),
+    }
+    .expect("invalid integer lane literal");
+    if negate {
+        -z
+    } else {
+        z
+    }
+}
+
 macro_rules! define_parse_f {
     ($name:ident, $f:ident, $i:ident, $parse_hex:ident) => {
         fn $name(s: &str) -> $f {
@@ -1,7 +1,8 @@
 use crate::script::*;
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{Cursor, Read};
+use std::io::Read;
 use std::path::Path;
 use std::{f32, f64};
 use watt_runtime::*;
@@ -11,9 +12,18 @@ type Exports = HashMap<String, ExternVal>;
 struct Registry {
     mod_exports: HashMap<Option<String>, Exports>,
     last_key: Option<String>,
+    /// Whether modules are converted to the compact format before being used
+    compact: bool,
+    /// Whether a module could not be compacted, its bodies being validated
+    /// when called
+    uncompacted: Cell<bool>,
 }
 
 pub fn run<P: AsRef<Path>>(path: P) {
+    run_script(path, false);
+}
+
+fn run_script<P: AsRef<Path>>(path: P, compact: bool) {
     let mut f = File::open(path).unwrap();
     let mut src = String::new();
     f.read_to_string(&mut src).unwrap();
@@ -23,7 +33,10 @@ pub fn run<P: AsRef<Path>>(path: P) {
     let mut registry = Registry {
         mod_exports: HashMap::new(),
         last_key: None,
+        compact,
+        uncompacted: Cell::new(false),
     };
+    let validated = validated_funcs();
 
     // Special test host module
     init_spectest(&mut store, &mut registry);
@@ -31,14 +44,15 @@ pub fn run<P: AsRef<Path>>(path: P) {
     for cmd in parser {
         match cmd {
             Cmd::ModuleSource(src) => {
-                let (opt_name, m) = decode_module_src(&src);
+                let (opt_name, inst, export_names) = with_module(&registry, &src, |opt_name, m| {
+                    let imports = resolve_imports(&m, &registry).unwrap();
+                    let export_names: Vec<String> = module_exports(&m)
+                        .map(|(name, _)| name.to_owned())
+                        .collect();
 
-                let imports = resolve_imports(&m, &mut registry).unwrap();
-                let export_names: Vec<String> = module_exports(&m)
-                    .map(|(name, _)| name.to_owned())
-                    .collect();
-
-                let inst = instantiate_module(&mut store, m, &imports[..]).unwrap();
+                    let inst = instantiate_module(&mut store, m, &imports[..]).unwrap();
+                    (opt_name, inst, export_names)
+                });
 
                 let exports = export_names
                     .into_iter()
@@ -68,17 +82,80 @@ pub fn run<P: AsRef<Path>>(path: P) {
             }
         }
     }
+
+    // Compact modules come with their bodies already lowered
+    if compact && !registry.uncompacted.get() {
+        assert_eq!(validated_funcs(), validated);
+    }
 }
 
-fn decode_module_src(module: &ModuleSource) -> (Option<String>, ast::Module) {
+fn decode_module_src(module: &ModuleSource) -> (Option<String>, ast::Module<'_>) {
     match *module {
         ModuleSource::Binary(ref name, ref bytes) => {
-            (name.clone(), decode_module(Cursor::new(bytes)).unwrap())
+            (name.clone(), decode_module(bytes).unwrap())
         }
         ModuleSource::Quoted(_, _) => unimplemented!("quoted modules are not supported"),
     }
 }
 
+// Decode a module of the script, converting it to the compact format first
+// when the script runs on compact modules
+fn with_module<R>(
+    registry: &Registry,
+    module: &ModuleSource,
+    f: impl FnOnce(Option<String>, ast::Module) -> R,
+) -> R {
+    if let (true, ModuleSource::Binary(ref name, ref bytes)) = (registry.compact, module) {
+        match compact_module(bytes) {
+            Ok(compact) => return f(name.clone(), decode_compact_module(&compact).unwrap()),
+            Err(Error::InvalidModule) => registry.uncompacted.set(true),
+            Err(err) => panic!("failed to compact module: {:?}", err),
+        }
+    }
+    let (name, m) = decode_module_src(module);
+    f(name, m)
+}
+
+// Convert the modules of the script to the compact format and back, checking
+// that they keep their imports and exports, and that invalid modules are
+// refused, then run the script on the compact modules
+pub fn run_compact<P: AsRef<Path>>(path: P) {
+    let mut f = File::open(&path).unwrap();
+    let mut src = String::new();
+    f.read_to_string(&mut src).unwrap();
+    let parser = parser::Parser::new(&src);
+
+    for cmd in parser {
+        match cmd {
+            Cmd::ModuleSource(ModuleSource::Binary(_, bytes)) => {
+                let m = decode_module(&bytes).unwrap();
+                let compact = match compact_module(&bytes) {
+                    Ok(compact) => compact,
+                    // Function bodies are only checked when called, so valid
+                    // scripts can hold modules that cannot be compacted
+                    Err(Error::InvalidModule) => continue,
+                    Err(err) => panic!("failed to compact module: {:?}", err),
+                };
+                let c = decode_compact_module(&compact).unwrap();
+                assert_eq!(
+                    format!("{:?}", module_imports(&m).collect::<Vec<_>>()),
+                    format!("{:?}", module_imports(&c).collect::<Vec<_>>()),
+                );
+                assert_eq!(
+                    format!("{:?}", module_exports(&m).collect::<Vec<_>>()),
+                    format!("{:?}", module_exports(&c).collect::<Vec<_>>()),
+                );
+            }
+            Cmd::Assertion(Assertion::Invalid(ModuleSource::Binary(_, bytes), _)) => {
+                assert_eq!(compact_module(&bytes).unwrap_err(), Error::InvalidModule);
+            }
+            _ => {}
+        }
+    }
+
+    run_script(path, true);
+}
+
 fn run_assertion(store: &mut Store, registry: &Registry, assertion: Assertion) {
     use self::Assertion::*;
 
@@ -139,14 +216,13 @@ fn run_assertion(store: &mut Store, registry: &Registry, assertion: Assertion) {
                 panic!("the action `{:?}` should cause a trap", action);
             }
         }
-        TrapInstantiate(module, _) => {
-            let (_, m) = decode_module_src(&module);
+        TrapInstantiate(module, _) => with_module(registry, &module, |_, m| {
             let imports = resolve_imports(&m, registry).unwrap();
             if let Err(Error::CodeTrapped(_)) = instantiate_module(store, m, &imports[..]) {
             } else {
                 panic!("instantiating module `{:?}` should cause a trap", module);
             }
-        }
+        }),
         Exhaustion(action, reason) => {
             match (reason.as_ref(), run_action(store, registry, &action)) {
                 ("call stack exhausted", Err(Error::StackOverflow)) => (),
@@ -156,13 +232,19 @@ fn run_assertion(store: &mut Store, registry: &Registry, assertion: Assertion) {
                 ),
             };
         }
+        Exception(action) => {
+            if let Err(Error::UncaughtException) = run_action(store, registry, &action) {
+            } else {
+                panic!("the action `{:?}` should throw an exception", action);
+            }
+        }
         Invalid(module, reason) => {
             let (_, m) = decode_module_src(&module);
-            // Do not resolve the imports for invalid modules
-            match (reason, instantiate_module(store, m, &[]).err()) {
+            // Function bodies are only checked when called by instantiated modules
+            match (reason, validate_module(&m)) {
                 (_, Some(Error::InvalidModule)) => (),
                 (reason, err) => panic!(
-                    "instantiating module `{:?}` should not be valid (reason = {}, err = {:?})",
+                    "validating module `{:?}` should fail (reason = {}, err = {:?})",
                     module, reason, err
                 ),
             }
@@ -171,7 +253,7 @@ fn run_assertion(store: &mut Store, registry: &Registry, assertion: Assertion) {
             match mod_src {
                 ModuleSource::Binary(_, bytes) => {
                     assert_eq!(
-                        decode_module(Cursor::new(bytes)).unwrap_err(),
+                        decode_module(&bytes).unwrap_err(),
                         Error::DecodeModuleFailed
                     );
                 }
@@ -180,9 +262,7 @@ fn run_assertion(store: &mut Store, registry: &Registry, assertion: Assertion) {
                 }
             }
         }
-        Unlinkable(module, reason) => {
-            let (_, m) = decode_module_src(&module);
-
+        Unlinkable(module, reason) => with_module(registry, &module, |_, m| {
             let imports = match (reason.as_ref(), resolve_imports(&m, registry)) {
                 ("unknown import", Err(_)) => return,
                 (_, Err(err)) => panic!("failed to resolve import: `{:?}`", err),
@@ -201,7 +281,7 @@ fn run_assertion(store: &mut Store, registry: &Registry, assertion: Assertion) {
                     module, reason, err
                 ),
             }
-        }
+        }),
     }
 }
 
@@ -269,13 +349,14 @@ fn init_spectest(store: &mut Store, registry: &mut Registry) {
                     min: 1,
                     max: Some(2),
                 },
+                shared: false,
             },
         )),
     );
 
     fn print(store: &mut Store, args_types: Vec<types::Value>) -> ExternVal {
         let args_len = args_types.len();
-        let func = move |stack: &mut Vec<values::Value>| {
+        let func = move |_: &mut Caller, stack: &mut Vec<values::Value>| {
             for val in &stack[(stack.len() - args_len)..stack.len()] {
                 println!("{:?}", val);
             }
@@ -328,6 +409,106 @@ fn init_spectest(store: &mut Store, registry: &mut Registry) {
         global(store, values::Value::F64(666.0)),
     );
 
+    // Host functions accessing the memory of their caller
+    symbols.insert(
+        "sum_bytes".to_owned(),
+        ExternVal::Func(alloc_func(
+            store,
+            &types::Func {
+                args: vec![types::I32, types::I32],
+                result: vec![types::I32],
+            },
+            Box::new(|caller: &mut Caller, stack: &mut Vec<values::Value>| {
+                let mem = match caller.memory() {
+                    Some(mem) => mem,
+                    None => return Some("no memory".to_owned()),
+                };
+                let (ptr, len) = match (stack[0], stack[1]) {
+                    (values::Value::I32(ptr), values::Value::I32(len)) => (ptr, len),
+                    _ => unreachable!(),
+                };
+                let bytes = match mem.get(ptr as usize..(ptr + len) as usize) {
+                    Some(bytes) => bytes,
+                    None => return Some("out of bounds memory access".to_owned()),
+                };
+                let sum = bytes.iter().map(|&b| b as u32).sum();
+                *stack = vec![values::Value::I32(sum)];
+                None
+            }),
+        )),
+    );
+    symbols.insert(
+        "fill_bytes".to_owned(),
+        ExternVal::Func(alloc_func(
+            store,
+            &types::Func {
+                args: vec![types::I32, types::I32, types::I32],
+                result: Vec::new(),
+            },
+            Box::new(|caller: &mut Caller, stack: &mut Vec<values::Value>| {
+                let mem = match caller.memory() {
+                    Some(mem) => mem,
+                    None => return Some("no memory".to_owned()),
+                };
+                let (ptr, len, byte) = match (stack[0], stack[1], stack[2]) {
+                    (values::Value::I32(ptr), values::Value::I32(len), values::Value::I32(b)) => {
+                        (ptr, len, b as u8)
+                    }
+                    _ => unreachable!(),
+                };
+                match mem.get_mut(ptr as usize..(ptr + len) as usize) {
+                    Some(bytes) => bytes.iter_mut().for_each(|b| *b = byte),
+                    None => return Some("out of bounds memory access".to_owned()),
+                }
+                stack.clear();
+                None
+            }),
+        )),
+    );
+
+    // Host functions accessing the globals of their caller and the user data
+    symbols.insert(
+        "counter_inc".to_owned(),
+        ExternVal::Func(alloc_func(
+            store,
+            &types::Func {
+                args: Vec::new(),
+                result: vec![types::I32],
+            },
+            Box::new(|caller: &mut Caller, stack: &mut Vec<values::Value>| {
+                let counter = match caller.global("counter") {
+                    Some(values::Value::I32(counter)) => counter + 1,
+                    _ => return Some("no i32 counter".to_owned()),
+                };
+                if !caller.set_global("counter", values::Value::I32(counter)) {
+                    return Some("immutable counter".to_owned());
+                }
+                stack.push(values::Value::I32(counter));
+                None
+            }),
+        )),
+    );
+    set_host_data(store, 0u32);
+    symbols.insert(
+        "data_inc".to_owned(),
+        ExternVal::Func(alloc_func(
+            store,
+            &types::Func {
+                args: Vec::new(),
+                result: vec![types::I32],
+            },
+            Box::new(|caller: &mut Caller, stack: &mut Vec<values::Value>| {
+                let count = match caller.data::<u32>() {
+                    Some(count) => count,
+                    None => return Some("no user data".to_owned()),
+                };
+                *count += 1;
+                stack.push(values::Value::I32(*count));
+                None
+            }),
+        )),
+    );
+
     registry
         .mod_exports
         .insert(Some(String::from("spectest")), symbols);
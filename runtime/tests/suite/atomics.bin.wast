@@ -0,0 +1,255 @@
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\c0\80\80\80\00\0b\60"
+  "\01\7f\01\7f\60\01\7f\01\7e\60\02\7f\7f\01\7f\60"
+  "\02\7f\7e\01\7e\60\02\7f\7f\00\60\02\7f\7e\00\60"
+  "\03\7f\7f\7f\01\7f\60\03\7f\7e\7e\01\7e\60\03\7f"
+  "\7f\7e\01\7f\60\03\7f\7e\7e\01\7f\60\00\00\03\c4"
+  "\80\80\80\00\43\00\04\02\02\02\02\02\02\06\01\05"
+  "\03\03\03\03\03\03\07\00\04\02\02\02\02\02\02\06"
+  "\00\04\02\02\02\02\02\02\06\01\05\03\03\03\03\03"
+  "\03\07\01\05\03\03\03\03\03\03\07\01\05\03\03\03"
+  "\03\03\03\07\02\08\09\0a\05\84\80\80\80\00\01\03"
+  "\01\01\07\ed\86\80\80\00\43\08\6c\6f\61\64\5f\69"
+  "\33\32\00\00\09\73\74\6f\72\65\5f\69\33\32\00\01"
+  "\07\61\64\64\5f\69\33\32\00\02\07\73\75\62\5f\69"
+  "\33\32\00\03\07\61\6e\64\5f\69\33\32\00\04\06\6f"
+  "\72\5f\69\33\32\00\05\07\78\6f\72\5f\69\33\32\00"
+  "\06\08\78\63\68\67\5f\69\33\32\00\07\0b\63\6d\70"
+  "\78\63\68\67\5f\69\33\32\00\08\08\6c\6f\61\64\5f"
+  "\69\36\34\00\09\09\73\74\6f\72\65\5f\69\36\34\00"
+  "\0a\07\61\64\64\5f\69\36\34\00\0b\07\73\75\62\5f"
+  "\69\36\34\00\0c\07\61\6e\64\5f\69\36\34\00\0d\06"
+  "\6f\72\5f\69\36\34\00\0e\07\78\6f\72\5f\69\36\34"
+  "\00\0f\08\78\63\68\67\5f\69\36\34\00\10\0b\63\6d"
+  "\70\78\63\68\67\5f\69\36\34\00\11\0b\6c\6f\61\64"
+  "\5f\69\33\32\5f\38\75\00\12\0c\73\74\6f\72\65\5f"
+  "\69\33\32\5f\38\75\00\13\0a\61\64\64\5f\69\33\32"
+  "\5f\38\75\00\14\0a\73\75\62\5f\69\33\32\5f\38\75"
+  "\00\15\0a\61\6e\64\5f\69\33\32\5f\38\75\00\16\09"
+  "\6f\72\5f\69\33\32\5f\38\75\00\17\0a\78\6f\72\5f"
+  "\69\33\32\5f\38\75\00\18\0b\78\63\68\67\5f\69\33"
+  "\32\5f\38\75\00\19\0e\63\6d\70\78\63\68\67\5f\69"
+  "\33\32\5f\38\75\00\1a\0c\6c\6f\61\64\5f\69\33\32"
+  "\5f\31\36\75\00\1b\0d\73\74\6f\72\65\5f\69\33\32"
+  "\5f\31\36\75\00\1c\0b\61\64\64\5f\69\33\32\5f\31"
+  "\36\75\00\1d\0b\73\75\62\5f\69\33\32\5f\31\36\75"
+  "\00\1e\0b\61\6e\64\5f\69\33\32\5f\31\36\75\00\1f"
+  "\0a\6f\72\5f\69\33\32\5f\31\36\75\00\20\0b\78\6f"
+  "\72\5f\69\33\32\5f\31\36\75\00\21\0c\78\63\68\67"
+  "\5f\69\33\32\5f\31\36\75\00\22\0f\63\6d\70\78\63"
+  "\68\67\5f\69\33\32\5f\31\36\75\00\23\0b\6c\6f\61"
+  "\64\5f\69\36\34\5f\38\75\00\24\0c\73\74\6f\72\65"
+  "\5f\69\36\34\5f\38\75\00\25\0a\61\64\64\5f\69\36"
+  "\34\5f\38\75\00\26\0a\73\75\62\5f\69\36\34\5f\38"
+  "\75\00\27\0a\61\6e\64\5f\69\36\34\5f\38\75\00\28"
+  "\09\6f\72\5f\69\36\34\5f\38\75\00\29\0a\78\6f\72"
+  "\5f\69\36\34\5f\38\75\00\2a\0b\78\63\68\67\5f\69"
+  "\36\34\5f\38\75\00\2b\0e\63\6d\70\78\63\68\67\5f"
+  "\69\36\34\5f\38\75\00\2c\0c\6c\6f\61\64\5f\69\36"
+  "\34\5f\31\36\75\00\2d\0d\73\74\6f\72\65\5f\69\36"
+  "\34\5f\31\36\75\00\2e\0b\61\64\64\5f\69\36\34\5f"
+  "\31\36\75\00\2f\0b\73\75\62\5f\69\36\34\5f\31\36"
+  "\75\00\30\0b\61\6e\64\5f\69\36\34\5f\31\36\75\00"
+  "\31\0a\6f\72\5f\69\36\34\5f\31\36\75\00\32\0b\78"
+  "\6f\72\5f\69\36\34\5f\31\36\75\00\33\0c\78\63\68"
+  "\67\5f\69\36\34\5f\31\36\75\00\34\0f\63\6d\70\78"
+  "\63\68\67\5f\69\36\34\5f\31\36\75\00\35\0c\6c\6f"
+  "\61\64\5f\69\36\34\5f\33\32\75\00\36\0d\73\74\6f"
+  "\72\65\5f\69\36\34\5f\33\32\75\00\37\0b\61\64\64"
+  "\5f\69\36\34\5f\33\32\75\00\38\0b\73\75\62\5f\69"
+  "\36\34\5f\33\32\75\00\39\0b\61\6e\64\5f\69\36\34"
+  "\5f\33\32\75\00\3a\0a\6f\72\5f\69\36\34\5f\33\32"
+  "\75\00\3b\0b\78\6f\72\5f\69\36\34\5f\33\32\75\00"
+  "\3c\0c\78\63\68\67\5f\69\36\34\5f\33\32\75\00\3d"
+  "\0f\63\6d\70\78\63\68\67\5f\69\36\34\5f\33\32\75"
+  "\00\3e\06\6e\6f\74\69\66\79\00\3f\06\77\61\69\74"
+  "\33\32\00\40\06\77\61\69\74\36\34\00\41\05\66\65"
+  "\6e\63\65\00\42\0a\ed\87\80\80\00\43\88\80\80\80"
+  "\00\00\20\00\fe\10\02\00\0b\8a\80\80\80\00\00\20"
+  "\00\20\01\fe\17\02\00\0b\8a\80\80\80\00\00\20\00"
+  "\20\01\fe\1e\02\00\0b\8a\80\80\80\00\00\20\00\20"
+  "\01\fe\25\02\00\0b\8a\80\80\80\00\00\20\00\20\01"
+  "\fe\2c\02\00\0b\8a\80\80\80\00\00\20\00\20\01\fe"
+  "\33\02\00\0b\8a\80\80\80\00\00\20\00\20\01\fe\3a"
+  "\02\00\0b\8a\80\80\80\00\00\20\00\20\01\fe\41\02"
+  "\00\0b\8c\80\80\80\00\00\20\00\20\01\20\02\fe\48"
+  "\02\00\0b\88\80\80\80\00\00\20\00\fe\11\03\00\0b"
+  "\8a\80\80\80\00\00\20\00\20\01\fe\18\03\00\0b\8a"
+  "\80\80\80\00\00\20\00\20\01\fe\1f\03\00\0b\8a\80"
+  "\80\80\00\00\20\00\20\01\fe\26\03\00\0b\8a\80\80"
+  "\80\00\00\20\00\20\01\fe\2d\03\00\0b\8a\80\80\80"
+  "\00\00\20\00\20\01\fe\34\03\00\0b\8a\80\80\80\00"
+  "\00\20\00\20\01\fe\3b\03\00\0b\8a\80\80\80\00\00"
+  "\20\00\20\01\fe\42\03\00\0b\8c\80\80\80\00\00\20"
+  "\00\20\01\20\02\fe\49\03\00\0b\88\80\80\80\00\00"
+  "\20\00\fe\12\00\00\0b\8a\80\80\80\00\00\20\00\20"
+  "\01\fe\19\00\00\0b\8a\80\80\80\00\00\20\00\20\01"
+  "\fe\20\00\00\0b\8a\80\80\80\00\00\20\00\20\01\fe"
+  "\27\00\00\0b\8a\80\80\80\00\00\20\00\20\01\fe\2e"
+  "\00\00\0b\8a\80\80\80\00\00\20\00\20\01\fe\35\00"
+  "\00\0b\8a\80\80\80\00\00\20\00\20\01\fe\3c\00\00"
+  "\0b\8a\80\80\80\00\00\20\00\20\01\fe\43\00\00\0b"
+  "\8c\80\80\80\00\00\20\00\20\01\20\02\fe\4a\00\00"
+  "\0b\88\80\80\80\00\00\20\00\fe\13\01\00\0b\8a\80"
+  "\80\80\00\00\20\00\20\01\fe\1a\01\00\0b\8a\80\80"
+  "\80\00\00\20\00\20\01\fe\21\01\00\0b\8a\80\80\80"
+  "\00\00\20\00\20\01\fe\28\01\00\0b\8a\80\80\80\00"
+  "\00\20\00\20\01\fe\2f\01\00\0b\8a\80\80\80\00\00"
+  "\20\00\20\01\fe\36\01\00\0b\8a\80\80\80\00\00\20"
+  "\00\20\01\fe\3d\01\00\0b\8a\80\80\80\00\00\20\00"
+  "\20\01\fe\44\01\00\0b\8c\80\80\80\00\00\20\00\20"
+  "\01\20\02\fe\4b\01\00\0b\88\80\80\80\00\00\20\00"
+  "\fe\14\00\00\0b\8a\80\80\80\00\00\20\00\20\01\fe"
+  "\1b\00\00\0b\8a\80\80\80\00\00\20\00\20\01\fe\22"
+  "\00\00\0b\8a\80\80\80\00\00\20\00\20\01\fe\29\00"
+  "\00\0b\8a\80\80\80\00\00\20\00\20\01\fe\30\00\00"
+  "\0b\8a\80\80\80\00\00\20\00\20\01\fe\37\00\00\0b"
+  "\8a\80\80\80\00\00\20\00\20\01\fe\3e\00\00\0b\8a"
+  "\80\80\80\00\00\20\00\20\01\fe\45\00\00\0b\8c\80"
+  "\80\80\00\00\20\00\20\01\20\02\fe\4c\00\00\0b\88"
+  "\80\80\80\00\00\20\00\fe\15\01\00\0b\8a\80\80\80"
+  "\00\00\20\00\20\01\fe\1c\01\00\0b\8a\80\80\80\00"
+  "\00\20\00\20\01\fe\23\01\00\0b\8a\80\80\80\00\00"
+  "\20\00\20\01\fe\2a\01\00\0b\8a\80\80\80\00\00\20"
+  "\00\20\01\fe\31\01\00\0b\8a\80\80\80\00\00\20\00"
+  "\20\01\fe\38\01\00\0b\8a\80\80\80\00\00\20\00\20"
+  "\01\fe\3f\01\00\0b\8a\80\80\80\00\00\20\00\20\01"
+  "\fe\46\01\00\0b\8c\80\80\80\00\00\20\00\20\01\20"
+  "\02\fe\4d\01\00\0b\88\80\80\80\00\00\20\00\fe\16"
+  "\02\00\0b\8a\80\80\80\00\00\20\00\20\01\fe\1d\02"
+  "\00\0b\8a\80\80\80\00\00\20\00\20\01\fe\24\02\00"
+  "\0b\8a\80\80\80\00\00\20\00\20\01\fe\2b\02\00\0b"
+  "\8a\80\80\80\00\00\20\00\20\01\fe\32\02\00\0b\8a"
+  "\80\80\80\00\00\20\00\20\01\fe\39\02\00\0b\8a\80"
+  "\80\80\00\00\20\00\20\01\fe\40\02\00\0b\8a\80\80"
+  "\80\00\00\20\00\20\01\fe\47\02\00\0b\8c\80\80\80"
+  "\00\00\20\00\20\01\20\02\fe\4e\02\00\0b\8a\80\80"
+  "\80\00\00\20\00\20\01\fe\00\02\00\0b\8c\80\80\80"
+  "\00\00\20\00\20\01\20\02\fe\01\02\00\0b\8c\80\80"
+  "\80\00\00\20\00\20\01\20\02\fe\02\03\00\0b\85\80"
+  "\80\80\00\00\fe\03\00\0b"
+)
+(assert_return (invoke "store_i64" (i32.const 0) (i64.const 1229782938247303441)))
+(assert_return (invoke "load_i64" (i32.const 0)) (i64.const 1229782938247303441))
+(assert_return (invoke "load_i32" (i32.const 4)) (i32.const 286331153))
+(assert_return (invoke "load_i32_8u" (i32.const 1)) (i32.const 17))
+(assert_return (invoke "load_i64_16u" (i32.const 6)) (i64.const 4369))
+(assert_return (invoke "add_i32" (i32.const 0) (i32.const 305419896)) (i32.const 286331153))
+(assert_return (invoke "load_i32" (i32.const 0)) (i32.const 591751049))
+(assert_return (invoke "sub_i32_8u" (i32.const 0) (i32.const 144)) (i32.const 137))
+(assert_return (invoke "load_i32_8u" (i32.const 0)) (i32.const 249))
+(assert_return (invoke "load_i32" (i32.const 0)) (i32.const 591751161))
+(assert_return (invoke "and_i64" (i32.const 0) (i64.const -71777214294589696)) (i64.const 1229782938552723449))
+(assert_return (invoke "or_i64_32u" (i32.const 4) (i64.const 252645135)) (i64.const 285217024))
+(assert_return (invoke "xor_i32_16u" (i32.const 2) (i32.const 65535)) (i32.const 8960))
+(assert_return (invoke "load_i64" (i32.const 0)) (i64.const 2238041692819121920))
+(assert_return (invoke "xchg_i64_8u" (i32.const 7) (i64.const 427)) (i64.const 31))
+(assert_return (invoke "load_i64" (i32.const 0)) (i64.const -6120639215580518656))
+(assert_return (invoke "cmpxchg_i32_8u" (i32.const 0) (i32.const 256) (i32.const 66)) (i32.const 0))
+(assert_return (invoke "load_i32_8u" (i32.const 0)) (i32.const 66))
+(assert_return (invoke "cmpxchg_i32_8u" (i32.const 0) (i32.const 65) (i32.const 67)) (i32.const 66))
+(assert_return (invoke "load_i32_8u" (i32.const 0)) (i32.const 66))
+(assert_return (invoke "cmpxchg_i64" (i32.const 8) (i64.const 0) (i64.const -1)) (i64.const 0))
+(assert_return (invoke "load_i64" (i32.const 8)) (i64.const -1))
+(assert_return (invoke "cmpxchg_i64_32u" (i32.const 8) (i64.const 4294967295) (i64.const 5)) (i64.const 4294967295))
+(assert_return (invoke "load_i64" (i32.const 8)) (i64.const -4294967291))
+(assert_trap (invoke "load_i32" (i32.const 1)) "unaligned atomic")
+(assert_trap (invoke "store_i64" (i32.const 4) (i64.const 0)) "unaligned atomic")
+(assert_trap (invoke "add_i32_16u" (i32.const 3) (i32.const 0)) "unaligned atomic")
+(assert_trap (invoke "cmpxchg_i64_32u" (i32.const 2) (i64.const 0) (i64.const 0)) "unaligned atomic")
+(assert_return (invoke "load_i32_8u" (i32.const 65535)) (i32.const 0))
+(assert_trap (invoke "load_i32" (i32.const 65536)) "out of bounds memory access")
+(assert_trap (invoke "xchg_i64" (i32.const 65536) (i64.const 0)) "out of bounds memory access")
+(assert_return (invoke "notify" (i32.const 0) (i32.const 10)) (i32.const 0))
+(assert_trap (invoke "notify" (i32.const 1) (i32.const 10)) "unaligned atomic")
+(assert_return (invoke "wait32" (i32.const 16) (i32.const 1) (i64.const -1)) (i32.const 1))
+(assert_return (invoke "wait32" (i32.const 16) (i32.const 0) (i64.const 0)) (i32.const 2))
+(assert_return (invoke "wait64" (i32.const 16) (i64.const 0) (i64.const 1000)) (i32.const 2))
+(assert_trap (invoke "wait32" (i32.const 16) (i32.const 0) (i64.const -1)) "wait would block forever")
+(assert_trap (invoke "wait64" (i32.const 20) (i64.const 0) (i64.const 0)) "unaligned atomic")
+(assert_return (invoke "fence" ))
+
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\87\80\80\80\00\01\60"
+  "\02\7f\7f\01\7f\03\83\80\80\80\00\02\00\00\05\83"
+  "\80\80\80\00\01\00\01\07\90\80\80\80\00\02\06\77"
+  "\61\69\74\33\32\00\00\03\72\6d\77\00\01\0a\a1\80"
+  "\80\80\00\02\8c\80\80\80\00\00\20\00\20\01\42\00"
+  "\fe\01\02\00\0b\8a\80\80\80\00\00\20\00\20\01\fe"
+  "\1e\02\00\0b"
+)
+(assert_trap (invoke "wait32" (i32.const 0) (i32.const 0)) "expected shared memory")
+(assert_return (invoke "rmw" (i32.const 0) (i32.const 3)) (i32.const 0))
+(assert_return (invoke "rmw" (i32.const 0) (i32.const 3)) (i32.const 3))
+
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\c0\80\80\80\00\0b\60"
+    "\01\7f\01\7f\60\01\7f\01\7e\60\02\7f\7f\01\7f\60"
+    "\02\7f\7e\01\7e\60\02\7f\7f\00\60\02\7f\7e\00\60"
+    "\03\7f\7f\7f\01\7f\60\03\7f\7e\7e\01\7e\60\03\7f"
+    "\7f\7e\01\7f\60\03\7f\7e\7e\01\7f\60\00\00\03\82"
+    "\80\80\80\00\01\0a\05\84\80\80\80\00\01\03\01\01"
+    "\0a\8f\80\80\80\00\01\89\80\80\80\00\00\41\00\fe"
+    "\10\01\00\1a\0b"
+  )
+  "alignment must be equal to natural alignment"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\c0\80\80\80\00\0b\60"
+    "\01\7f\01\7f\60\01\7f\01\7e\60\02\7f\7f\01\7f\60"
+    "\02\7f\7e\01\7e\60\02\7f\7f\00\60\02\7f\7e\00\60"
+    "\03\7f\7f\7f\01\7f\60\03\7f\7e\7e\01\7e\60\03\7f"
+    "\7f\7e\01\7f\60\03\7f\7e\7e\01\7f\60\00\00\03\82"
+    "\80\80\80\00\01\0a\05\84\80\80\80\00\01\03\01\01"
+    "\0a\91\80\80\80\00\01\8b\80\80\80\00\00\41\00\42"
+    "\00\fe\1f\02\00\1a\0b"
+  )
+  "alignment must be equal to natural alignment"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\c0\80\80\80\00\0b\60"
+    "\01\7f\01\7f\60\01\7f\01\7e\60\02\7f\7f\01\7f\60"
+    "\02\7f\7e\01\7e\60\02\7f\7f\00\60\02\7f\7e\00\60"
+    "\03\7f\7f\7f\01\7f\60\03\7f\7e\7e\01\7e\60\03\7f"
+    "\7f\7e\01\7f\60\03\7f\7e\7e\01\7f\60\00\00\03\82"
+    "\80\80\80\00\01\0a\0a\8f\80\80\80\00\01\89\80\80"
+    "\80\00\00\41\00\fe\10\02\00\1a\0b"
+  )
+  "unknown memory"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\c0\80\80\80\00\0b\60"
+    "\01\7f\01\7f\60\01\7f\01\7e\60\02\7f\7f\01\7f\60"
+    "\02\7f\7e\01\7e\60\02\7f\7f\00\60\02\7f\7e\00\60"
+    "\03\7f\7f\7f\01\7f\60\03\7f\7e\7e\01\7e\60\03\7f"
+    "\7f\7e\01\7f\60\03\7f\7e\7e\01\7f\60\00\00\03\82"
+    "\80\80\80\00\01\0a\05\84\80\80\80\00\01\03\01\01"
+    "\0a\91\80\80\80\00\01\8b\80\80\80\00\00\41\00\42"
+    "\00\fe\1e\02\00\1a\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\c0\80\80\80\00\0b\60"
+    "\01\7f\01\7f\60\01\7f\01\7e\60\02\7f\7f\01\7f\60"
+    "\02\7f\7e\01\7e\60\02\7f\7f\00\60\02\7f\7e\00\60"
+    "\03\7f\7f\7f\01\7f\60\03\7f\7e\7e\01\7e\60\03\7f"
+    "\7f\7e\01\7f\60\03\7f\7e\7e\01\7f\60\00\00\03\82"
+    "\80\80\80\00\01\0a\05\84\80\80\80\00\01\03\01\01"
+    "\0a\93\80\80\80\00\01\8d\80\80\80\00\00\41\00\41"
+    "\00\41\00\fe\01\02\00\1a\0b"
+  )
+  "type mismatch"
+)
+(assert_malformed
+  (module binary
+    "\00\61\73\6d\01\00\00\00\05\83\80\80\80\00\01\02"
+    "\01"
+  )
+  "shared memory must have maximum"
+)
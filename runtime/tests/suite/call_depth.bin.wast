@@ -0,0 +1,11 @@
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\86\80\80\80\00\01\60"
+  "\01\7f\01\7f\03\82\80\80\80\00\01\00\07\89\80\80"
+  "\80\00\01\05\64\65\70\74\68\00\00\0a\9b\80\80\80"
+  "\00\01\95\80\80\80\00\00\20\00\45\04\7f\41\00\05"
+  "\20\00\41\01\6b\10\00\41\01\6a\0b\0b"
+)
+(assert_return (invoke "depth" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "depth" (i32.const 1000)) (i32.const 1000))
+(assert_return (invoke "depth" (i32.const 9000)) (i32.const 9000))
+(assert_exhaustion (invoke "depth" (i32.const 20000)) "call stack exhausted")
@@ -0,0 +1,133 @@
+(module $M binary
+  "\00\61\73\6d\01\00\00\00\01\99\80\80\80\00\06\60"
+  "\00\00\60\01\7f\00\60\00\01\7f\60\01\7f\01\7f\60"
+  "\01\7e\00\60\00\01\7e\03\8d\80\80\80\00\0c\03\03"
+  "\02\03\02\00\02\00\03\02\05\03\0d\87\80\80\80\00"
+  "\03\00\01\00\00\00\04\07\a8\81\80\80\00\0d\08\74"
+  "\68\72\6f\77\2d\69\66\00\00\0d\63\61\74\63\68\2d"
+  "\70\61\79\6c\6f\61\64\00\01\09\63\61\74\63\68\2d"
+  "\61\6c\6c\00\02\07\72\65\74\68\72\6f\77\00\03\09"
+  "\63\61\74\63\68\2d\72\65\66\00\04\08\75\6e\63\61"
+  "\75\67\68\74\00\05\08\6d\69\73\6d\61\74\63\68\00"
+  "\06\0e\74\68\72\6f\77\2d\72\65\66\2d\6e\75\6c\6c"
+  "\00\07\0f\75\6e\77\69\6e\64\2d\6f\70\65\72\61\6e"
+  "\64\73\00\08\0a\72\65\74\72\79\2d\6c\6f\6f\70\00"
+  "\09\09\63\61\74\63\68\2d\69\36\34\00\0a\11\63\61"
+  "\74\63\68\2d\6f\75\74\65\72\2d\6c\61\62\65\6c\00"
+  "\0b\01\65\04\00\0a\b8\82\80\80\00\0c\8d\80\80\80"
+  "\00\00\20\00\04\40\41\07\08\00\0b\41\00\0b\95\80"
+  "\80\80\00\00\02\7f\1f\40\01\00\00\00\20\00\10\00"
+  "\1a\0b\41\e4\00\0f\0b\0b\92\80\80\80\00\00\02\40"
+  "\1f\40\01\02\00\08\01\0b\41\00\0f\0b\41\01\0b\a2"
+  "\80\80\80\00\01\01\69\02\7f\1f\40\01\00\00\00\02"
+  "\69\1f\40\01\03\00\20\00\08\00\0b\00\0b\21\01\20"
+  "\01\0a\0b\00\0b\0b\92\80\80\80\00\00\02\69\1f\40"
+  "\01\01\01\00\08\01\0b\00\0b\1a\41\01\0b\86\80\80"
+  "\80\00\00\41\03\08\00\0b\92\80\80\80\00\00\02\40"
+  "\1f\40\01\00\01\00\41\01\08\00\0b\0b\41\00\0b\87"
+  "\80\80\80\00\01\01\69\20\00\0a\0b\9e\80\80\80\00"
+  "\00\41\e8\07\02\7f\1f\40\01\00\00\00\41\05\41\06"
+  "\20\00\10\00\1a\1a\1a\0b\41\e4\00\0b\6a\0b\a7\80"
+  "\80\80\00\01\01\7f\03\40\02\40\1f\40\01\02\00\20"
+  "\00\41\01\6a\21\00\20\00\41\03\49\04\40\08\01\0b"
+  "\0b\20\00\0f\0b\0c\00\0b\00\0b\97\80\80\80\00\00"
+  "\02\7e\1f\40\01\00\02\00\42\89\cf\95\9a\12\08\02"
+  "\0b\42\00\0f\0b\0b\98\80\80\80\00\00\02\7f\02\40"
+  "\1f\7f\01\00\00\01\20\00\10\00\0b\0f\0b\00\0b\41"
+  "\01\6a\0b"
+)
+(register "M" $M)
+(assert_return (invoke "catch-payload" (i32.const 0)) (i32.const 100))
+(assert_return (invoke "catch-payload" (i32.const 1)) (i32.const 7))
+(assert_return (invoke "catch-all") (i32.const 1))
+(assert_return (invoke "rethrow" (i32.const 42)) (i32.const 42))
+(assert_return (invoke "catch-ref") (i32.const 1))
+(assert_exception (invoke "uncaught"))
+(assert_exception (invoke "mismatch"))
+(assert_trap (invoke "throw-ref-null") "null exception reference")
+(assert_return (invoke "unwind-operands" (i32.const 0)) (i32.const 1100))
+(assert_return (invoke "unwind-operands" (i32.const 1)) (i32.const 1007))
+(assert_return (invoke "retry-loop") (i32.const 3))
+(assert_return (invoke "catch-i64") (i64.const 4886718345))
+(assert_return (invoke "catch-outer-label" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "catch-outer-label" (i32.const 1)) (i32.const 8))
+
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\8c\80\80\80\00\03\60"
+  "\00\00\60\01\7f\00\60\00\01\7f\02\95\80\80\80\00"
+  "\02\01\4d\01\65\04\00\01\01\4d\08\75\6e\63\61\75"
+  "\67\68\74\00\00\03\83\80\80\80\00\02\02\02\0d\83"
+  "\80\80\80\00\01\00\01\07\a0\80\80\80\00\02\0e\63"
+  "\61\74\63\68\2d\69\6d\70\6f\72\74\65\64\00\01\0b"
+  "\63\61\74\63\68\2d\6c\6f\63\61\6c\00\02\0a\ad\80"
+  "\80\80\00\02\91\80\80\80\00\00\02\7f\1f\40\01\00"
+  "\00\00\10\00\0b\41\00\0f\0b\0b\91\80\80\80\00\00"
+  "\02\7f\1f\40\01\00\01\00\10\00\0b\41\00\0f\0b\0b"
+)
+(assert_return (invoke "catch-imported") (i32.const 3))
+(assert_exception (invoke "catch-local"))
+
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\8c\80\80\80\00\03\60"
+    "\00\00\60\01\7f\00\60\00\01\7f\03\82\80\80\80\00"
+    "\01\00\0a\8a\80\80\80\00\01\84\80\80\80\00\00\08"
+    "\00\0b"
+  )
+  "unknown tag"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\8c\80\80\80\00\03\60"
+    "\00\00\60\01\7f\00\60\00\01\7f\03\82\80\80\80\00"
+    "\01\00\0d\83\80\80\80\00\01\00\02\0a\88\80\80\80"
+    "\00\01\82\80\80\80\00\00\0b"
+  )
+  "non-empty tag result type"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\8c\80\80\80\00\03\60"
+    "\00\00\60\01\7f\00\60\00\01\7f\03\82\80\80\80\00"
+    "\01\00\0d\83\80\80\80\00\01\00\01\0a\92\80\80\80"
+    "\00\01\8c\80\80\80\00\00\02\40\1f\40\01\00\00\00"
+    "\0b\0b\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\8c\80\80\80\00\03\60"
+    "\00\00\60\01\7f\00\60\00\01\7f\03\82\80\80\80\00"
+    "\01\00\0a\91\80\80\80\00\01\8b\80\80\80\00\00\02"
+    "\40\1f\40\01\03\00\0b\0b\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\8c\80\80\80\00\03\60"
+    "\00\00\60\01\7f\00\60\00\01\7f\03\82\80\80\80\00"
+    "\01\00\0a\8e\80\80\80\00\01\88\80\80\80\00\00\1f"
+    "\40\01\02\01\0b\0b"
+  )
+  "unknown label"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\8c\80\80\80\00\03\60"
+    "\00\00\60\01\7f\00\60\00\01\7f\03\82\80\80\80\00"
+    "\01\00\0a\8b\80\80\80\00\01\85\80\80\80\00\00\41"
+    "\00\0a\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\8c\80\80\80\00\03\60"
+    "\00\00\60\01\7f\00\60\00\01\7f\03\82\80\80\80\00"
+    "\01\00\0d\83\80\80\80\00\01\00\01\0a\8a\80\80\80"
+    "\00\01\84\80\80\80\00\00\08\00\0b"
+  )
+  "type mismatch"
+)
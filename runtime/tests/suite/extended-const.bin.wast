@@ -0,0 +1,61 @@
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\8e\80\80\80\00\03\60"
+  "\00\01\7f\60\00\01\7e\60\01\7f\01\7f\02\98\80\80"
+  "\80\00\01\08\73\70\65\63\74\65\73\74\0a\67\6c\6f"
+  "\62\61\6c\5f\69\33\32\03\7f\00\03\87\80\80\80\00"
+  "\06\00\01\00\00\02\02\04\84\80\80\80\00\01\70\00"
+  "\04\05\83\80\80\80\00\01\00\01\06\a8\80\80\80\00"
+  "\03\7f\00\23\00\41\04\41\08\6c\6a\0b\7e\00\42\80"
+  "\80\80\80\80\80\80\80\c0\00\42\04\7e\42\03\7d\0b"
+  "\7f\00\41\00\41\01\6b\0b\07\9e\80\80\80\00\05\02"
+  "\67\31\00\00\02\67\32\00\01\02\67\33\00\02\04\6c"
+  "\6f\61\64\00\04\04\63\61\6c\6c\00\05\09\8b\80\80"
+  "\80\00\01\00\23\00\41\99\05\6b\0b\01\03\0a\bd\80"
+  "\80\80\00\06\84\80\80\80\00\00\23\01\0b\84\80\80"
+  "\80\00\00\23\02\0b\84\80\80\80\00\00\23\03\0b\84"
+  "\80\80\80\00\00\41\2a\0b\87\80\80\80\00\00\20\00"
+  "\2d\00\00\0b\87\80\80\80\00\00\20\00\11\00\00\0b"
+  "\0b\8c\80\80\80\00\01\00\41\e4\00\23\00\6a\0b\02"
+  "\07\09"
+)
+(assert_return (invoke "g1") (i32.const 698))
+(assert_return (invoke "g2") (i64.const -3))
+(assert_return (invoke "g3") (i32.const -1))
+(assert_return (invoke "load" (i32.const 766)) (i32.const 7))
+(assert_return (invoke "load" (i32.const 767)) (i32.const 9))
+(assert_return (invoke "call" (i32.const 1)) (i32.const 42))
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\06\89\80\80\80\00\01\7f"
+    "\00\41\01\41\02\6d\0b"
+  )
+  "constant expression required"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\06\89\80\80\80\00\01\7f"
+    "\00\41\01\42\02\6a\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\06\89\80\80\80\00\01\7e"
+    "\00\41\01\41\02\6a\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\06\87\80\80\80\00\01\7f"
+    "\00\41\01\6a\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\06\8b\80\80\80\00\01\7f"
+    "\00\41\01\41\02\41\03\6a\0b"
+  )
+  "type mismatch"
+)
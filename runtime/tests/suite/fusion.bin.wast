@@ -0,0 +1,48 @@
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\91\80\80\80\00\03\60"
+  "\01\7f\01\7f\60\01\7f\01\7e\60\02\7f\7f\01\7f\03"
+  "\8b\80\80\80\00\0a\00\00\00\01\00\02\02\02\00\00"
+  "\05\83\80\80\80\00\01\00\01\07\ec\80\80\80\00\0a"
+  "\04\6c\6f\61\64\00\00\0b\6c\6f\61\64\5f\6f\66\66"
+  "\73\65\74\00\01\07\6c\6f\61\64\38\5f\73\00\02\08"
+  "\6c\6f\61\64\5f\69\36\34\00\03\04\61\64\64\72\00"
+  "\04\03\61\64\64\00\05\07\73\75\62\5f\73\65\74\00"
+  "\06\07\64\69\76\5f\73\65\74\00\07\11\73\71\75\61"
+  "\72\65\5f\61\66\74\65\72\5f\76\31\32\38\00\08\09"
+  "\73\75\6d\5f\62\79\74\65\73\00\09\0a\b1\81\80\80"
+  "\00\0a\8a\80\80\80\00\00\20\00\41\04\6a\28\02\00"
+  "\0b\8a\80\80\80\00\00\20\00\41\04\6a\28\02\08\0b"
+  "\8a\80\80\80\00\00\20\00\41\04\6a\2c\00\00\0b\8a"
+  "\80\80\80\00\00\20\00\41\04\6a\29\03\00\0b\87\80"
+  "\80\80\00\00\20\00\41\10\6a\0b\87\80\80\80\00\00"
+  "\20\00\20\01\6a\0b\8d\80\80\80\00\01\01\7f\20\00"
+  "\20\01\6b\21\02\20\02\0b\8d\80\80\80\00\01\01\7f"
+  "\20\00\20\01\6d\21\02\20\02\0b\8f\80\80\80\00\02"
+  "\01\7b\01\7f\20\00\20\00\6c\21\02\20\02\0b\9f\80"
+  "\80\80\00\01\01\7f\03\40\20\01\20\00\41\7f\6a\2d"
+  "\00\00\6a\21\01\20\00\41\7f\6a\22\00\0d\00\0b\20"
+  "\01\0b\0b\af\80\80\80\00\01\00\41\00\0b\29\01\02"
+  "\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\11\12"
+  "\13\14\15\16\17\18\19\1a\1b\1c\1d\1e\1f\20\00\00"
+  "\00\00\00\00\00\00\ff"
+)
+(assert_return (invoke "load" (i32.const 0)) (i32.const 134678021))
+(assert_return (invoke "load" (i32.const -4)) (i32.const 67305985))
+(assert_trap (invoke "load" (i32.const 65533)) "out of bounds memory access")
+(assert_return (invoke "load_offset" (i32.const 0)) (i32.const 269422093))
+(assert_return (invoke "load_offset" (i32.const -4)) (i32.const 202050057))
+(assert_trap (invoke "load_offset" (i32.const 65524)) "out of bounds memory access")
+(assert_return (invoke "load8_s" (i32.const 36)) (i32.const -1))
+(assert_return (invoke "load8_s" (i32.const 0)) (i32.const 5))
+(assert_return (invoke "load_i64" (i32.const 0)) (i64.const 867798387104613893))
+(assert_return (invoke "addr" (i32.const 1)) (i32.const 17))
+(assert_return (invoke "addr" (i32.const -1)) (i32.const 15))
+(assert_return (invoke "add" (i32.const 2) (i32.const 3)) (i32.const 5))
+(assert_return (invoke "add" (i32.const -1) (i32.const 1)) (i32.const 0))
+(assert_return (invoke "sub_set" (i32.const 2) (i32.const 3)) (i32.const -1))
+(assert_return (invoke "div_set" (i32.const 7) (i32.const 2)) (i32.const 3))
+(assert_trap (invoke "div_set" (i32.const 7) (i32.const 0)) "integer divide by zero")
+(assert_trap (invoke "div_set" (i32.const -2147483648) (i32.const -1)) "integer overflow")
+(assert_return (invoke "square_after_v128" (i32.const 12)) (i32.const 144))
+(assert_return (invoke "sum_bytes" (i32.const 4)) (i32.const 10))
+(assert_return (invoke "sum_bytes" (i32.const 32)) (i32.const 528))
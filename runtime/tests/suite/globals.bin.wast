@@ -48,36 +48,6 @@
   )
   "global is immutable"
 )
-(assert_invalid
-  (module binary
-    "\00\61\73\6d\01\00\00\00\02\88\80\80\80\00\01\01"
-    "\6d\01\61\03\7f\01"
-  )
-  "mutable globals cannot be imported"
-)
-(assert_invalid
-  (module binary
-    "\00\61\73\6d\01\00\00\00\02\88\80\80\80\00\01\01"
-    "\6d\01\61\03\7f\01"
-  )
-  "mutable globals cannot be imported"
-)
-(assert_invalid
-  (module binary
-    "\00\61\73\6d\01\00\00\00\06\89\80\80\80\00\01\7d"
-    "\01\43\00\00\00\00\0b\07\85\80\80\80\00\01\01\61"
-    "\03\00"
-  )
-  "mutable globals cannot be exported"
-)
-(assert_invalid
-  (module binary
-    "\00\61\73\6d\01\00\00\00\06\89\80\80\80\00\01\7d"
-    "\01\43\00\00\00\00\0b\07\85\80\80\80\00\01\01\61"
-    "\03\00"
-  )
-  "mutable globals cannot be exported"
-)
 (assert_invalid
   (module binary
     "\00\61\73\6d\01\00\00\00\06\8a\80\80\80\00\01\7d"
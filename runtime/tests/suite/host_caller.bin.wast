@@ -0,0 +1,44 @@
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\85\80\80\80\00\01\60"
+  "\00\01\7f\02\ac\80\80\80\00\02\08\73\70\65\63\74"
+  "\65\73\74\0b\63\6f\75\6e\74\65\72\5f\69\6e\63\00"
+  "\00\08\73\70\65\63\74\65\73\74\08\64\61\74\61\5f"
+  "\69\6e\63\00\00\03\83\80\80\80\00\02\00\00\06\86"
+  "\80\80\80\00\01\7f\01\41\0a\0b\07\98\80\80\80\00"
+  "\03\03\69\6e\63\00\02\04\64\61\74\61\00\03\07\63"
+  "\6f\75\6e\74\65\72\03\00\0a\93\80\80\80\00\02\84"
+  "\80\80\80\00\00\10\00\0b\84\80\80\80\00\00\10\01"
+  "\0b"
+)
+(assert_return (invoke "inc") (i32.const 11))
+(assert_return (invoke "inc") (i32.const 12))
+(assert_return (get "counter") (i32.const 12))
+(assert_return (invoke "data") (i32.const 1))
+(assert_return (invoke "data") (i32.const 2))
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\85\80\80\80\00\01\60"
+  "\00\01\7f\02\ac\80\80\80\00\02\08\73\70\65\63\74"
+  "\65\73\74\0b\63\6f\75\6e\74\65\72\5f\69\6e\63\00"
+  "\00\08\73\70\65\63\74\65\73\74\08\64\61\74\61\5f"
+  "\69\6e\63\00\00\03\83\80\80\80\00\02\00\00\06\86"
+  "\80\80\80\00\01\7f\00\41\0a\0b\07\98\80\80\80\00"
+  "\03\03\69\6e\63\00\02\04\64\61\74\61\00\03\07\63"
+  "\6f\75\6e\74\65\72\03\00\0a\93\80\80\80\00\02\84"
+  "\80\80\80\00\00\10\00\0b\84\80\80\80\00\00\10\01"
+  "\0b"
+)
+(assert_trap (invoke "inc") "immutable counter")
+(assert_return (get "counter") (i32.const 10))
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\85\80\80\80\00\01\60"
+  "\00\01\7f\02\ac\80\80\80\00\02\08\73\70\65\63\74"
+  "\65\73\74\0b\63\6f\75\6e\74\65\72\5f\69\6e\63\00"
+  "\00\08\73\70\65\63\74\65\73\74\08\64\61\74\61\5f"
+  "\69\6e\63\00\00\03\83\80\80\80\00\02\00\00\06\86"
+  "\80\80\80\00\01\7f\01\41\0a\0b\07\8e\80\80\80\00"
+  "\02\03\69\6e\63\00\02\04\64\61\74\61\00\03\0a\93"
+  "\80\80\80\00\02\84\80\80\80\00\00\10\00\0b\84\80"
+  "\80\80\00\00\10\01\0b"
+)
+(assert_trap (invoke "inc") "no i32 counter")
+(assert_return (invoke "data") (i32.const 3))
@@ -0,0 +1,29 @@
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\8d\80\80\80\00\02\60"
+  "\02\7f\7f\01\7f\60\03\7f\7f\7f\00\02\ac\80\80\80"
+  "\00\02\08\73\70\65\63\74\65\73\74\09\73\75\6d\5f"
+  "\62\79\74\65\73\00\00\08\73\70\65\63\74\65\73\74"
+  "\0a\66\69\6c\6c\5f\62\79\74\65\73\00\01\03\83\80"
+  "\80\80\00\02\00\01\05\83\80\80\80\00\01\00\01\07"
+  "\8e\80\80\80\00\02\03\73\75\6d\00\02\04\66\69\6c"
+  "\6c\00\03\0a\9d\80\80\80\00\02\88\80\80\80\00\00"
+  "\20\00\20\01\10\00\0b\8a\80\80\80\00\00\20\00\20"
+  "\01\20\02\10\01\0b\0b\8a\80\80\80\00\01\00\41\10"
+  "\0b\04\01\02\03\04"
+)
+(assert_return (invoke "sum" (i32.const 16) (i32.const 4)) (i32.const 10))
+(assert_return (invoke "sum" (i32.const 17) (i32.const 2)) (i32.const 5))
+(assert_return (invoke "sum" (i32.const 0) (i32.const 0)) (i32.const 0))
+(invoke "fill" (i32.const 100) (i32.const 8) (i32.const 255))
+(assert_return (invoke "sum" (i32.const 96) (i32.const 16)) (i32.const 2040))
+(assert_trap (invoke "sum" (i32.const 65535) (i32.const 2)) "out of bounds memory access")
+(assert_trap (invoke "fill" (i32.const 65536) (i32.const 1) (i32.const 0)) "out of bounds memory access")
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\87\80\80\80\00\01\60"
+  "\02\7f\7f\01\7f\02\96\80\80\80\00\01\08\73\70\65"
+  "\63\74\65\73\74\09\73\75\6d\5f\62\79\74\65\73\00"
+  "\00\03\82\80\80\80\00\01\00\07\87\80\80\80\00\01"
+  "\03\73\75\6d\00\01\0a\8e\80\80\80\00\01\88\80\80"
+  "\80\00\00\20\00\20\01\10\00\0b"
+)
+(assert_trap (invoke "sum" (i32.const 0) (i32.const 0)) "no memory")
@@ -0,0 +1,25 @@
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\85\80\80\80\00\01\60"
+  "\00\01\7f\03\84\80\80\80\00\03\00\00\00\07\97\80"
+  "\80\80\00\03\02\6f\6b\00\00\03\62\61\64\00\01\08"
+  "\63\61\6c\6c\5f\62\61\64\00\02\0a\9f\80\80\80\00"
+  "\03\84\80\80\80\00\00\41\2a\0b\87\80\80\80\00\00"
+  "\41\01\42\02\6a\0b\84\80\80\80\00\00\10\01\0b"
+)
+(assert_return (invoke "ok") (i32.const 42))
+(assert_return (invoke "ok") (i32.const 42))
+(assert_trap (invoke "bad") "invalid function")
+(assert_trap (invoke "bad") "invalid function")
+(assert_trap (invoke "call_bad") "invalid function")
+(assert_return (invoke "ok") (i32.const 42))
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\85\80\80\80\00\01\60"
+    "\00\01\7f\03\84\80\80\80\00\03\00\00\00\07\97\80"
+    "\80\80\00\03\02\6f\6b\00\00\03\62\61\64\00\01\08"
+    "\63\61\6c\6c\5f\62\61\64\00\02\0a\9f\80\80\80\00"
+    "\03\84\80\80\80\00\00\41\2a\0b\87\80\80\80\00\00"
+    "\41\01\42\02\6a\0b\84\80\80\80\00\00\10\01\0b"
+  )
+  "type mismatch"
+)
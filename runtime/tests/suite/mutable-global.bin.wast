@@ -0,0 +1,49 @@
+(module $M binary
+  "\00\61\73\6d\01\00\00\00\01\89\80\80\80\00\02\60"
+  "\00\01\7f\60\01\7f\00\03\83\80\80\80\00\02\00\01"
+  "\06\86\80\80\80\00\01\7f\01\41\2a\0b\07\91\80\80"
+  "\80\00\03\01\67\03\00\03\67\65\74\00\00\03\73\65"
+  "\74\00\01\0a\95\80\80\80\00\02\84\80\80\80\00\00"
+  "\23\00\0b\86\80\80\80\00\00\20\00\24\00\0b"
+)
+(register "M" $M)
+(module $N binary
+  "\00\61\73\6d\01\00\00\00\01\89\80\80\80\00\02\60"
+  "\00\01\7f\60\01\7f\00\02\88\80\80\80\00\01\01\4d"
+  "\01\67\03\7f\01\03\83\80\80\80\00\02\00\01\07\91"
+  "\80\80\80\00\03\01\67\03\00\03\67\65\74\00\00\03"
+  "\73\65\74\00\01\0a\95\80\80\80\00\02\84\80\80\80"
+  "\00\00\23\00\0b\86\80\80\80\00\00\20\00\24\00\0b"
+)
+(assert_return (invoke $M "get") (i32.const 42))
+(assert_return (get $M "g") (i32.const 42))
+(assert_return (invoke $N "get") (i32.const 42))
+(invoke $N "set" (i32.const 7))
+(assert_return (invoke $M "get") (i32.const 7))
+(assert_return (get $M "g") (i32.const 7))
+(invoke $M "set" (i32.const 9))
+(assert_return (invoke $N "get") (i32.const 9))
+(assert_return (get $N "g") (i32.const 9))
+(assert_unlinkable
+  (module binary
+    "\00\61\73\6d\01\00\00\00\02\88\80\80\80\00\01\01"
+    "\4d\01\67\03\7f\00"
+  )
+  "incompatible import type"
+)
+(assert_unlinkable
+  (module binary
+    "\00\61\73\6d\01\00\00\00\02\98\80\80\80\00\01\08"
+    "\73\70\65\63\74\65\73\74\0a\67\6c\6f\62\61\6c\5f"
+    "\69\33\32\03\7f\01"
+  )
+  "incompatible import type"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\02\88\80\80\80\00\01\01"
+    "\4d\01\67\03\7f\01\06\86\80\80\80\00\01\7f\00\23"
+    "\00\0b"
+  )
+  "constant expression required"
+)
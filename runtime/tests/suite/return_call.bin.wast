@@ -0,0 +1,101 @@
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\95\80\80\80\00\04\60"
+  "\02\7e\7e\01\7e\60\01\7e\01\7e\60\01\7e\01\7f\60"
+  "\00\01\7f\03\8a\80\80\80\00\09\00\01\02\02\01\03"
+  "\03\03\03\04\85\80\80\80\00\01\70\01\02\02\07\ee"
+  "\80\80\80\00\08\07\66\61\63\2d\61\63\63\00\00\05"
+  "\63\6f\75\6e\74\00\01\04\65\76\65\6e\00\02\03\6f"
+  "\64\64\00\03\0e\63\6f\75\6e\74\2d\69\6e\64\69\72"
+  "\65\63\74\00\04\16\74\79\70\65\2d\6d\69\73\6d\61"
+  "\74\63\68\2d\69\6e\64\69\72\65\63\74\00\05\08\74"
+  "\79\70\65\2d\69\33\32\00\06\16\75\6e\69\6e\69\74"
+  "\69\61\6c\69\7a\65\64\2d\69\6e\64\69\72\65\63\74"
+  "\00\08\09\87\80\80\80\00\01\00\41\00\0b\01\04\0a"
+  "\aa\81\80\80\00\09\97\80\80\80\00\00\20\00\50\04"
+  "\7e\20\01\05\20\00\42\01\7d\20\00\20\01\7e\12\00"
+  "\0b\0b\92\80\80\80\00\00\20\00\50\04\7e\20\00\05"
+  "\20\00\42\01\7d\12\01\0b\0b\92\80\80\80\00\00\20"
+  "\00\50\04\7f\41\2c\05\20\00\42\01\7d\12\03\0b\0b"
+  "\93\80\80\80\00\00\20\00\50\04\7f\41\e3\00\05\20"
+  "\00\42\01\7d\12\02\0b\0b\95\80\80\80\00\00\20\00"
+  "\50\04\7e\20\00\05\20\00\42\01\7d\41\00\13\01\00"
+  "\0b\0b\89\80\80\80\00\00\42\00\41\00\13\02\00\0b"
+  "\84\80\80\80\00\00\12\07\0b\85\80\80\80\00\00\41"
+  "\b2\02\0b\87\80\80\80\00\00\41\01\13\03\00\0b"
+)
+(assert_return (invoke "fac-acc" (i64.const 0) (i64.const 1)) (i64.const 1))
+(assert_return (invoke "fac-acc" (i64.const 1) (i64.const 1)) (i64.const 1))
+(assert_return (invoke "fac-acc" (i64.const 5) (i64.const 1)) (i64.const 120))
+(assert_return (invoke "fac-acc" (i64.const 25) (i64.const 1)) (i64.const 7034535277573963776))
+(assert_return (invoke "count" (i64.const 0)) (i64.const 0))
+(assert_return (invoke "count" (i64.const 1000)) (i64.const 0))
+(assert_return (invoke "count" (i64.const 100000)) (i64.const 0))
+(assert_return (invoke "even" (i64.const 0)) (i32.const 44))
+(assert_return (invoke "even" (i64.const 1)) (i32.const 99))
+(assert_return (invoke "even" (i64.const 100)) (i32.const 44))
+(assert_return (invoke "even" (i64.const 77)) (i32.const 99))
+(assert_return (invoke "even" (i64.const 100000)) (i32.const 44))
+(assert_return (invoke "even" (i64.const 100001)) (i32.const 99))
+(assert_return (invoke "odd" (i64.const 0)) (i32.const 99))
+(assert_return (invoke "odd" (i64.const 1)) (i32.const 44))
+(assert_return (invoke "odd" (i64.const 200)) (i32.const 99))
+(assert_return (invoke "odd" (i64.const 77)) (i32.const 44))
+(assert_return (invoke "odd" (i64.const 100000)) (i32.const 99))
+(assert_return (invoke "odd" (i64.const 99999)) (i32.const 44))
+(assert_return (invoke "count-indirect" (i64.const 0)) (i64.const 0))
+(assert_return (invoke "count-indirect" (i64.const 100000)) (i64.const 0))
+(assert_trap (invoke "type-mismatch-indirect") "indirect call type mismatch")
+(assert_trap (invoke "uninitialized-indirect") "uninitialized element")
+(assert_return (invoke "type-i32") (i32.const 306))
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\88\80\80\80\00\02\60"
+    "\00\00\60\00\01\7f\03\83\80\80\80\00\02\00\01\0a"
+    "\93\80\80\80\00\02\84\80\80\80\00\00\12\01\0b\84"
+    "\80\80\80\00\00\41\00\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\8a\80\80\80\00\02\60"
+    "\00\01\7f\60\01\7f\01\7f\03\83\80\80\80\00\02\00"
+    "\01\0a\93\80\80\80\00\02\84\80\80\80\00\00\12\01"
+    "\0b\84\80\80\80\00\00\20\00\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\89\80\80\80\00\02\60"
+    "\00\01\7f\60\00\01\7e\03\83\80\80\80\00\02\00\01"
+    "\0a\93\80\80\80\00\02\84\80\80\80\00\00\12\01\0b"
+    "\84\80\80\80\00\00\42\00\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\84\80\80\80\00\01\60"
+    "\00\00\03\82\80\80\80\00\01\00\0a\8a\80\80\80\00"
+    "\01\84\80\80\80\00\00\12\01\0b"
+  )
+  "unknown function"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\84\80\80\80\00\01\60"
+    "\00\00\03\82\80\80\80\00\01\00\0a\8d\80\80\80\00"
+    "\01\87\80\80\80\00\00\41\00\13\00\00\0b"
+  )
+  "unknown table"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\88\80\80\80\00\02\60"
+    "\00\00\60\00\01\7f\03\82\80\80\80\00\01\00\04\84"
+    "\80\80\80\00\01\70\00\00\0a\8d\80\80\80\00\01\87"
+    "\80\80\80\00\00\41\00\13\01\00\0b"
+  )
+  "type mismatch"
+)
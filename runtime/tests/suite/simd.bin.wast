@@ -0,0 +1,365 @@
+(module binary
+  "\00\61\73\6d\01\00\00\00\01\c3\80\80\80\00\0c\60"
+  "\02\7b\7b\01\7b\60\01\7b\01\7b\60\02\7b\7f\01\7b"
+  "\60\01\7b\01\7f\60\03\7b\7b\7b\01\7b\60\01\7b\01"
+  "\7e\60\02\7b\7c\01\7b\60\01\7f\01\7b\60\01\7d\01"
+  "\7b\60\00\01\7b\60\02\7f\7b\01\7b\60\02\7f\7b\01"
+  "\7e\03\c7\80\80\80\00\46\00\00\00\00\00\00\00\00"
+  "\00\00\00\00\00\00\00\00\00\00\01\01\01\01\01\01"
+  "\01\01\02\02\02\03\03\03\03\03\04\00\03\03\03\05"
+  "\02\06\07\08\00\00\00\00\00\00\00\01\01\01\01\01"
+  "\01\01\01\09\09\09\07\07\07\07\07\0a\0b\0a\05\84"
+  "\80\80\80\00\01\01\01\01\06\96\80\80\80\00\01\7b"
+  "\01\fd\0c\10\00\81\80\7f\01\80\ff\03\fe\02\01\ff"
+  "\7f\00\80\0b\07\d6\89\80\80\00\46\0f\69\38\78\31"
+  "\36\2e\61\64\64\5f\73\61\74\5f\73\00\00\0f\69\38"
+  "\78\31\36\2e\73\75\62\5f\73\61\74\5f\75\00\01\09"
+  "\69\31\36\78\38\2e\6d\75\6c\00\02\09\69\36\34\78"
+  "\32\2e\6d\75\6c\00\03\0b\69\33\32\78\34\2e\6d\69"
+  "\6e\5f\73\00\04\0b\69\31\36\78\38\2e\6d\61\78\5f"
+  "\75\00\05\0c\69\38\78\31\36\2e\61\76\67\72\5f\75"
+  "\00\06\13\69\31\36\78\38\2e\71\31\35\6d\75\6c\72"
+  "\5f\73\61\74\5f\73\00\07\11\69\33\32\78\34\2e\64"
+  "\6f\74\5f\69\31\36\78\38\5f\73\00\08\14\69\38\78"
+  "\31\36\2e\6e\61\72\72\6f\77\5f\69\31\36\78\38\5f"
+  "\75\00\09\14\69\31\36\78\38\2e\6e\61\72\72\6f\77"
+  "\5f\69\33\32\78\34\5f\73\00\0a\19\69\31\36\78\38"
+  "\2e\65\78\74\6d\75\6c\5f\68\69\67\68\5f\69\38\78"
+  "\31\36\5f\73\00\0b\18\69\36\34\78\32\2e\65\78\74"
+  "\6d\75\6c\5f\6c\6f\77\5f\69\33\32\78\34\5f\75\00"
+  "\0c\0a\69\38\78\31\36\2e\6c\74\5f\73\00\0d\0a\69"
+  "\31\36\78\38\2e\67\65\5f\75\00\0e\0a\69\36\34\78"
+  "\32\2e\67\74\5f\73\00\0f\0b\76\31\32\38\2e\61\6e"
+  "\64\6e\6f\74\00\10\0d\69\38\78\31\36\2e\73\77\69"
+  "\7a\7a\6c\65\00\11\0c\69\38\78\31\36\2e\70\6f\70"
+  "\63\6e\74\00\12\09\69\31\36\78\38\2e\61\62\73\00"
+  "\13\09\69\33\32\78\34\2e\6e\65\67\00\14\08\76\31"
+  "\32\38\2e\6e\6f\74\00\15\18\69\33\32\78\34\2e\65"
+  "\78\74\65\6e\64\5f\6c\6f\77\5f\69\31\36\78\38\5f"
+  "\75\00\16\19\69\36\34\78\32\2e\65\78\74\65\6e\64"
+  "\5f\68\69\67\68\5f\69\33\32\78\34\5f\73\00\17\1d"
+  "\69\33\32\78\34\2e\65\78\74\61\64\64\5f\70\61\69"
+  "\72\77\69\73\65\5f\69\31\36\78\38\5f\75\00\18\1d"
+  "\69\31\36\78\38\2e\65\78\74\61\64\64\5f\70\61\69"
+  "\72\77\69\73\65\5f\69\38\78\31\36\5f\73\00\19\09"
+  "\69\38\78\31\36\2e\73\68\6c\00\1a\0b\69\31\36\78"
+  "\38\2e\73\68\72\5f\73\00\1b\0b\69\36\34\78\32\2e"
+  "\73\68\72\5f\75\00\1c\0d\76\31\32\38\2e\61\6e\79"
+  "\5f\74\72\75\65\00\1d\0e\69\38\78\31\36\2e\61\6c"
+  "\6c\5f\74\72\75\65\00\1e\0e\69\33\32\78\34\2e\61"
+  "\6c\6c\5f\74\72\75\65\00\1f\0d\69\38\78\31\36\2e"
+  "\62\69\74\6d\61\73\6b\00\20\0d\69\36\34\78\32\2e"
+  "\62\69\74\6d\61\73\6b\00\21\0e\76\31\32\38\2e\62"
+  "\69\74\73\65\6c\65\63\74\00\22\0d\69\38\78\31\36"
+  "\2e\73\68\75\66\66\6c\65\00\23\14\69\38\78\31\36"
+  "\2e\65\78\74\72\61\63\74\5f\6c\61\6e\65\5f\73\00"
+  "\24\14\69\38\78\31\36\2e\65\78\74\72\61\63\74\5f"
+  "\6c\61\6e\65\5f\75\00\25\14\69\31\36\78\38\2e\65"
+  "\78\74\72\61\63\74\5f\6c\61\6e\65\5f\73\00\26\12"
+  "\69\36\34\78\32\2e\65\78\74\72\61\63\74\5f\6c\61"
+  "\6e\65\00\27\12\69\31\36\78\38\2e\72\65\70\6c\61"
+  "\63\65\5f\6c\61\6e\65\00\28\12\66\36\34\78\32\2e"
+  "\72\65\70\6c\61\63\65\5f\6c\61\6e\65\00\29\0b\69"
+  "\38\78\31\36\2e\73\70\6c\61\74\00\2a\0b\66\33\32"
+  "\78\34\2e\73\70\6c\61\74\00\2b\09\66\33\32\78\34"
+  "\2e\61\64\64\00\2c\09\66\33\32\78\34\2e\6d\75\6c"
+  "\00\2d\09\66\33\32\78\34\2e\6d\69\6e\00\2e\0a\66"
+  "\33\32\78\34\2e\70\6d\69\6e\00\2f\0a\66\33\32\78"
+  "\34\2e\70\6d\61\78\00\30\08\66\33\32\78\34\2e\6c"
+  "\74\00\31\09\66\36\34\78\32\2e\73\75\62\00\32\0d"
+  "\66\33\32\78\34\2e\6e\65\61\72\65\73\74\00\33\0a"
+  "\66\36\34\78\32\2e\73\71\72\74\00\34\15\66\33\32"
+  "\78\34\2e\63\6f\6e\76\65\72\74\5f\69\33\32\78\34"
+  "\5f\75\00\35\17\69\33\32\78\34\2e\74\72\75\6e\63"
+  "\5f\73\61\74\5f\66\33\32\78\34\5f\73\00\36\1c\69"
+  "\33\32\78\34\2e\74\72\75\6e\63\5f\73\61\74\5f\66"
+  "\36\34\78\32\5f\75\5f\7a\65\72\6f\00\37\19\66\36"
+  "\34\78\32\2e\63\6f\6e\76\65\72\74\5f\6c\6f\77\5f"
+  "\69\33\32\78\34\5f\73\00\38\17\66\33\32\78\34\2e"
+  "\64\65\6d\6f\74\65\5f\66\36\34\78\32\5f\7a\65\72"
+  "\6f\00\39\17\66\36\34\78\32\2e\70\72\6f\6d\6f\74"
+  "\65\5f\6c\6f\77\5f\66\33\32\78\34\00\3a\05\63\6f"
+  "\6e\73\74\00\3b\05\6c\6f\63\61\6c\00\3c\06\67\6c"
+  "\6f\62\61\6c\00\3d\09\76\31\32\38\2e\6c\6f\61\64"
+  "\00\3e\0e\76\31\32\38\2e\6c\6f\61\64\38\78\38\5f"
+  "\73\00\3f\0f\76\31\32\38\2e\6c\6f\61\64\33\32\78"
+  "\32\5f\75\00\40\11\76\31\32\38\2e\6c\6f\61\64\31"
+  "\36\5f\73\70\6c\61\74\00\41\10\76\31\32\38\2e\6c"
+  "\6f\61\64\33\32\5f\7a\65\72\6f\00\42\10\76\31\32"
+  "\38\2e\6c\6f\61\64\31\36\5f\6c\61\6e\65\00\43\11"
+  "\76\31\32\38\2e\73\74\6f\72\65\36\34\5f\6c\61\6e"
+  "\65\00\44\0a\76\31\32\38\2e\73\74\6f\72\65\00\45"
+  "\0a\a7\87\80\80\00\46\88\80\80\80\00\00\20\00\20"
+  "\01\fd\6f\0b\88\80\80\80\00\00\20\00\20\01\fd\73"
+  "\0b\89\80\80\80\00\00\20\00\20\01\fd\95\01\0b\89"
+  "\80\80\80\00\00\20\00\20\01\fd\d5\01\0b\89\80\80"
+  "\80\00\00\20\00\20\01\fd\b6\01\0b\89\80\80\80\00"
+  "\00\20\00\20\01\fd\99\01\0b\88\80\80\80\00\00\20"
+  "\00\20\01\fd\7b\0b\89\80\80\80\00\00\20\00\20\01"
+  "\fd\82\01\0b\89\80\80\80\00\00\20\00\20\01\fd\ba"
+  "\01\0b\88\80\80\80\00\00\20\00\20\01\fd\66\0b\89"
+  "\80\80\80\00\00\20\00\20\01\fd\85\01\0b\89\80\80"
+  "\80\00\00\20\00\20\01\fd\9d\01\0b\89\80\80\80\00"
+  "\00\20\00\20\01\fd\de\01\0b\88\80\80\80\00\00\20"
+  "\00\20\01\fd\25\0b\88\80\80\80\00\00\20\00\20\01"
+  "\fd\36\0b\89\80\80\80\00\00\20\00\20\01\fd\d9\01"
+  "\0b\88\80\80\80\00\00\20\00\20\01\fd\4f\0b\88\80"
+  "\80\80\00\00\20\00\20\01\fd\0e\0b\86\80\80\80\00"
+  "\00\20\00\fd\62\0b\87\80\80\80\00\00\20\00\fd\80"
+  "\01\0b\87\80\80\80\00\00\20\00\fd\a1\01\0b\86\80"
+  "\80\80\00\00\20\00\fd\4d\0b\87\80\80\80\00\00\20"
+  "\00\fd\a9\01\0b\87\80\80\80\00\00\20\00\fd\c8\01"
+  "\0b\86\80\80\80\00\00\20\00\fd\7f\0b\86\80\80\80"
+  "\00\00\20\00\fd\7c\0b\88\80\80\80\00\00\20\00\20"
+  "\01\fd\6b\0b\89\80\80\80\00\00\20\00\20\01\fd\8c"
+  "\01\0b\89\80\80\80\00\00\20\00\20\01\fd\cd\01\0b"
+  "\86\80\80\80\00\00\20\00\fd\53\0b\86\80\80\80\00"
+  "\00\20\00\fd\63\0b\87\80\80\80\00\00\20\00\fd\a3"
+  "\01\0b\86\80\80\80\00\00\20\00\fd\64\0b\87\80\80"
+  "\80\00\00\20\00\fd\c4\01\0b\8a\80\80\80\00\00\20"
+  "\00\20\01\20\02\fd\52\0b\98\80\80\80\00\00\20\00"
+  "\20\01\fd\0d\1f\1d\1b\19\17\15\13\11\0f\0d\0b\09"
+  "\07\05\03\01\0b\87\80\80\80\00\00\20\00\fd\15\0f"
+  "\0b\87\80\80\80\00\00\20\00\fd\16\0f\0b\87\80\80"
+  "\80\00\00\20\00\fd\18\06\0b\87\80\80\80\00\00\20"
+  "\00\fd\1d\01\0b\89\80\80\80\00\00\20\00\20\01\fd"
+  "\1a\03\0b\89\80\80\80\00\00\20\00\20\01\fd\22\01"
+  "\0b\86\80\80\80\00\00\20\00\fd\0f\0b\86\80\80\80"
+  "\00\00\20\00\fd\13\0b\89\80\80\80\00\00\20\00\20"
+  "\01\fd\e4\01\0b\89\80\80\80\00\00\20\00\20\01\fd"
+  "\e6\01\0b\89\80\80\80\00\00\20\00\20\01\fd\e8\01"
+  "\0b\89\80\80\80\00\00\20\00\20\01\fd\ea\01\0b\89"
+  "\80\80\80\00\00\20\00\20\01\fd\eb\01\0b\88\80\80"
+  "\80\00\00\20\00\20\01\fd\43\0b\89\80\80\80\00\00"
+  "\20\00\20\01\fd\f1\01\0b\86\80\80\80\00\00\20\00"
+  "\fd\6a\0b\87\80\80\80\00\00\20\00\fd\ef\01\0b\87"
+  "\80\80\80\00\00\20\00\fd\fb\01\0b\87\80\80\80\00"
+  "\00\20\00\fd\f8\01\0b\87\80\80\80\00\00\20\00\fd"
+  "\fd\01\0b\87\80\80\80\00\00\20\00\fd\fe\01\0b\86"
+  "\80\80\80\00\00\20\00\fd\5e\0b\86\80\80\80\00\00"
+  "\20\00\fd\5f\0b\94\80\80\80\00\00\fd\0c\f0\de\bc"
+  "\9a\78\56\34\12\0f\0f\f0\f0\00\ff\ff\00\0b\86\80"
+  "\80\80\00\01\01\7b\20\00\0b\84\80\80\80\00\00\23"
+  "\00\0b\88\80\80\80\00\00\20\00\fd\00\04\01\0b\88"
+  "\80\80\80\00\00\20\00\fd\01\03\00\0b\88\80\80\80"
+  "\00\00\20\00\fd\06\03\00\0b\88\80\80\80\00\00\20"
+  "\00\fd\08\01\00\0b\88\80\80\80\00\00\20\00\fd\5c"
+  "\02\00\0b\8b\80\80\80\00\00\20\00\20\01\fd\55\01"
+  "\00\07\0b\90\80\80\80\00\00\20\00\20\01\fd\5b\03"
+  "\00\01\20\00\29\03\00\0b\90\80\80\80\00\00\20\00"
+  "\20\01\fd\0b\04\00\20\00\fd\00\04\00\0b\0b\a6\80"
+  "\80\80\00\01\00\41\00\0b\20\80\81\82\83\84\85\86"
+  "\87\88\89\8a\8b\8c\8d\8e\8f\90\91\92\93\94\95\96"
+  "\97\98\99\9a\9b\9c\9d\9e\9f"
+)
+(assert_return (invoke "i8x16.add_sat_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x8183ff0e 0xff81007f 0x0001fe05 0xffffffff))
+(assert_return (invoke "i8x16.add_sat_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x8183ff0e 0xff81007f 0x0001fe05 0xffffffff))
+(assert_return (invoke "i8x16.add_sat_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x8080de00 0x11b4577f 0xf1f20d12 0x80ff7eff))
+(assert_return (invoke "i8x16.sub_sat_u" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x7f7f0000 0xff7f0000 0x0000fe01 0x010000ff))
+(assert_return (invoke "i8x16.sub_sat_u" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x0000ffee 0x0000fe00 0xfefd0000 0x00ff0100))
+(assert_return (invoke "i8x16.sub_sat_u" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x1a3bdee0 0x00005500 0xefee000c 0x00ff8000))
+(assert_return (invoke "i16x8.mul" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x8202ffe0 0xff803f01 0xfefefc06 0x80008000))
+(assert_return (invoke "i16x8.mul" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x8202ffe0 0xff803f01 0xfefefc06 0x80008000))
+(assert_return (invoke "i16x8.mul" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0xf8bcef00 0xe6005d88 0xd1e00f2d 0x80000100))
+(assert_return (invoke "i64x2.mul" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x0f2dffe0 0xc41df774 0x0402fc06 0x82037cfe))
+(assert_return (invoke "i64x2.mul" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x0f2dffe0 0xc41df774 0x0402fc06 0x82037cfe))
+(assert_return (invoke "i64x2.mul" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x02bdef00 0x0bfb593e 0x2edf0f2d 0x978d2ef1))
+(assert_return (invoke "i32x4.min_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x80810010 0xff80017f 0xffff0002 0x80007fff))
+(assert_return (invoke "i32x4.min_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x80810010 0xff80017f 0xffff0002 0x80007fff))
+(assert_return (invoke "i32x4.min_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x80810010 0xff80017f 0xf0f00f0f 0x80007fff))
+(assert_return (invoke "i16x8.max_u" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x8081fffe 0xff80ff7f 0xfffffe03 0x80008000))
+(assert_return (invoke "i16x8.max_u" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x8081fffe 0xff80ff7f 0xfffffe03 0x80008000))
+(assert_return (invoke "i16x8.max_u" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x9abcdef0 0xff805678 0xf0f0fe03 0x8000ff00))
+(assert_return (invoke "i8x16.avgr_u" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x41428087 0x8041807f 0x80817f03 0x80808080))
+(assert_return (invoke "i8x16.avgr_u" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x41428087 0x8041807f 0x80817f03 0x80808080))
+(assert_return (invoke "i8x16.avgr_u" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x8d9f6f80 0x895a2c7c 0x79798709 0x4080bf80))
+(assert_return (invoke "i16x8.q15mulr_sat_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0xfeff0000 0x0000fffe 0x00000000 0x80018001))
+(assert_return (invoke "i16x8.q15mulr_sat_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0xfeff0000 0x0000fffe 0x00000000 0x80018001))
+(assert_return (invoke "i16x8.q15mulr_sat_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x64defffc 0xffee0103 0xffe2ffc4 0xff01ff00))
+(assert_return (invoke "i32x4.dot_i16x8_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0xff7f81e2 0xffff3e81 0xfffffb04 0x80010000))
+(assert_return (invoke "i32x4.dot_i16x8_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0xff7f81e2 0xffff3e81 0xfffffb04 0x80010000))
+(assert_return (invoke "i32x4.dot_i16x8_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x326ce7bc 0x00784388 0xffd2e10d 0xff008100))
+(assert_return (invoke "i8x16.narrow_i16x8_u" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x00ff0010 0x00ffff00 0x0100ff00 0xff000002))
+(assert_return (invoke "i8x16.narrow_i16x8_u" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x0100ff00 0xff000002 0x00ff0010 0x00ffff00))
+(assert_return (invoke "i8x16.narrow_i16x8_u" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0xffff0000 0xff0000ff 0x00ff0010 0x00ffff00))
+(assert_return (invoke "i16x8.narrow_i32x4_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x80008000 0x80007fff 0x7fff7fff 0x7fff8000))
+(assert_return (invoke "i16x8.narrow_i32x4_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x7fff7fff 0x7fff8000 0x80008000 0x80007fff))
+(assert_return (invoke "i16x8.narrow_i32x4_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x7fff8000 0x7fff8000 0x80008000 0x80007fff))
+(assert_return (invoke "i16x8.extmul_high_i8x16_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x00000006 0xfffffffe 0xc0800000 0xc0800000))
+(assert_return (invoke "i16x8.extmul_high_i8x16_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x00000006 0xfffffffe 0xc0800000 0xc0800000))
+(assert_return (invoke "i16x8.extmul_high_i8x16_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0xffe2002d 0xfff0ffe0 0xff810000 0x00000000))
+(assert_return (invoke "i64x2.extmul_low_i32x4_u" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x0f2dffe0 0x00820282 0x437d3f01 0x0001fe7f))
+(assert_return (invoke "i64x2.extmul_low_i32x4_u" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x0f2dffe0 0x00820282 0x437d3f01 0x0001fe7f))
+(assert_return (invoke "i64x2.extmul_low_i32x4_u" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x02bdef00 0x4dac68ae 0x004d5d88 0x122b3c68))
+(assert_return (invoke "i8x16.lt_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0xffff0000 0xffff0000 0x0000ff00 0xff0000ff))
+(assert_return (invoke "i8x16.lt_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x0000ffff 0x0000ff00 0xffff00ff 0x00ffff00))
+(assert_return (invoke "i8x16.lt_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x0000ffff 0x000000ff 0xffff0000 0x00ffff00))
+(assert_return (invoke "i16x8.ge_u" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0xffff0000 0xffff0000 0x0000ffff 0xffff0000))
+(assert_return (invoke "i16x8.ge_u" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x0000ffff 0x0000ffff 0xffff0000 0x0000ffff))
+(assert_return (invoke "i16x8.ge_u" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0xffffffff 0x0000ffff 0xffff0000 0x0000ffff))
+(assert_return (invoke "i64x2.gt_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x00000000 0x00000000 0x00000000 0x00000000))
+(assert_return (invoke "i64x2.gt_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0xffffffff 0xffffffff 0xffffffff 0xffffffff))
+(assert_return (invoke "i64x2.gt_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0xffffffff 0xffffffff 0xffffffff 0xffffffff))
+(assert_return (invoke "v128.andnot" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x80810000 0xff800000 0x0000fe01 0x80007fff))
+(assert_return (invoke "v128.andnot" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x0102ffee 0x0001fe00 0xfefd0000 0x7fff8000))
+(assert_return (invoke "v128.andnot" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x1a3cdee0 0x00345600 0xf0f0010c 0x00ff8000))
+(assert_return (invoke "i8x16.swizzle" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x1003000f 0x040201ff 0x05060708 0x0c0d0e11)) (v128.const i32x4 0x00801080 0x7f810000 0x0180ff03 0xff7f0000))
+(assert_return (invoke "i8x16.swizzle" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (v128.const i32x4 0x1003000f 0x040201ff 0x05060708 0x0c0d0e11)) (v128.const i32x4 0x009af000 0x78bcde00 0x5634120f 0x00ffff00))
+(assert_return (invoke "i8x16.popcnt" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x01020001 0x08010107 0x01010702 0x01000708))
+(assert_return (invoke "i8x16.popcnt" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x01010807 0x00010807 0x08080001 0x07080100))
+(assert_return (invoke "i8x16.popcnt" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (v128.const i32x4 0x04050604 0x02030404 0x04040404 0x00080800))
+(assert_return (invoke "i16x8.abs" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x7f7f0010 0x0080017f 0x010201fd 0x80007fff))
+(assert_return (invoke "i16x8.abs" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x01020002 0x00010081 0x00010002 0x7fff8000))
+(assert_return (invoke "i16x8.abs" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (v128.const i32x4 0x65442110 0x12345678 0x0f100f0f 0x00ff0100))
+(assert_return (invoke "i32x4.neg" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x7f7efff0 0x007ffe81 0xfefd01fd 0x7fff8001))
+(assert_return (invoke "i32x4.neg" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0xfefd0002 0xfffe0081 0x0000fffe 0x80008000))
+(assert_return (invoke "i32x4.neg" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (v128.const i32x4 0x65432110 0xedcba988 0x0f0ff0f1 0xff000100))
+(assert_return (invoke "v128.not" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x7f7effef 0x007ffe80 0xfefd01fc 0x7fff8000))
+(assert_return (invoke "v128.not" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0xfefd0001 0xfffe0080 0x0000fffd 0x80007fff))
+(assert_return (invoke "v128.not" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (v128.const i32x4 0x6543210f 0xedcba987 0x0f0ff0f0 0xff0000ff))
+(assert_return (invoke "i32x4.extend_low_i16x8_u" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x00000010 0x00008081 0x0000017f 0x0000ff80))
+(assert_return (invoke "i32x4.extend_low_i16x8_u" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x0000fffe 0x00000102 0x0000ff7f 0x00000001))
+(assert_return (invoke "i32x4.extend_low_i16x8_u" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (v128.const i32x4 0x0000def0 0x00009abc 0x00005678 0x00001234))
+(assert_return (invoke "i64x2.extend_high_i32x4_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x0102fe03 0x00000000 0x80007fff 0xffffffff))
+(assert_return (invoke "i64x2.extend_high_i32x4_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0xffff0002 0xffffffff 0x7fff8000 0x00000000))
+(assert_return (invoke "i64x2.extend_high_i32x4_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (v128.const i32x4 0xf0f00f0f 0xffffffff 0x00ffff00 0x00000000))
+(assert_return (invoke "i32x4.extadd_pairwise_i16x8_u" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0x00008091 0x000100ff 0x0000ff05 0x0000ffff))
+(assert_return (invoke "i32x4.extadd_pairwise_i16x8_u" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x00010100 0x0000ff80 0x00010001 0x0000ffff))
+(assert_return (invoke "i32x4.extadd_pairwise_i16x8_u" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (v128.const i32x4 0x000179ac 0x000068ac 0x0000ffff 0x0000ffff))
+(assert_return (invoke "i16x8.extadd_pairwise_i8x16_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (v128.const i32x4 0xff010010 0xff7f0080 0x00030001 0xff80007e))
+(assert_return (invoke "i16x8.extadd_pairwise_i8x16_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x0003fffd 0x0001007e 0xfffe0002 0x007eff80))
+(assert_return (invoke "i16x8.extadd_pairwise_i8x16_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (v128.const i32x4 0xff56ffce 0x004600ce 0xffe0001e 0xffffffff))
+(assert_return (invoke "i8x16.shl" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (i32.const 1)) (v128.const i32x4 0x00020020 0xfe0002fe 0x0204fc06 0x0000fefe))
+(assert_return (invoke "i8x16.shl" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (i32.const 11)) (v128.const i32x4 0x0810f8f0 0x0008f8f8 0xf8f80010 0xf8f80000))
+(assert_return (invoke "i8x16.shl" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (i32.const 7)) (v128.const i32x4 0x00000000 0x00000000 0x00008080 0x00808000))
+(assert_return (invoke "i16x8.shr_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (i32.const 1)) (v128.const i32x4 0xc0400008 0xffc000bf 0x0081ff01 0xc0003fff))
+(assert_return (invoke "i16x8.shr_s" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (i32.const 19)) (v128.const i32x4 0x0020ffff 0x0000ffef 0xffff0000 0x0ffff000))
+(assert_return (invoke "i16x8.shr_s" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (i32.const 7)) (v128.const i32x4 0xff35ffbd 0x002400ac 0xffe1001e 0x0001fffe))
+(assert_return (invoke "i64x2.shr_u" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (i32.const 1)) (v128.const i32x4 0xc0408008 0x7fc000bf 0x80817f01 0x40003fff))
+(assert_return (invoke "i64x2.shr_u" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (i32.const 67)) (v128.const i32x4 0xe0205fff 0x00003fef 0x1fffe000 0x0ffff000))
+(assert_return (invoke "i64x2.shr_u" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00) (i32.const 7)) (v128.const i32x4 0xf13579bd 0x002468ac 0x01e1e01e 0x0001fffe))
+(assert_return (invoke "v128.any_true" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (i32.const 1))
+(assert_return (invoke "v128.any_true" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (i32.const 1))
+(assert_return (invoke "v128.any_true" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (i32.const 1))
+(assert_return (invoke "v128.any_true" (v128.const i32x4 0x00000000 0x00000000 0x00000000 0x00000000)) (i32.const 0))
+(assert_return (invoke "i8x16.all_true" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (i32.const 0))
+(assert_return (invoke "i8x16.all_true" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (i32.const 0))
+(assert_return (invoke "i8x16.all_true" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (i32.const 0))
+(assert_return (invoke "i8x16.all_true" (v128.const i32x4 0x00000000 0x00000000 0x00000000 0x00000000)) (i32.const 0))
+(assert_return (invoke "i32x4.all_true" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (i32.const 1))
+(assert_return (invoke "i32x4.all_true" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (i32.const 1))
+(assert_return (invoke "i32x4.all_true" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (i32.const 1))
+(assert_return (invoke "i32x4.all_true" (v128.const i32x4 0x00000000 0x00000000 0x00000000 0x00000000)) (i32.const 0))
+(assert_return (invoke "i8x16.bitmask" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (i32.const 37580))
+(assert_return (invoke "i8x16.bitmask" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (i32.const 27683))
+(assert_return (invoke "i8x16.bitmask" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (i32.const 27663))
+(assert_return (invoke "i8x16.bitmask" (v128.const i32x4 0x00000000 0x00000000 0x00000000 0x00000000)) (i32.const 0))
+(assert_return (invoke "i64x2.bitmask" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (i32.const 3))
+(assert_return (invoke "i64x2.bitmask" (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (i32.const 0))
+(assert_return (invoke "i64x2.bitmask" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (i32.const 0))
+(assert_return (invoke "i64x2.bitmask" (v128.const i32x4 0x00000000 0x00000000 0x00000000 0x00000000)) (i32.const 0))
+(assert_return (invoke "v128.bitselect" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000) (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (v128.const i32x4 0x8182211e 0x1201a97f 0x0f0f0e03 0x7f007f00))
+(assert_return (invoke "i8x16.shuffle" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x00ff807f 0xff01ff00 0xfe017f80 0x008001ff))
+(assert_return (invoke "i8x16.extract_lane_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (i32.const -128))
+(assert_return (invoke "i8x16.extract_lane_u" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (i32.const 128))
+(assert_return (invoke "i16x8.extract_lane_s" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) (i32.const 32767))
+(assert_return (invoke "i64x2.extract_lane" (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (i64.const 72056498568564495))
+(assert_return (invoke "i16x8.replace_lane" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (i32.const 74565)) (v128.const i32x4 0x80810010 0x2345017f 0x0102fe03 0x80007fff))
+(assert_return (invoke "f64x2.replace_lane" (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff) (f64.const 0x1.8000000000000p+0)) (v128.const i32x4 0x80810010 0xff80017f 0x00000000 0x3ff80000))
+(assert_return (invoke "i8x16.splat" (i32.const 511)) (v128.const i32x4 0xffffffff 0xffffffff 0xffffffff 0xffffffff))
+(assert_return (invoke "f32x4.splat" (f32.const -0x1.4000000000000p+1)) (v128.const i32x4 0xc0200000 0xc0200000 0xc0200000 0xc0200000))
+(assert_return (invoke "f32x4.add" (v128.const i32x4 0x3fc00000 0x80000000 0x40400000 0x7f800000) (v128.const i32x4 0xbfc00000 0x00000000 0x40000000 0x3f800000)) (v128.const i32x4 0x00000000 0x00000000 0x40a00000 0x7f800000))
+(assert_return (invoke "f32x4.mul" (v128.const i32x4 0x3fc00000 0x80000000 0x40400000 0x7f800000) (v128.const i32x4 0xbfc00000 0x00000000 0x40000000 0x3f800000)) (v128.const i32x4 0xc0100000 0x80000000 0x40c00000 0x7f800000))
+(assert_return (invoke "f32x4.min" (v128.const i32x4 0x3fc00000 0x80000000 0x40400000 0x7f800000) (v128.const i32x4 0xbfc00000 0x00000000 0x40000000 0x3f800000)) (v128.const i32x4 0xbfc00000 0x80000000 0x40000000 0x3f800000))
+(assert_return (invoke "f32x4.pmin" (v128.const i32x4 0xbfc00000 0x00000000 0x40000000 0x3f800000) (v128.const i32x4 0x3fc00000 0x80000000 0x40400000 0x7f800000)) (v128.const i32x4 0xbfc00000 0x00000000 0x40000000 0x3f800000))
+(assert_return (invoke "f32x4.pmax" (v128.const i32x4 0xbfc00000 0x00000000 0x40000000 0x3f800000) (v128.const i32x4 0x3fc00000 0x80000000 0x40400000 0x7f800000)) (v128.const i32x4 0x3fc00000 0x00000000 0x40400000 0x7f800000))
+(assert_return (invoke "f32x4.lt" (v128.const i32x4 0xbfc00000 0x00000000 0x40000000 0x3f800000) (v128.const i32x4 0x3fc00000 0x80000000 0x40400000 0x7f800000)) (v128.const i32x4 0xffffffff 0x00000000 0xffffffff 0xffffffff))
+(assert_return (invoke "f64x2.sub" (v128.const i32x4 0x00000000 0x3ff00000 0x00000000 0x3fe00000) (v128.const i32x4 0x00000000 0x3fd00000 0x00000000 0x40000000)) (v128.const i32x4 0x00000000 0x3fe80000 0x00000000 0xbff80000))
+(assert_return (invoke "f32x4.nearest" (v128.const i32x4 0x40200000 0x40600000 0xbf000000 0xbfc00000)) (v128.const i32x4 0x40000000 0x40800000 0x80000000 0xc0000000))
+(assert_return (invoke "f64x2.sqrt" (v128.const i32x4 0x00000000 0x40100000 0x00000000 0x40020000)) (v128.const i32x4 0x00000000 0x40000000 0x00000000 0x3ff80000))
+(assert_return (invoke "f32x4.convert_i32x4_u" (v128.const i32x4 0x00000001 0xffffffff 0x80000000 0x00000007)) (v128.const i32x4 0x3f800000 0x4f800000 0x4f000000 0x40e00000))
+(assert_return (invoke "i32x4.trunc_sat_f32x4_s" (v128.const i32x4 0xbff33333 0x50df8476 0x7fc00000 0xff800000)) (v128.const i32x4 0xffffffff 0x7fffffff 0x00000000 0x80000000))
+(assert_return (invoke "i32x4.trunc_sat_f64x2_u_zero" (v128.const i32x4 0x00000000 0xc0080000 0x20000000 0x41f2a05f)) (v128.const i32x4 0x00000000 0xffffffff 0x00000000 0x00000000))
+(assert_return (invoke "f64x2.convert_low_i32x4_s" (v128.const i32x4 0xfffffff9 0x00000009 0x00000001 0x00000001)) (v128.const i32x4 0x00000000 0xc01c0000 0x00000000 0x40220000))
+(assert_return (invoke "f32x4.demote_f64x2_zero" (v128.const i32x4 0x00000000 0x3ff80000 0x00000000 0xbfd00000)) (v128.const i32x4 0x3fc00000 0xbe800000 0x00000000 0x00000000))
+(assert_return (invoke "f64x2.promote_low_f32x4" (v128.const i32x4 0x3fc00000 0xbe800000 0x00000000 0x00000000)) (v128.const i32x4 0x00000000 0x3ff80000 0x00000000 0xbfd00000))
+(assert_return (invoke "const") (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00))
+(assert_return (invoke "local") (v128.const i32x4 0x00000000 0x00000000 0x00000000 0x00000000))
+(assert_return (invoke "global") (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff))
+(assert_return (invoke "v128.load" (i32.const 2)) (v128.const i32x4 0x86858483 0x8a898887 0x8e8d8c8b 0x9291908f))
+(assert_return (invoke "v128.load8x8_s" (i32.const 1)) (v128.const i32x4 0xff82ff81 0xff84ff83 0xff86ff85 0xff88ff87))
+(assert_return (invoke "v128.load32x2_u" (i32.const 4)) (v128.const i32x4 0x87868584 0x00000000 0x8b8a8988 0x00000000))
+(assert_return (invoke "v128.load16_splat" (i32.const 5)) (v128.const i32x4 0x86858685 0x86858685 0x86858685 0x86858685))
+(assert_return (invoke "v128.load32_zero" (i32.const 6)) (v128.const i32x4 0x89888786 0x00000000 0x00000000 0x00000000))
+(assert_return (invoke "v128.load16_lane" (i32.const 30) (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x9f9eff00))
+(assert_return (invoke "v128.store64_lane" (i32.const 8) (v128.const i32x4 0x9abcdef0 0x12345678 0xf0f00f0f 0x00ffff00)) (i64.const 72056498568564495))
+(assert_return (invoke "v128.store" (i32.const 16) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000)) (v128.const i32x4 0x0102fffe 0x0001ff7f 0xffff0002 0x7fff8000))
+(assert_trap (invoke "v128.load" (i32.const 65520)) "out of bounds memory access")
+(assert_trap (invoke "v128.load" (i32.const -1)) "out of bounds memory access")
+(assert_trap (invoke "v128.load32_zero" (i32.const 65533)) "out of bounds memory access")
+(assert_trap (invoke "v128.load16_lane" (i32.const 65535) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) "out of bounds memory access")
+(assert_trap (invoke "v128.store" (i32.const 65521) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) "out of bounds memory access")
+(assert_trap (invoke "v128.store64_lane" (i32.const 65529) (v128.const i32x4 0x80810010 0xff80017f 0x0102fe03 0x80007fff)) "out of bounds memory access")
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\85\80\80\80\00\01\60"
+    "\00\01\7b\03\82\80\80\80\00\01\00\05\83\80\80\80"
+    "\00\01\00\01\0a\8a\80\80\80\00\01\84\80\80\80\00"
+    "\00\41\00\0b"
+  )
+  "type mismatch"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\86\80\80\80\00\01\60"
+    "\01\7b\01\7f\03\82\80\80\80\00\01\00\05\83\80\80"
+    "\80\00\01\00\01\0a\8d\80\80\80\00\01\87\80\80\80"
+    "\00\00\20\00\fd\1b\04\0b"
+  )
+  "invalid lane index"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\87\80\80\80\00\01\60"
+    "\02\7b\7b\01\7b\03\82\80\80\80\00\01\00\05\83\80"
+    "\80\80\00\01\00\01\0a\9e\80\80\80\00\01\98\80\80"
+    "\80\00\00\20\00\20\01\fd\0d\20\00\00\00\00\00\00"
+    "\00\00\00\00\00\00\00\00\00\0b"
+  )
+  "invalid lane index"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\86\80\80\80\00\01\60"
+    "\01\7f\01\7b\03\82\80\80\80\00\01\00\05\83\80\80"
+    "\80\00\01\00\01\0a\8e\80\80\80\00\01\88\80\80\80"
+    "\00\00\20\00\fd\00\05\00\0b"
+  )
+  "alignment must not be larger than natural"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\86\80\80\80\00\01\60"
+    "\01\7f\01\7b\03\82\80\80\80\00\01\00\0a\8e\80\80"
+    "\80\00\01\88\80\80\80\00\00\20\00\fd\00\04\00\0b"
+  )
+  "unknown memory 0"
+)
+(assert_invalid
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\87\80\80\80\00\01\60"
+    "\02\7b\7b\01\7b\03\82\80\80\80\00\01\00\05\83\80"
+    "\80\80\00\01\00\01\0a\8e\80\80\80\00\01\88\80\80"
+    "\80\00\00\20\00\20\01\fd\6b\0b"
+  )
+  "type mismatch"
+)
+(assert_malformed
+  (module binary
+    "\00\61\73\6d\01\00\00\00\01\84\80\80\80\00\01\60"
+    "\00\00\03\82\80\80\80\00\01\00\05\83\80\80\80\00"
+    "\01\00\01\0a\8b\80\80\80\00\01\85\80\80\80\00\00"
+    "\fd\9a\01\0b"
+  )
+  "unexpected end"
+)
@@ -0,0 +1,154 @@
+// The functions a wasm module may import from watt, with their signatures as
+// declared by the proc-macro2 shim. This file is included by both sides: the
+// runtime checks every import of a module against it and the shim generates
+// its extern block from it, so that the two cannot drift apart. The groups are
+// what an import policy allows or denies.
+//
+// Every type is a single identifier. Handles are named after what they refer
+// to. Pointers into the memory of the module are `Ptr` and `PtrMut`, and
+// `Batch` is the buffer of (kind, handle) pairs filled in by
+// token_stream_iter_next_batch. Everything other than u64, i64, f32 and f64
+// is passed as an i32.
+macro_rules! watt_abi {
+    ($callback:ident) => {
+        $callback! {
+            // Building, inspecting and iterating token trees, spans, identifiers
+            // and literals
+            Tokens {
+                fn token_stream_new() -> TokenStream;
+                fn token_stream_is_empty(stream: TokenStream) -> bool;
+                fn token_stream_into_iter(stream: TokenStream) -> IntoIter;
+                fn token_stream_iter_next_batch(iter: IntoIter, buf: Batch, cap: usize) -> usize;
+                fn token_stream_from_group(group: Group) -> TokenStream;
+                fn token_stream_from_ident(ident: Ident) -> TokenStream;
+                fn token_stream_from_punct(punct: Punct) -> TokenStream;
+                fn token_stream_from_literal(literal: Literal) -> TokenStream;
+                fn token_stream_push_group(stream: TokenStream, group: Group);
+                fn token_stream_push_ident(stream: TokenStream, ident: Ident);
+                fn token_stream_push_punct(stream: TokenStream, punct: Punct);
+                fn token_stream_push_literal(stream: TokenStream, literal: Literal);
+                fn token_stream_extend(stream: TokenStream, next: TokenStream);
+
+                fn span_call_site() -> Span;
+
+                fn group_new(delimiter: u32, stream: TokenStream) -> Group;
+                fn group_delimiter(group: Group) -> u32;
+                fn group_stream(group: Group) -> TokenStream;
+                fn group_span(group: Group) -> Span;
+                fn group_set_span(group: Group, span: Span);
+
+                fn punct_new(op: u32, spacing: u32) -> Punct;
+                fn punct_as_char(punct: Punct) -> u32;
+                fn punct_spacing(punct: Punct) -> u32;
+                fn punct_span(punct: Punct) -> Span;
+                fn punct_set_span(punct: Punct, span: Span);
+
+                fn ident_new(string: String, span: Span) -> Ident;
+                fn ident_span(ident: Ident) -> Span;
+                fn ident_set_span(ident: Ident, span: Span);
+                fn ident_eq(ident: Ident, other: Ident) -> bool;
+                fn ident_eq_str(ident: Ident, other: String) -> bool;
+                fn ident_cmp(ident: Ident, other: Ident) -> u32;
+
+                fn literal_u8_suffixed(n: u8) -> Literal;
+                fn literal_u16_suffixed(n: u16) -> Literal;
+                fn literal_u32_suffixed(n: u32) -> Literal;
+                fn literal_u64_suffixed(n: u64) -> Literal;
+                fn literal_u128_suffixed(lo: u64, hi: u64) -> Literal;
+                fn literal_usize_suffixed(n: usize) -> Literal;
+                fn literal_i8_suffixed(n: i8) -> Literal;
+                fn literal_i16_suffixed(n: i16) -> Literal;
+                fn literal_i32_suffixed(n: i32) -> Literal;
+                fn literal_i64_suffixed(n: i64) -> Literal;
+                fn literal_i128_suffixed(lo: u64, hi: u64) -> Literal;
+                fn literal_isize_suffixed(n: isize) -> Literal;
+                fn literal_u8_unsuffixed(n: u8) -> Literal;
+                fn literal_u16_unsuffixed(n: u16) -> Literal;
+                fn literal_u32_unsuffixed(n: u32) -> Literal;
+                fn literal_u64_unsuffixed(n: u64) -> Literal;
+                fn literal_u128_unsuffixed(lo: u64, hi: u64) -> Literal;
+                fn literal_usize_unsuffixed(n: usize) -> Literal;
+                fn literal_i8_unsuffixed(n: i8) -> Literal;
+                fn literal_i16_unsuffixed(n: i16) -> Literal;
+                fn literal_i32_unsuffixed(n: i32) -> Literal;
+                fn literal_i64_unsuffixed(n: i64) -> Literal;
+                fn literal_i128_unsuffixed(lo: u64, hi: u64) -> Literal;
+                fn literal_isize_unsuffixed(n: isize) -> Literal;
+                fn literal_f64_unsuffixed(f: f64) -> Literal;
+                fn literal_f64_suffixed(f: f64) -> Literal;
+                fn literal_f32_unsuffixed(f: f32) -> Literal;
+                fn literal_f32_suffixed(f: f32) -> Literal;
+                fn literal_string(string: String) -> Literal;
+                fn literal_character(ch: u32) -> Literal;
+                fn literal_byte_string(bytes: Bytes) -> Literal;
+                fn literal_span(literal: Literal) -> Span;
+                fn literal_set_span(literal: Literal, span: Span);
+
+                fn token_stream_clone(stream: TokenStream) -> TokenStream;
+                fn group_clone(group: Group) -> Group;
+                fn ident_clone(ident: Ident) -> Ident;
+                fn punct_clone(punct: Punct) -> Punct;
+                fn literal_clone(literal: Literal) -> Literal;
+                fn token_stream_iter_clone(iter: IntoIter) -> IntoIter;
+
+                fn token_stream_drop(stream: TokenStream);
+                fn group_drop(group: Group);
+                fn ident_drop(ident: Ident);
+                fn punct_drop(punct: Punct);
+                fn literal_drop(literal: Literal);
+                fn token_stream_iter_drop(iter: IntoIter);
+            }
+
+            // Parsing source text into tokens
+            Parse {
+                fn token_stream_from_str(string: String) -> TokenStream;
+                fn watt_token_stream_parse(string: String) -> Bytes;
+            }
+
+            // Printing tokens and spans, as Display and Debug
+            Format {
+                fn token_stream_to_string(stream: TokenStream) -> String;
+                fn group_to_string(group: Group) -> String;
+                fn ident_to_string(ident: Ident) -> String;
+                fn punct_to_string(punct: Punct) -> String;
+                fn literal_to_string(literal: Literal) -> String;
+                fn token_stream_debug(stream: TokenStream) -> String;
+                fn group_debug(group: Group) -> String;
+                fn ident_debug(ident: Ident) -> String;
+                fn punct_debug(punct: Punct) -> String;
+                fn literal_debug(literal: Literal) -> String;
+                fn span_debug(span: Span) -> String;
+            }
+
+            // Moving strings and bytes in and out of the memory of the module, and
+            // reporting panics
+            Transfer {
+                fn watt_string_new(ptr: Ptr, len: usize) -> String;
+                fn watt_string_len(string: String) -> usize;
+                fn watt_string_copy(string: String, ptr: PtrMut);
+                fn watt_bytes_new(ptr: Ptr, len: usize) -> Bytes;
+                fn watt_bytes_len(bytes: Bytes) -> usize;
+                fn watt_bytes_copy(bytes: Bytes, ptr: PtrMut);
+                fn watt_string_drop(string: String);
+                fn watt_bytes_drop(bytes: Bytes);
+                fn watt_print_panic(message: String);
+            }
+
+            // Token at a time iteration, and character at a time transfer, still
+            // imported by modules built against earlier versions of the shim
+            Legacy {
+                fn token_stream_iter_next(iter: IntoIter) -> TokenTree;
+                fn token_tree_kind(token: TokenTree) -> u32;
+                fn token_tree_unwrap_group(token: TokenTree) -> Group;
+                fn token_tree_unwrap_ident(token: TokenTree) -> Ident;
+                fn token_tree_unwrap_punct(token: TokenTree) -> Punct;
+                fn token_tree_unwrap_literal(token: TokenTree) -> Literal;
+                fn watt_string_with_capacity(cap: usize) -> String;
+                fn watt_string_push_char(string: String, ch: u32);
+                fn watt_string_char_at(string: String, pos: usize) -> u32;
+                fn watt_bytes_with_capacity(cap: usize) -> Bytes;
+                fn watt_bytes_push(bytes: Bytes, b: u8);
+            }
+        }
+    };
+}
@@ -2,7 +2,6 @@ use proc_macro::{
     token_stream, Group, Ident, LexError, Literal, Punct, Span, TokenStream, TokenTree,
 };
 use std::cell::RefCell;
-use std::ops::{Index, IndexMut};
 
 thread_local! {
     static DATA: RefCell<Data> = RefCell::new(Data::default());
@@ -21,6 +20,8 @@ pub struct Data {
     pub span: Collection<Span>,
     pub intoiter: Collection<token_stream::IntoIter>,
     pub lexerror: Collection<LexError>,
+    pub panic: Option<String>,
+    call_site: Option<Handle>,
 }
 
 impl Data {
@@ -28,6 +29,19 @@ impl Data {
         DATA.with(|d| op(&mut d.borrow_mut()))
     }
 
+    // Spans cannot be dropped by the module, so the call site is only pushed
+    // once per invocation rather than on every request
+    pub fn call_site(&mut self) -> Result<Handle, String> {
+        match self.call_site {
+            Some(span) => Ok(span),
+            None => {
+                let span = self.span.push(Span::call_site())?;
+                self.call_site = Some(span);
+                Ok(span)
+            }
+        }
+    }
+
     pub fn guard() -> impl Drop {
         struct Guard;
 
@@ -41,36 +55,188 @@ impl Data {
     }
 }
 
+// Objects referred to by the module through handles. Slots of dropped objects
+// are reused, and handles carry the generation of their slot in their high
+// bits, so that a stale handle is detected rather than aliasing the object
+// which took its place. A slot whose generation is exhausted is retired rather
+// than reused, so that generations never wrap around. Handles also carry the
+// kind of object they refer to, so that a handle passed where another kind is
+// expected is detected too.
+//
+//     generation: 8 bits | kind: 4 bits | index: 20 bits
 pub struct Collection<T> {
-    vec: Vec<T>,
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
 }
 
 pub type Handle = u32;
 
-impl<T> Collection<T> {
-    pub fn push(&mut self, value: T) -> Handle {
-        let handle = self.vec.len();
-        self.vec.push(value);
-        handle as Handle
-    }
+const INDEX_BITS: u32 = 20;
+const KIND_BITS: u32 = 4;
+const INDEX_MASK: u32 = (1 << INDEX_BITS) - 1;
+const KIND_MASK: u32 = (1 << KIND_BITS) - 1;
+const GENERATION_SHIFT: u32 = INDEX_BITS + KIND_BITS;
+const GENERATION_MASK: u32 = (1 << (32 - GENERATION_SHIFT)) - 1;
+
+pub trait Kind {
+    const KIND: u32;
 }
 
-impl<T> Index<Handle> for Collection<T> {
-    type Output = T;
+// Spans are kind 0 so that the first span, which is the call site in the
+// serialized form of token streams, is handle 0. No kind has all bits set, so
+// no handle is the sentinel.
+const KINDS: &[&str] = &[
+    "Span",
+    "String",
+    "Bytes",
+    "TokenStream",
+    "TokenTree",
+    "Group",
+    "Ident",
+    "Punct",
+    "Literal",
+    "IntoIter",
+    "LexError",
+];
 
-    fn index(&self, handle: Handle) -> &Self::Output {
-        &self.vec[handle as usize]
-    }
+macro_rules! kinds {
+    ($($t:ty => $kind:expr,)*) => {
+        $(
+            impl Kind for $t {
+                const KIND: u32 = $kind;
+            }
+        )*
+    };
 }
 
-impl<T> IndexMut<Handle> for Collection<T> {
-    fn index_mut(&mut self, handle: Handle) -> &mut Self::Output {
-        &mut self.vec[handle as usize]
+kinds! {
+    Span => 0,
+    String => 1,
+    Vec<u8> => 2,
+    TokenStream => 3,
+    TokenTree => 4,
+    Group => 5,
+    Ident => 6,
+    Punct => 7,
+    Literal => 8,
+    token_stream::IntoIter => 9,
+    LexError => 10,
+}
+
+impl<T: Kind> Collection<T> {
+    pub fn push(&mut self, value: T) -> Result<Handle, String> {
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index as usize].value = Some(value);
+                index
+            }
+            None => {
+                let index = self.slots.len() as u32;
+                if index > INDEX_MASK {
+                    return Err(format!("too many live {} handles", KINDS[T::KIND as usize],));
+                }
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                index
+            }
+        };
+        let generation = self.slots[index as usize].generation;
+        Ok(generation << GENERATION_SHIFT | T::KIND << INDEX_BITS | index)
+    }
+
+    pub fn get(&self, handle: Handle) -> Result<&T, String> {
+        match self.slots.get(self.index(handle)?) {
+            Some(slot) if slot.generation == handle >> GENERATION_SHIFT => {
+                slot.value.as_ref().ok_or_else(|| dropped::<T>(handle))
+            }
+            _ => Err(dropped::<T>(handle)),
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Result<&mut T, String> {
+        let index = self.index(handle)?;
+        match self.slots.get_mut(index) {
+            Some(slot) if slot.generation == handle >> GENERATION_SHIFT => {
+                slot.value.as_mut().ok_or_else(|| dropped::<T>(handle))
+            }
+            _ => Err(dropped::<T>(handle)),
+        }
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Result<T, String> {
+        let index = self.index(handle)?;
+        let slot = match self.slots.get_mut(index) {
+            Some(slot) if slot.generation == handle >> GENERATION_SHIFT => slot,
+            _ => return Err(dropped::<T>(handle)),
+        };
+        let value = slot.value.take().ok_or_else(|| dropped::<T>(handle))?;
+        if slot.generation < GENERATION_MASK {
+            slot.generation += 1;
+            self.free.push(index as u32);
+        }
+        Ok(value)
+    }
+
+    // Index of the slot of a handle of the right kind
+    fn index(&self, handle: Handle) -> Result<usize, String> {
+        let kind = handle >> INDEX_BITS & KIND_MASK;
+        if kind == T::KIND {
+            return Ok((handle & INDEX_MASK) as usize);
+        }
+        Err(match KINDS.get(kind as usize) {
+            Some(found) => format!(
+                "expected {} handle, found {} handle {:#x}",
+                KINDS[T::KIND as usize],
+                found,
+                handle,
+            ),
+            None => format!("invalid handle {:#x}", handle),
+        })
     }
 }
 
+fn dropped<T: Kind>(handle: Handle) -> String {
+    format!(
+        "{} handle {:#x} was dropped or never created",
+        KINDS[T::KIND as usize],
+        handle,
+    )
+}
+
 impl<T> Default for Collection<T> {
     fn default() -> Self {
-        Collection { vec: Vec::new() }
+        Collection {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_many_live_handles() {
+        let mut strings = Collection::default();
+        let first = strings.push(String::new()).unwrap();
+        for _ in 0..INDEX_MASK {
+            strings.push(String::new()).unwrap();
+        }
+        assert_eq!(
+            strings.push(String::new()).unwrap_err(),
+            "too many live String handles",
+        );
+
+        // Dropping a handle makes room for another one
+        strings.remove(first).unwrap();
+        strings.push(String::new()).unwrap();
     }
 }
@@ -1,4 +1,4 @@
-use crate::watt::{module_exports, module_imports, Module};
+use crate::watt::{fusion_sites, module_exports, module_imports, Module, Store};
 
 pub fn print_module(module: &Module) {
     let mut imports: Vec<_> = module_imports(module).collect();
@@ -13,3 +13,9 @@ pub fn print_module(module: &Module) {
         eprintln!("EXPORT {:?}: {:?}", name, sig);
     }
 }
+
+pub fn print_fusions(store: &Store) {
+    for (pattern, sites) in fusion_sites(store) {
+        eprintln!("FUSION {:?}: {} sites", pattern, sites);
+    }
+}
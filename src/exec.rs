@@ -1,43 +1,181 @@
 use crate::data::Data;
+use crate::policy::ImportPolicy;
+use crate::watt::error::E;
+use crate::watt::interpreter::{Trap, TrapOrigin};
+use crate::watt::runtime::FuncAddr;
 use crate::watt::{
-    decode_module, get_export, init_store, instantiate_module, invoke_func, ExternVal, Value,
+    decode_compact_module, decode_module, get_export, init_store, instantiate_module, invoke_func,
+    set_max_call_depth, ExternVal, Module, Store, Value,
 };
-use crate::{debug, import};
+use crate::{debug, import, local};
 use proc_macro::TokenStream;
-use std::io::Cursor;
+use std::env;
+use std::str::FromStr;
 
-pub fn proc_macro(fun: &str, inputs: Vec<TokenStream>, wasm: &[u8]) -> TokenStream {
-    let cursor = Cursor::new(wasm);
-    let module = decode_module(cursor).unwrap();
+#[derive(Copy, Clone)]
+pub enum Source<'a> {
+    Wasm(&'a [u8]),
+    Compact(&'a [u8]),
+}
+
+// Options of an invocation, chosen by the shim through `WasmMacro`
+#[derive(Clone, Default)]
+pub struct Options {
+    pub max_call_depth: Option<usize>,
+    pub stub_unresolved: bool,
+    pub policy: ImportPolicy,
+}
+
+// The environment variable takes precedence over the limit set by the shim, so
+// that the user of a macro can raise it without rebuilding anything
+fn call_depth_limit(options: &Options) -> Option<usize> {
+    env::var("WATT_MAX_CALL_DEPTH")
+        .ok()
+        .and_then(|depth| depth.parse().ok())
+        .or(options.max_call_depth)
+}
+
+pub fn proc_macro(
+    fun: &str,
+    inputs: Vec<TokenStream>,
+    source: Source,
+    options: &Options,
+) -> TokenStream {
+    let module = match source {
+        Source::Wasm(wasm) => decode_module(wasm).unwrap(),
+        Source::Compact(compact) => match decode_compact_module(compact) {
+            Ok(module) => module,
+            Err(err) => panic!(
+                "failed to load compact module ({:?}), it may need to be regenerated with watt-compact",
+                err
+            ),
+        },
+    };
+    run(fun, inputs, module, options)
+}
+
+fn run(fun: &str, inputs: Vec<TokenStream>, module: Module, options: &Options) -> TokenStream {
     if cfg!(watt_debug) {
         debug::print_module(&module);
     }
 
     let mut store = init_store();
-    let extern_vals = import::extern_vals(&module, &mut store);
+    if let Some(depth) = call_depth_limit(options) {
+        set_max_call_depth(&mut store, depth);
+    }
+    let extern_vals = match import::extern_vals(&module, &mut store, options) {
+        Ok(extern_vals) => extern_vals,
+        Err(report) => return compile_error(&report),
+    };
     let module_instance = instantiate_module(&mut store, module, &extern_vals).unwrap();
     let main = match get_export(&module_instance, fun) {
         Ok(ExternVal::Func(main)) => main,
         _ => unimplemented!("unresolved macro: {:?}", fun),
     };
 
+    // Modules built with the local-tokens feature of the proc-macro2 shim keep
+    // token trees in their own memory. Their inputs and output cross the
+    // boundary serialized, through functions they export for that purpose.
+    let local = match (
+        get_export(&module_instance, "watt_token_stream_decode"),
+        get_export(&module_instance, "watt_token_stream_encode"),
+    ) {
+        (Ok(ExternVal::Func(decode)), Ok(ExternVal::Func(encode))) => Some((decode, encode)),
+        _ => None,
+    };
+
     let _guard = Data::guard();
-    let args = Data::with(|d| {
-        inputs
-            .into_iter()
-            .map(|input| Value::I32(d.tokenstream.push(input)))
-            .collect()
-    });
-
-    let res = invoke_func(&mut store, main, args);
+    let args: Result<Vec<Value>, String> = match local {
+        None => Data::with(|d| {
+            inputs
+                .into_iter()
+                .map(|input| d.tokenstream.push(input).map(Value::I32))
+                .collect()
+        }),
+        Some((decode, _)) => {
+            // The call site is span 0 of the serialized form
+            Data::with(Data::call_site).and_then(|_| {
+                inputs
+                    .into_iter()
+                    .map(|input| {
+                        let bytes = Data::with(|d| {
+                            let bytes = local::encode(&input, &mut d.span)?;
+                            d.bytes.push(bytes)
+                        })?;
+                        Ok(Value::I32(call(&mut store, decode, bytes)))
+                    })
+                    .collect()
+            })
+        }
+    };
+    let args = match args {
+        Ok(args) => args,
+        Err(err) => return compile_error(&format!("failed to pass input to wasm macro: {}", err)),
+    };
+
+    let handle = invoke(&mut store, main, args);
+    let output = match local {
+        None => Data::with(|d| match d.tokenstream.get(handle) {
+            Ok(stream) => stream.clone(),
+            Err(err) => panic!("invalid token stream returned by wasm: {}", err),
+        }),
+        Some((_, encode)) => {
+            let bytes = call(&mut store, encode, handle);
+            let output = Data::with(|d| match d.bytes.get(bytes) {
+                Ok(bytes) => local::decode(bytes, &d.span),
+                Err(err) => panic!("invalid token stream returned by wasm: {}", err),
+            });
+            match output {
+                Some(output) => output,
+                None => compile_error(&format!(
+                    "malformed token stream returned by wasm macro {:?}",
+                    fun
+                )),
+            }
+        }
+    };
+
+    // Functions are lowered on their first call, so fusions are only known
+    // once the macro has run
+    if cfg!(watt_stats) {
+        debug::print_fusions(&store);
+    }
+    output
+}
+
+fn compile_error(msg: &str) -> TokenStream {
+    let tokens = format!("compile_error!({:?});", msg);
+    TokenStream::from_str(&tokens).unwrap()
+}
+
+fn call(store: &mut Store, func: FuncAddr, arg: u32) -> u32 {
+    invoke(store, func, vec![Value::I32(arg)])
+}
+
+fn invoke(store: &mut Store, func: FuncAddr, args: Vec<Value>) -> u32 {
+    let res = invoke_func(store, func, args);
     let values = match res {
         Ok(values) => values,
-        Err(err) => panic!("{:?}", err),
+        Err(err) => {
+            // A panic reported by the module may since have been caught, so
+            // it only explains the errors a panic ends in: an exception that
+            // unwinds out of the macro, or the trap of an aborting panic
+            let panic = Data::with(|d| d.panic.take());
+            match (panic, &err.0) {
+                (Some(msg), E::UncaughtException)
+                | (
+                    Some(msg),
+                    E::CodeTrapped(Trap {
+                        origin: TrapOrigin::Unreachable,
+                    }),
+                ) => panic!("{} ({:?})", msg, err.0),
+                _ => panic!("{:?}", err),
+            }
+        }
     };
     let handle = values.into_iter().next().unwrap();
-    let handle = match handle {
+    match handle {
         Value::I32(handle) => handle,
         _ => unimplemented!("unexpected macro return type"),
-    };
-    Data::with(|d| d.tokenstream[handle].clone())
+    }
 }
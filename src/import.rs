@@ -1,127 +1,256 @@
+use crate::exec::Options;
+use crate::policy::ImportGroup;
+use crate::signature;
 use crate::sym;
-use crate::watt::{alloc_func, module_imports, Extern, ExternVal, HostFunc, Module, Store};
+use crate::watt::runtime::TagAddr;
+use crate::watt::types::{self, Float, Int};
+use crate::watt::{
+    alloc_func, alloc_global, alloc_tag, module_imports, type_tag, Caller, Extern, ExternVal,
+    HostFunc, Module, Store, Value,
+};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
 
 type Import<'a> = (&'a str, &'a str, Extern);
 
-pub fn extern_vals(module: &Module, store: &mut Store) -> Vec<ExternVal> {
-    module_imports(module)
-        .map(|import| resolve(import, store))
-        .collect()
+// Host globals handed out so far, by module and name, along with their type.
+// Importing the same global twice gives back the same one so that writes
+// through one import are visible through the other.
+type Globals = HashMap<(String, String), (types::Global, ExternVal)>;
+
+// Exception tags handed out so far, by module and name, so that a module
+// importing the same tag twice can catch what it throws through either import.
+type Tags = HashMap<(String, String), TagAddr>;
+
+// Why an import cannot be provided
+enum Reason {
+    NotProvided,
+    Signature(String),
+    Denied(ImportGroup),
+    Unsupported(String),
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::NotProvided => f.write_str("function not provided by watt"),
+            Reason::Signature(msg) | Reason::Unsupported(msg) => f.write_str(msg),
+            Reason::Denied(group) => write!(
+                f,
+                "{:?} imports are denied by the import policy of this macro",
+                group,
+            ),
+        }
+    }
 }
 
-fn resolve(import: Import, store: &mut Store) -> ExternVal {
-    let (_module, name, sig) = import;
+// Every import watt cannot provide is reported at once, along with what the
+// module was likely built with, rather than failing on the first one
+pub fn extern_vals(
+    module: &Module,
+    store: &mut Store,
+    options: &Options,
+) -> Result<Vec<ExternVal>, String> {
+    let mut globals = Globals::new();
+    let mut tags = Tags::new();
+    let mut extern_vals = Vec::new();
+    let mut unsupported = Vec::new();
+    for import in module_imports(module) {
+        let (module, name, _) = import;
+        match resolve(import, store, options, &mut globals, &mut tags) {
+            Ok(extern_val) => extern_vals.push(extern_val),
+            Err(reason) => unsupported.push((module, name, reason)),
+        }
+    }
+    if unsupported.is_empty() {
+        Ok(extern_vals)
+    } else {
+        Err(report_unsupported(&unsupported))
+    }
+}
+
+fn resolve(
+    import: Import,
+    store: &mut Store,
+    options: &Options,
+    globals: &mut Globals,
+    tags: &mut Tags,
+) -> Result<ExternVal, Reason> {
+    let (module, name, sig) = import;
     let func = match sig {
         Extern::Func(func) => func,
-        Extern::Table(_) | Extern::Memory(_) | Extern::Global(_) => {
-            unimplemented!("unsupported import")
+        Extern::Global(global) => {
+            let key = (module.to_owned(), name.to_owned());
+            let (type_, extern_val) = globals
+                .entry(key)
+                .or_insert_with(|| (global.clone(), host_global(store, &global)));
+            if *type_ != global {
+                return Err(Reason::Unsupported(format!(
+                    "global imported as {} but previously as {}",
+                    display_global(&global),
+                    display_global(type_),
+                )));
+            }
+            return Ok(*extern_val);
+        }
+        Extern::Tag(tag) => {
+            let key = (module.to_owned(), name.to_owned());
+            let addr = *tags.entry(key).or_insert_with(|| alloc_tag(store, &tag));
+            let type_ = type_tag(store, addr);
+            if type_ != tag {
+                return Err(Reason::Unsupported(format!(
+                    "tag imported as {} but previously as {}",
+                    signature::display(&tag.args, &tag.result),
+                    signature::display(&type_.args, &type_.result),
+                )));
+            }
+            return Ok(ExternVal::Tag(addr));
+        }
+        Extern::Table(_) => {
+            return Err(Reason::Unsupported(
+                "table imports are not supported".to_owned(),
+            ))
+        }
+        Extern::Memory(_) => {
+            return Err(Reason::Unsupported(
+                "memory imports are not supported".to_owned(),
+            ))
+        }
+    };
+
+    let signature = match signature::find(name) {
+        Some(signature) => signature,
+        // Functions watt does not provide are stubbed out if the shim asks
+        // for it, trapping only when called, instead of failing instantiation
+        None if options.stub_unresolved => {
+            let hostfunc = stub(module, name);
+            return Ok(ExternVal::Func(alloc_func(store, &func, hostfunc)));
+        }
+        None => return Err(Reason::NotProvided),
+    };
+    signature::check(signature, &func).map_err(Reason::Signature)?;
+    if !options.policy.allows(signature.group) {
+        return Err(Reason::Denied(signature.group));
+    }
+
+    let hostfunc = sym_func(name).ok_or(Reason::NotProvided)?;
+    Ok(ExternVal::Func(alloc_func(store, &func, hostfunc)))
+}
+
+// Large dependency graphs often link in functions the macro never calls on the
+// path it takes. Those may keep their imports as long as they are not called.
+fn stub(module: &str, name: &str) -> HostFunc {
+    let msg = format!(
+        "called {}::{}, which watt does not provide and only stubbed out",
+        module, name,
+    );
+    Box::new(move |_, _| Some(msg.clone()))
+}
+
+fn report_unsupported(unsupported: &[(&str, &str, Reason)]) -> String {
+    let mut report = "wasm module has imports which watt does not provide:".to_owned();
+    for (module, name, reason) in unsupported {
+        report += &format!("\n    {}::{}: {}", module, name, reason);
+    }
+
+    let any = |f: &dyn Fn(&str, &str) -> bool| {
+        unsupported.iter().any(|&(module, name, _)| f(module, name))
+    };
+    let wasm_bindgen = |module: &str, name: &str| {
+        module == "wbg"
+            || module.starts_with("__wbindgen")
+            || name.starts_with("__wbg")
+            || name.starts_with("__wbindgen")
+    };
+    if any(&wasm_bindgen) {
+        report += "\nwasm-bindgen detected: macros run in watt have no JavaScript host, so \
+                   wasm-bindgen and crates depending on it cannot be used";
+    }
+    if any(&|module, _| module.starts_with("wasi")) {
+        report += "\nWASI imports detected: build the macro for wasm32-unknown-unknown \
+                   rather than a WASI target";
+    }
+    let any_reason =
+        |f: &dyn Fn(&Reason) -> bool| unsupported.iter().any(|(_, _, reason)| f(reason));
+    if any_reason(&|reason| matches!(reason, Reason::NotProvided)) {
+        report += "\nto instantiate the module anyway, with functions watt does not provide \
+                   trapping only if called, use WasmMacro::stub_unresolved_imports";
+    }
+    if any_reason(&|reason| matches!(reason, Reason::Signature(_))) {
+        report += "\nmismatched signatures: the module may have been built against a \
+                   different version of the proc-macro2 shim than this version of watt";
+    }
+    report
+}
+
+// Host functions are looked up in the same table that imports are checked
+// against, so every function with a signature has an implementation in sym.
+macro_rules! sym_func {
+    ($($group:ident {
+        $(fn $name:ident($($arg:ident: $ty:ident),*) $(-> $ret:ident)?;)*
+    })*) => {
+        fn sym_func(name: &str) -> Option<HostFunc> {
+            match name {
+                $($(
+                    stringify!($name) => Some(host_func(name, sym::$name)),
+                )*)*
+                _ => None,
+            }
         }
     };
-    let hostfunc: HostFunc = match name {
-        "token_stream_new" => Box::new(sym::token_stream_new),
-        "token_stream_is_empty" => Box::new(sym::token_stream_is_empty),
-        "token_stream_from_str" => Box::new(sym::token_stream_from_str),
-        "token_stream_into_iter" => Box::new(sym::token_stream_into_iter),
-        "token_stream_iter_next" => Box::new(sym::token_stream_iter_next),
-        "token_stream_from_group" => Box::new(sym::token_stream_from_group),
-        "token_stream_from_ident" => Box::new(sym::token_stream_from_ident),
-        "token_stream_from_punct" => Box::new(sym::token_stream_from_punct),
-        "token_stream_from_literal" => Box::new(sym::token_stream_from_literal),
-        "token_stream_push_group" => Box::new(sym::token_stream_push_group),
-        "token_stream_push_ident" => Box::new(sym::token_stream_push_ident),
-        "token_stream_push_punct" => Box::new(sym::token_stream_push_punct),
-        "token_stream_push_literal" => Box::new(sym::token_stream_push_literal),
-        "token_stream_extend" => Box::new(sym::token_stream_extend),
-
-        "token_tree_kind" => Box::new(sym::token_tree_kind),
-        "token_tree_unwrap_group" => Box::new(sym::token_tree_unwrap_group),
-        "token_tree_unwrap_ident" => Box::new(sym::token_tree_unwrap_ident),
-        "token_tree_unwrap_punct" => Box::new(sym::token_tree_unwrap_punct),
-        "token_tree_unwrap_literal" => Box::new(sym::token_tree_unwrap_literal),
-
-        "span_call_site" => Box::new(sym::span_call_site),
-
-        "group_new" => Box::new(sym::group_new),
-        "group_delimiter" => Box::new(sym::group_delimiter),
-        "group_stream" => Box::new(sym::group_stream),
-        "group_span" => Box::new(sym::group_span),
-        "group_set_span" => Box::new(sym::group_set_span),
-
-        "punct_new" => Box::new(sym::punct_new),
-        "punct_as_char" => Box::new(sym::punct_as_char),
-        "punct_spacing" => Box::new(sym::punct_spacing),
-        "punct_span" => Box::new(sym::punct_span),
-        "punct_set_span" => Box::new(sym::punct_set_span),
-
-        "ident_new" => Box::new(sym::ident_new),
-        "ident_span" => Box::new(sym::ident_span),
-        "ident_set_span" => Box::new(sym::ident_set_span),
-        "ident_eq" => Box::new(sym::ident_eq),
-        "ident_eq_str" => Box::new(sym::ident_eq_str),
-        "ident_cmp" => Box::new(sym::ident_cmp),
-
-        "literal_u8_suffixed" => Box::new(sym::literal_u8_suffixed),
-        "literal_u16_suffixed" => Box::new(sym::literal_u16_suffixed),
-        "literal_u32_suffixed" => Box::new(sym::literal_u32_suffixed),
-        "literal_u64_suffixed" => Box::new(sym::literal_u64_suffixed),
-        "literal_u128_suffixed" => Box::new(sym::literal_u128_suffixed),
-        "literal_usize_suffixed" => Box::new(sym::literal_usize_suffixed),
-        "literal_i8_suffixed" => Box::new(sym::literal_i8_suffixed),
-        "literal_i16_suffixed" => Box::new(sym::literal_i16_suffixed),
-        "literal_i32_suffixed" => Box::new(sym::literal_i32_suffixed),
-        "literal_i64_suffixed" => Box::new(sym::literal_i64_suffixed),
-        "literal_i128_suffixed" => Box::new(sym::literal_i128_suffixed),
-        "literal_isize_suffixed" => Box::new(sym::literal_isize_suffixed),
-        "literal_u8_unsuffixed" => Box::new(sym::literal_u8_unsuffixed),
-        "literal_u16_unsuffixed" => Box::new(sym::literal_u16_unsuffixed),
-        "literal_u32_unsuffixed" => Box::new(sym::literal_u32_unsuffixed),
-        "literal_u64_unsuffixed" => Box::new(sym::literal_u64_unsuffixed),
-        "literal_u128_unsuffixed" => Box::new(sym::literal_u128_unsuffixed),
-        "literal_usize_unsuffixed" => Box::new(sym::literal_usize_unsuffixed),
-        "literal_i8_unsuffixed" => Box::new(sym::literal_i8_unsuffixed),
-        "literal_i16_unsuffixed" => Box::new(sym::literal_i16_unsuffixed),
-        "literal_i32_unsuffixed" => Box::new(sym::literal_i32_unsuffixed),
-        "literal_i64_unsuffixed" => Box::new(sym::literal_i64_unsuffixed),
-        "literal_i128_unsuffixed" => Box::new(sym::literal_i128_unsuffixed),
-        "literal_isize_unsuffixed" => Box::new(sym::literal_isize_unsuffixed),
-        "literal_f64_unsuffixed" => Box::new(sym::literal_f64_unsuffixed),
-        "literal_f64_suffixed" => Box::new(sym::literal_f64_suffixed),
-        "literal_f32_unsuffixed" => Box::new(sym::literal_f32_unsuffixed),
-        "literal_f32_suffixed" => Box::new(sym::literal_f32_suffixed),
-        "literal_string" => Box::new(sym::literal_string),
-        "literal_character" => Box::new(sym::literal_character),
-        "literal_byte_string" => Box::new(sym::literal_byte_string),
-        "literal_span" => Box::new(sym::literal_span),
-        "literal_set_span" => Box::new(sym::literal_set_span),
-
-        "token_stream_clone" => Box::new(sym::token_stream_clone),
-        "group_clone" => Box::new(sym::group_clone),
-        "ident_clone" => Box::new(sym::ident_clone),
-        "punct_clone" => Box::new(sym::punct_clone),
-        "literal_clone" => Box::new(sym::literal_clone),
-        "token_stream_iter_clone" => Box::new(sym::token_stream_iter_clone),
-
-        "token_stream_to_string" => Box::new(sym::token_stream_to_string),
-        "group_to_string" => Box::new(sym::group_to_string),
-        "ident_to_string" => Box::new(sym::ident_to_string),
-        "punct_to_string" => Box::new(sym::punct_to_string),
-        "literal_to_string" => Box::new(sym::literal_to_string),
-        "token_stream_debug" => Box::new(sym::token_stream_debug),
-        "group_debug" => Box::new(sym::group_debug),
-        "ident_debug" => Box::new(sym::ident_debug),
-        "punct_debug" => Box::new(sym::punct_debug),
-        "literal_debug" => Box::new(sym::literal_debug),
-        "span_debug" => Box::new(sym::span_debug),
-
-        "watt_string_with_capacity" => Box::new(sym::watt_string_with_capacity),
-        "watt_string_push_char" => Box::new(sym::watt_string_push_char),
-        "watt_string_len" => Box::new(sym::watt_string_len),
-        "watt_string_char_at" => Box::new(sym::watt_string_char_at),
-        "watt_bytes_with_capacity" => Box::new(sym::watt_bytes_with_capacity),
-        "watt_bytes_push" => Box::new(sym::watt_bytes_push),
-        "watt_print_panic" => Box::new(sym::watt_print_panic),
-
-        _ => unreachable!("unresolved import: {:?}", name),
+}
+
+watt_abi!(sym_func);
+
+// Most functions only work on their arguments. Those copying strings, bytes and
+// token trees from or into the memory of the module in bulk also take the
+// caller.
+trait SymFunc<Kind> {
+    fn call(&self, caller: &mut Caller, stack: &mut Vec<Value>) -> sym::Result;
+}
+
+struct Stack;
+struct Memory;
+
+impl<F: Fn(&mut Vec<Value>) -> sym::Result> SymFunc<Stack> for F {
+    fn call(&self, _caller: &mut Caller, stack: &mut Vec<Value>) -> sym::Result {
+        self(stack)
+    }
+}
+
+impl<F: Fn(&mut Caller, &mut Vec<Value>) -> sym::Result> SymFunc<Memory> for F {
+    fn call(&self, caller: &mut Caller, stack: &mut Vec<Value>) -> sym::Result {
+        self(caller, stack)
+    }
+}
+
+// Errors such as an invalid or wrong-kind handle trap, naming the function
+// which was passed it.
+fn host_func<Kind>(name: &str, f: impl SymFunc<Kind> + 'static) -> HostFunc {
+    let name = name.to_owned();
+    Box::new(move |caller, stack| {
+        f.call(caller, stack)
+            .err()
+            .map(|err| format!("{}: {}", name, err))
+    })
+}
+
+fn display_global(global: &types::Global) -> String {
+    let mutability = if global.mutable { "mut " } else { "" };
+    format!("{}{}", mutability, signature::display_type(&global.value))
+}
+
+// Globals such as a shared `__stack_pointer` or `__tls_base` are owned by the
+// host. They start out zeroed; the module is responsible for initializing any
+// mutable ones before use, the same way it would under a dynamic linker.
+fn host_global(store: &mut Store, global: &types::Global) -> ExternVal {
+    let value = match global.value {
+        types::Value::Int(Int::I32) => Value::I32(0),
+        types::Value::Int(Int::I64) => Value::I64(0),
+        types::Value::Float(Float::F32) => Value::F32(0.0),
+        types::Value::Float(Float::F64) => Value::F64(0.0),
+        types::Value::V128 => Value::V128(0),
+        types::Value::ExnRef => Value::ExnRef(None),
     };
-    ExternVal::Func(alloc_func(store, &func, hostfunc))
+    ExternVal::Global(alloc_global(store, global, value))
 }
@@ -103,6 +103,13 @@
 //! $ cargo build --release --target wasm32-unknown-unknown
 //! ```
 //!
+//! By default every token tree lives in the Watt runtime and the macro works
+//! on handles to them, one call into the runtime per operation. Macros that
+//! inspect their input closely can instead enable the `local-tokens` feature of
+//! the patched proc-macro2, which keeps token trees in the Wasm memory and
+//! hands the input and output across in one piece each. Nothing changes in the
+//! shim crate; Watt picks up the mode from the compiled binary.
+//!
 //! Next we need to make a small proc-macro shim crate to hand off the compiled
 //! Wasm bytes into the Watt runtime. In a new Cargo.toml, put:
 //!
@@ -150,10 +157,8 @@
 //!   when it would have been running debug-mode for a traditional proc macro.
 //!
 //!   There is a great deal of low-hanging fruit in the runtime; as I said, it
-//!   hasn't been optimized. In particular I am doing something silly with how
-//!   strings are handed off one character at a time instead of in bulk in
-//!   memory. I would love contributions from people who have a better idea of
-//!   how this stuff is intended to work in Wasm in general.
+//!   hasn't been optimized. I would love contributions from people who have a
+//!   better idea of how this stuff is intended to work in Wasm in general.
 //!
 //!   As another idea, maybe there could be some kind of `cargo install
 //!   watt-runtime` which installs an optimized Wasm runtime locally, which the
@@ -189,15 +194,24 @@ extern crate proc_macro;
 #[path = "../runtime/src/lib.rs"]
 mod watt;
 
+#[macro_use]
+mod abi;
 mod data;
 mod debug;
 mod exec;
 mod import;
+mod local;
+mod policy;
+mod signature;
 mod sym;
+mod xid;
 
+use crate::exec::{Options, Source};
 use crate::watt::*;
 use proc_macro::TokenStream;
 
+pub use crate::policy::{ImportGroup, ImportPolicy};
+
 /// A #\[proc_macro\] implemented in wasm!
 ///
 /// # Canonical macro implementation:
@@ -232,7 +246,7 @@ use proc_macro::TokenStream;
 /// # };
 /// ```
 pub fn proc_macro(fun: &str, input: TokenStream, wasm: &[u8]) -> TokenStream {
-    exec::proc_macro(fun, vec![input], wasm)
+    exec::proc_macro(fun, vec![input], Source::Wasm(wasm), &Options::default())
 }
 
 /// A #\[proc_macro_derive\] implemented in wasm!
@@ -269,7 +283,7 @@ pub fn proc_macro(fun: &str, input: TokenStream, wasm: &[u8]) -> TokenStream {
 /// # };
 /// ```
 pub fn proc_macro_derive(fun: &str, input: TokenStream, wasm: &[u8]) -> TokenStream {
-    exec::proc_macro(fun, vec![input], wasm)
+    exec::proc_macro(fun, vec![input], Source::Wasm(wasm), &Options::default())
 }
 
 /// A #\[proc_macro_attribute\] implemented in wasm!
@@ -311,5 +325,228 @@ pub fn proc_macro_attribute(
     input: TokenStream,
     wasm: &[u8],
 ) -> TokenStream {
-    exec::proc_macro(fun, vec![args, input], wasm)
+    exec::proc_macro(
+        fun,
+        vec![args, input],
+        Source::Wasm(wasm),
+        &Options::default(),
+    )
+}
+
+/// A #\[proc_macro\] implemented in wasm, loaded from the compact format.
+///
+/// The compact format is produced from the wasm binary once, when the shim is
+/// published, by the `watt-compact` tool:
+///
+/// ```console
+/// $ cargo run -p watt-compact -- my_macro.wasm my_macro.watt
+/// ```
+///
+/// The module is validated at that point, so that an invalid module is caught
+/// before the shim is published, and its function bodies are stored already
+/// lowered, so that they are not validated again each time the macro runs.
+/// Custom sections are stripped. The artifact carries a format version and a
+/// checksum; loading an artifact that is stale or corrupted panics rather than
+/// running it.
+///
+/// # Canonical entry point:
+///
+/// ```
+/// # const IGNORE: &str = stringify! {
+/// extern crate proc_macro;
+///
+/// use proc_macro::TokenStream;
+///
+/// static WATT: &[u8] = include_bytes!("my_macro.watt");
+///
+/// #[proc_macro]
+/// pub fn my_macro(input: TokenStream) -> TokenStream {
+///     watt::proc_macro_compact("my_macro", input, WATT)
+/// }
+/// # };
+/// ```
+pub fn proc_macro_compact(fun: &str, input: TokenStream, compact: &[u8]) -> TokenStream {
+    exec::proc_macro(
+        fun,
+        vec![input],
+        Source::Compact(compact),
+        &Options::default(),
+    )
+}
+
+/// A #\[proc_macro_derive\] implemented in wasm, loaded from the compact
+/// format. See [`proc_macro_compact`].
+///
+/// # Canonical entry point:
+///
+/// ```
+/// # const IGNORE: &str = stringify! {
+/// extern crate proc_macro;
+///
+/// use proc_macro::TokenStream;
+///
+/// static WATT: &[u8] = include_bytes!("my_macro.watt");
+///
+/// #[proc_macro_derive(MyDerive)]
+/// pub fn my_macro(input: TokenStream) -> TokenStream {
+///     watt::proc_macro_derive_compact("my_macro", input, WATT)
+/// }
+/// # };
+/// ```
+pub fn proc_macro_derive_compact(fun: &str, input: TokenStream, compact: &[u8]) -> TokenStream {
+    exec::proc_macro(
+        fun,
+        vec![input],
+        Source::Compact(compact),
+        &Options::default(),
+    )
+}
+
+/// A #\[proc_macro_attribute\] implemented in wasm, loaded from the compact
+/// format. See [`proc_macro_compact`].
+///
+/// # Canonical entry point:
+///
+/// ```
+/// # const IGNORE: &str = stringify! {
+/// extern crate proc_macro;
+///
+/// use proc_macro::TokenStream;
+///
+/// static WATT: &[u8] = include_bytes!("my_macro.watt");
+///
+/// #[proc_macro_attribute]
+/// pub fn my_macro(args: TokenStream, input: TokenStream) -> TokenStream {
+///     watt::proc_macro_attribute_compact("my_macro", args, input, WATT)
+/// }
+/// # };
+/// ```
+pub fn proc_macro_attribute_compact(
+    fun: &str,
+    args: TokenStream,
+    input: TokenStream,
+    compact: &[u8],
+) -> TokenStream {
+    exec::proc_macro(
+        fun,
+        vec![args, input],
+        Source::Compact(compact),
+        &Options::default(),
+    )
+}
+
+/// A macro implemented in wasm, with options for how it runs.
+///
+/// The free functions such as [`proc_macro`] run a macro with the default
+/// options. A shim which needs other options builds a `WasmMacro` in its entry
+/// point instead; options only apply to the invocations made through it.
+///
+/// # Canonical entry point:
+///
+/// ```
+/// # const IGNORE: &str = stringify! {
+/// extern crate proc_macro;
+///
+/// use proc_macro::TokenStream;
+/// use watt::WasmMacro;
+///
+/// static WASM: &[u8] = include_bytes!("my_macro.wasm");
+///
+/// #[proc_macro]
+/// pub fn my_macro(input: TokenStream) -> TokenStream {
+///     WasmMacro::new(WASM)
+///         .max_call_depth(100_000)
+///         .proc_macro("my_macro", input)
+/// }
+/// # };
+/// ```
+pub struct WasmMacro<'a> {
+    source: Source<'a>,
+    options: Options,
+}
+
+impl<'a> WasmMacro<'a> {
+    /// A macro whose module is a wasm binary.
+    pub fn new(wasm: &'a [u8]) -> Self {
+        WasmMacro {
+            source: Source::Wasm(wasm),
+            options: Options::default(),
+        }
+    }
+
+    /// A macro whose module is in the compact format. See
+    /// [`proc_macro_compact`].
+    pub fn compact(compact: &'a [u8]) -> Self {
+        WasmMacro {
+            source: Source::Compact(compact),
+            options: Options::default(),
+        }
+    }
+
+    /// Set the maximum number of nested wasm function calls, beyond which the
+    /// macro panics with a stack overflow. The default is 10,000.
+    ///
+    /// Wasm frames are kept on the heap, so the limit is not tied to the size
+    /// of the thread's stack. The `WATT_MAX_CALL_DEPTH` environment variable,
+    /// if set, takes precedence over this limit.
+    pub fn max_call_depth(mut self, depth: usize) -> Self {
+        self.options.max_call_depth = Some(depth);
+        self
+    }
+
+    /// Let the macro run even if its wasm imports functions which watt does
+    /// not provide. Each such import is replaced by a stub which traps with a
+    /// message naming it when called, so the macro works as long as it stays
+    /// on paths which do not call any of them.
+    ///
+    /// This is useful when a dependency links in code, such as bindings to a
+    /// JavaScript host, which the macro never actually runs. Imports which watt
+    /// provides with a different signature are still rejected.
+    pub fn stub_unresolved_imports(mut self, stub: bool) -> Self {
+        self.options.stub_unresolved = stub;
+        self
+    }
+
+    /// Restrict which groups of host functions the wasm of the macro may
+    /// import.
+    ///
+    /// Whatever the macro does, it can only reach the outside world through
+    /// the functions watt provides. A policy narrows that surface further, for
+    /// example to forbid parsing tokens from strings. A module importing
+    /// anything denied fails with a compile error listing the offending
+    /// imports. The `watt-audit` tool in the watt repository lists the imports
+    /// of a wasm file by group.
+    ///
+    /// ```
+    /// # const IGNORE: &str = stringify! {
+    /// WasmMacro::new(WASM)
+    ///     .import_policy(ImportPolicy::allow_all().deny(ImportGroup::Parse))
+    ///     .proc_macro("my_macro", input)
+    /// # };
+    /// ```
+    pub fn import_policy(mut self, policy: ImportPolicy) -> Self {
+        self.options.policy = policy;
+        self
+    }
+
+    /// Run the macro as a #\[proc_macro\]. See [`proc_macro`].
+    pub fn proc_macro(&self, fun: &str, input: TokenStream) -> TokenStream {
+        exec::proc_macro(fun, vec![input], self.source, &self.options)
+    }
+
+    /// Run the macro as a #\[proc_macro_derive\]. See [`proc_macro_derive`].
+    pub fn proc_macro_derive(&self, fun: &str, input: TokenStream) -> TokenStream {
+        exec::proc_macro(fun, vec![input], self.source, &self.options)
+    }
+
+    /// Run the macro as a #\[proc_macro_attribute\]. See
+    /// [`proc_macro_attribute`].
+    pub fn proc_macro_attribute(
+        &self,
+        fun: &str,
+        args: TokenStream,
+        input: TokenStream,
+    ) -> TokenStream {
+        exec::proc_macro(fun, vec![args, input], self.source, &self.options)
+    }
 }
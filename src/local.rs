@@ -0,0 +1,223 @@
+// Serialized form of token streams, for modules built with the local-tokens
+// feature of the proc-macro2 shim. Those keep token trees in their own memory,
+// so that their inputs and output cross the boundary once per invocation.
+//
+// Integers are u32 little-endian and strings are prefixed by their length:
+//
+//     stream  := len token*
+//     token   := TOKEN_GROUP span delimiter stream
+//              | TOKEN_IDENT span string
+//              | TOKEN_PUNCT span char spacing
+//              | TOKEN_LITERAL span string
+//
+// Spans stay on the host and are serialized as their handle. The call site is
+// always handle 0.
+
+use crate::data::{Collection, Handle};
+use crate::xid;
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use std::char;
+use std::convert::TryInto;
+use std::str::{self, FromStr};
+
+const TOKEN_GROUP: u32 = 0;
+const TOKEN_IDENT: u32 = 1;
+const TOKEN_PUNCT: u32 = 2;
+const TOKEN_LITERAL: u32 = 3;
+const DELIMITER_PARENTHESIS: u32 = 0;
+const DELIMITER_BRACE: u32 = 1;
+const DELIMITER_BRACKET: u32 = 2;
+const DELIMITER_NONE: u32 = 3;
+const SPACING_ALONE: u32 = 0;
+const SPACING_JOINT: u32 = 1;
+pub const PUNCT_CHARS: &str = "=<>!~+-*/%^&|@.,;:#$?'";
+
+// Fails if there are too many live spans to give each token a handle
+pub fn encode(stream: &TokenStream, spans: &mut Collection<Span>) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    encode_stream(stream, spans, &mut bytes)?;
+    Ok(bytes)
+}
+
+fn encode_stream(
+    stream: &TokenStream,
+    spans: &mut Collection<Span>,
+    bytes: &mut Vec<u8>,
+) -> Result<(), String> {
+    // The length is only known once the stream has been iterated
+    let len_pos = bytes.len();
+    bytes.extend_from_slice(&[0; 4]);
+    let mut len = 0u32;
+    for token in stream.clone() {
+        let span = spans.push(token.span())?;
+        match token {
+            TokenTree::Group(group) => {
+                push_u32(bytes, TOKEN_GROUP);
+                push_u32(bytes, span);
+                push_u32(
+                    bytes,
+                    match group.delimiter() {
+                        Delimiter::Parenthesis => DELIMITER_PARENTHESIS,
+                        Delimiter::Brace => DELIMITER_BRACE,
+                        Delimiter::Bracket => DELIMITER_BRACKET,
+                        Delimiter::None => DELIMITER_NONE,
+                    },
+                );
+                encode_stream(&group.stream(), spans, bytes)?;
+            }
+            TokenTree::Ident(ident) => {
+                push_u32(bytes, TOKEN_IDENT);
+                push_u32(bytes, span);
+                push_str(bytes, &ident.to_string());
+            }
+            TokenTree::Punct(punct) => {
+                push_u32(bytes, TOKEN_PUNCT);
+                push_u32(bytes, span);
+                push_u32(bytes, punct.as_char() as u32);
+                push_u32(
+                    bytes,
+                    match punct.spacing() {
+                        Spacing::Alone => SPACING_ALONE,
+                        Spacing::Joint => SPACING_JOINT,
+                    },
+                );
+            }
+            TokenTree::Literal(literal) => {
+                push_u32(bytes, TOKEN_LITERAL);
+                push_u32(bytes, span);
+                push_str(bytes, &literal.to_string());
+            }
+        }
+        len += 1;
+    }
+    bytes[len_pos..len_pos + 4].copy_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+fn push_u32(bytes: &mut Vec<u8>, n: u32) {
+    bytes.extend_from_slice(&n.to_le_bytes());
+}
+
+fn push_str(bytes: &mut Vec<u8>, string: &str) {
+    push_u32(bytes, string.len() as u32);
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+// Returns None if the bytes are not a well-formed token stream
+pub fn decode(bytes: &[u8], spans: &Collection<Span>) -> Option<TokenStream> {
+    let mut reader = Reader { bytes, spans };
+    let stream = reader.stream()?;
+    if reader.bytes.is_empty() {
+        Some(stream)
+    } else {
+        None
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    spans: &'a Collection<Span>,
+}
+
+impl<'a> Reader<'a> {
+    fn stream(&mut self) -> Option<TokenStream> {
+        let len = self.u32()?;
+        let mut tokens = Vec::new();
+        for _ in 0..len {
+            let kind = self.u32()?;
+            let span = self.span()?;
+            let mut token = match kind {
+                TOKEN_GROUP => {
+                    let delimiter = match self.u32()? {
+                        DELIMITER_PARENTHESIS => Delimiter::Parenthesis,
+                        DELIMITER_BRACE => Delimiter::Brace,
+                        DELIMITER_BRACKET => Delimiter::Bracket,
+                        DELIMITER_NONE => Delimiter::None,
+                        _ => return None,
+                    };
+                    TokenTree::Group(Group::new(delimiter, self.stream()?))
+                }
+                TOKEN_IDENT => TokenTree::Ident(ident(self.str()?, span)?),
+                TOKEN_PUNCT => {
+                    let op = char::from_u32(self.u32()?).filter(|&op| PUNCT_CHARS.contains(op))?;
+                    let spacing = match self.u32()? {
+                        SPACING_ALONE => Spacing::Alone,
+                        SPACING_JOINT => Spacing::Joint,
+                        _ => return None,
+                    };
+                    TokenTree::Punct(Punct::new(op, spacing))
+                }
+                TOKEN_LITERAL => TokenTree::Literal(Literal::from_str(self.str()?).ok()?),
+                _ => return None,
+            };
+            token.set_span(span);
+            tokens.push(token);
+        }
+        Some(tokens.into_iter().collect())
+    }
+
+    fn span(&mut self) -> Option<Span> {
+        let handle: Handle = self.u32()?;
+        self.spans.get(handle).ok().copied()
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.bytes(4)?;
+        Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn str(&mut self) -> Option<&'a str> {
+        let len = self.u32()?;
+        str::from_utf8(self.bytes(len as usize)?).ok()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(bytes)
+    }
+}
+
+// Returns None unless the string is an identifier, possibly raw, which the
+// compiler would accept rather than panic on
+pub fn ident(string: &str, span: Span) -> Option<Ident> {
+    match string.strip_prefix("r#") {
+        Some(raw) => match raw {
+            "_" | "crate" | "self" | "super" | "Self" => None,
+            raw if is_ident(raw) => Some(Ident::new_raw(raw, span)),
+            _ => None,
+        },
+        None if is_ident(string) => Some(Ident::new(string, span)),
+        None => None,
+    }
+}
+
+fn is_ident(string: &str) -> bool {
+    let mut chars = string.chars();
+    match chars.next() {
+        Some(first) if first == '_' || xid::is_xid_start(first) => {}
+        _ => return false,
+    }
+    chars.all(xid::is_xid_continue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xid_idents() {
+        assert!(is_ident("_"));
+        assert!(is_ident("\u{e9}t\u{e9}"));
+        assert!(!is_ident(""));
+        assert!(!is_ident("1x"));
+
+        // U+0345 is alphabetic, but only XID_Continue
+        assert!('\u{345}'.is_alphabetic());
+        assert!(is_ident("x\u{345}"));
+        assert!(!is_ident("\u{345}x"));
+    }
+}
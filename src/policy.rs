@@ -0,0 +1,85 @@
+/// A group of the functions which watt provides to the wasm of a macro.
+///
+/// See [`ImportPolicy`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImportGroup {
+    /// Building, inspecting and iterating token trees, spans, identifiers and
+    /// literals.
+    Tokens,
+    /// Parsing source text into tokens, as `TokenStream::from_str` does.
+    Parse,
+    /// Printing tokens and spans through their `Display` and `Debug` impls.
+    Format,
+    /// Moving strings and bytes in and out of the memory of the wasm module,
+    /// and reporting panics. Nearly every macro needs these, since identifiers
+    /// and literals are created from strings.
+    Transfer,
+    /// Functions only imported by modules built against earlier versions of
+    /// the proc-macro2 shim.
+    Legacy,
+}
+
+/// Which groups of functions the wasm of a macro may import.
+///
+/// A module importing a function from a denied group fails to instantiate,
+/// with a compile error listing the offending imports. Everything is allowed
+/// by default.
+///
+/// Only functions are covered. Globals and exception tags imported by the
+/// module are always provided, since they are plain values and tags owned by
+/// the host which give no access to anything outside the module.
+///
+/// ```
+/// use watt::{ImportGroup, ImportPolicy};
+///
+/// let policy = ImportPolicy::allow_all().deny(ImportGroup::Parse);
+/// assert!(!policy.allows(ImportGroup::Parse));
+/// assert!(policy.allows(ImportGroup::Tokens));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ImportPolicy {
+    allowed: u32,
+}
+
+impl ImportPolicy {
+    /// A policy allowing every group.
+    pub fn allow_all() -> Self {
+        ImportPolicy { allowed: !0 }
+    }
+
+    /// A policy allowing no group, to be extended with [`allow`].
+    ///
+    /// [`allow`]: ImportPolicy::allow
+    pub fn deny_all() -> Self {
+        ImportPolicy { allowed: 0 }
+    }
+
+    /// Allow the functions in `group`.
+    pub fn allow(self, group: ImportGroup) -> Self {
+        ImportPolicy {
+            allowed: self.allowed | bit(group),
+        }
+    }
+
+    /// Deny the functions in `group`.
+    pub fn deny(self, group: ImportGroup) -> Self {
+        ImportPolicy {
+            allowed: self.allowed & !bit(group),
+        }
+    }
+
+    /// Whether the functions in `group` may be imported.
+    pub fn allows(&self, group: ImportGroup) -> bool {
+        self.allowed & bit(group) != 0
+    }
+}
+
+impl Default for ImportPolicy {
+    fn default() -> Self {
+        ImportPolicy::allow_all()
+    }
+}
+
+fn bit(group: ImportGroup) -> u32 {
+    1 << group as u32
+}
@@ -0,0 +1,87 @@
+// Signatures of the functions a module may import, in terms of wasm types,
+// generated from the table shared with the proc-macro2 shim. This file is also
+// included by watt-audit, so that it classifies imports the way watt does.
+
+use crate::policy::ImportGroup;
+use crate::types::{self, Float, Int};
+
+pub struct Signature {
+    pub name: &'static str,
+    pub group: ImportGroup,
+    pub params: &'static [types::Value],
+    pub results: &'static [types::Value],
+}
+
+macro_rules! signatures {
+    ($($group:ident {
+        $(fn $name:ident($($arg:ident: $ty:ident),*) $(-> $ret:ident)?;)*
+    })*) => {
+        &[$($(
+            Signature {
+                name: stringify!($name),
+                group: ImportGroup::$group,
+                params: &[$(wasm_type!($ty)),*],
+                results: &[$(wasm_type!($ret))?],
+            },
+        )*)*]
+    };
+}
+
+macro_rules! wasm_type {
+    (u64) => {
+        types::Value::Int(Int::I64)
+    };
+    (i64) => {
+        types::Value::Int(Int::I64)
+    };
+    (f32) => {
+        types::Value::Float(Float::F32)
+    };
+    (f64) => {
+        types::Value::Float(Float::F64)
+    };
+    ($other:ident) => {
+        types::Value::Int(Int::I32)
+    };
+}
+
+pub const ABI: &[Signature] = watt_abi!(signatures);
+
+pub fn find(name: &str) -> Option<&'static Signature> {
+    ABI.iter().find(|signature| signature.name == name)
+}
+
+// A module declaring an import with the wrong signature would otherwise pass
+// the host function whatever arguments it likes
+pub fn check(signature: &Signature, func: &types::Func) -> Result<(), String> {
+    if func.args != signature.params || func.result != signature.results {
+        return Err(format!(
+            "declared as {} but watt provides {}",
+            display(&func.args, &func.result),
+            display(signature.params, signature.results),
+        ));
+    }
+    Ok(())
+}
+
+pub fn display(params: &[types::Value], results: &[types::Value]) -> String {
+    let types = |values: &[types::Value]| {
+        values
+            .iter()
+            .map(display_type)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!("fn({}) -> ({})", types(params), types(results))
+}
+
+pub fn display_type(value: &types::Value) -> &'static str {
+    match value {
+        types::Value::Int(Int::I32) => "i32",
+        types::Value::Int(Int::I64) => "i64",
+        types::Value::Float(Float::F32) => "f32",
+        types::Value::Float(Float::F64) => "f64",
+        types::Value::V128 => "v128",
+        types::Value::ExnRef => "exnref",
+    }
+}
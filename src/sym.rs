@@ -1,10 +1,11 @@
 use crate::data::Data;
-use crate::watt::Value;
-use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use crate::local;
+use crate::watt::{Caller, Value};
+use proc_macro::{Delimiter, Group, Literal, Punct, Spacing, TokenStream, TokenTree};
 use std::char;
 use std::cmp::Ordering;
 use std::iter::once;
-use std::str::FromStr;
+use std::str::{self, FromStr};
 
 const SENTINEL: u32 = u32::max_value();
 const TOKEN_GROUP: u32 = 0;
@@ -21,249 +22,306 @@ const ORDERING_LESS: u32 = 0;
 const ORDERING_EQUAL: u32 = 1;
 const ORDERING_GREATER: u32 = 2;
 
+pub type Result<T = ()> = std::result::Result<T, String>;
+
 // args: []
 // result: [Int(I32)]
-pub fn token_stream_new(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_new(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let stream = d.tokenstream.push(TokenStream::new());
+        let stream = d.tokenstream.push(TokenStream::new())?;
         stack.push(Value::I32(stream));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_is_empty(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_is_empty(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let stream = &d.tokenstream[pop(stack)];
+        let stream = d.tokenstream.get(pop(stack))?;
         let is_empty = stream.is_empty();
         stack.push(Value::I32(is_empty as u32));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_from_str(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_from_str(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let string = &d.string[pop(stack)];
+        let string = d.string.get(pop(stack))?;
         let result = TokenStream::from_str(string);
         stack.push(Value::I32(match result {
-            Ok(stream) => d.tokenstream.push(stream),
+            Ok(stream) => d.tokenstream.push(stream)?,
             Err(_error) => SENTINEL,
         }));
-        None
+        Ok(())
     })
 }
 
+// Parses a string into the serialized form of a token stream, for modules
+// keeping their token trees in their own memory
+//
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_into_iter(stack: &mut Vec<Value>) -> Option<String> {
+pub fn watt_token_stream_parse(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let stream = &d.tokenstream[pop(stack)];
+        let string = d.string.get(pop(stack))?;
+        let result = TokenStream::from_str(string);
+        stack.push(Value::I32(match result {
+            Ok(stream) => {
+                let bytes = local::encode(&stream, &mut d.span)?;
+                d.bytes.push(bytes)?
+            }
+            Err(_error) => SENTINEL,
+        }));
+        Ok(())
+    })
+}
+
+// args: [Int(I32)]
+// result: [Int(I32)]
+pub fn token_stream_into_iter(stack: &mut Vec<Value>) -> Result {
+    Data::with(|d| {
+        let stream = d.tokenstream.get(pop(stack))?;
         let iter = stream.clone().into_iter();
-        stack.push(Value::I32(d.intoiter.push(iter)));
-        None
+        stack.push(Value::I32(d.intoiter.push(iter)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_iter_next(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_iter_next(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let iter = &mut d.intoiter[pop(stack)];
+        let iter = d.intoiter.get_mut(pop(stack))?;
         stack.push(Value::I32(match iter.next() {
-            Some(token) => d.tokentree.push(token),
+            Some(token) => d.tokentree.push(token)?,
             None => SENTINEL,
         }));
-        None
+        Ok(())
+    })
+}
+
+// Writes the kind and handle of up to `cap` next token trees as pairs of u32
+// at `ptr`, returning how many were written. Zero means the end of the stream.
+//
+// args: [Int(I32), Int(I32), Int(I32)]
+// result: [Int(I32)]
+pub fn token_stream_iter_next_batch(caller: &mut Caller, stack: &mut Vec<Value>) -> Result {
+    let mem = match caller.memory() {
+        Some(mem) => mem,
+        None => return Err(NO_MEMORY.to_owned()),
+    };
+    Data::with(|d| {
+        let cap = pop(stack);
+        let ptr = pop(stack);
+        let iter = d.intoiter.get_mut(pop(stack))?;
+        let buf = match guest_bytes_mut(mem, ptr, cap.saturating_mul(8)) {
+            Some(buf) => buf,
+            None => return Err(OUT_OF_BOUNDS.to_owned()),
+        };
+        let mut len = 0;
+        for (entry, token) in buf.chunks_exact_mut(8).zip(iter) {
+            let (kind, handle) = match token {
+                TokenTree::Group(group) => (TOKEN_GROUP, d.group.push(group)?),
+                TokenTree::Ident(ident) => (TOKEN_IDENT, d.ident.push(ident)?),
+                TokenTree::Punct(punct) => (TOKEN_PUNCT, d.punct.push(punct)?),
+                TokenTree::Literal(literal) => (TOKEN_LITERAL, d.literal.push(literal)?),
+            };
+            entry[..4].copy_from_slice(&kind.to_le_bytes());
+            entry[4..].copy_from_slice(&handle.to_le_bytes());
+            len += 1;
+        }
+        stack.push(Value::I32(len));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_from_group(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_from_group(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let group = &d.group[pop(stack)];
+        let group = d.group.get(pop(stack))?;
         let tree = TokenTree::Group(group.clone());
-        stack.push(Value::I32(d.tokenstream.push(TokenStream::from(tree))));
-        None
+        stack.push(Value::I32(d.tokenstream.push(TokenStream::from(tree))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_from_ident(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_from_ident(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let ident = &d.ident[pop(stack)];
+        let ident = d.ident.get(pop(stack))?;
         let tree = TokenTree::Ident(ident.clone());
-        stack.push(Value::I32(d.tokenstream.push(TokenStream::from(tree))));
-        None
+        stack.push(Value::I32(d.tokenstream.push(TokenStream::from(tree))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_from_punct(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_from_punct(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let punct = &d.punct[pop(stack)];
+        let punct = d.punct.get(pop(stack))?;
         let tree = TokenTree::Punct(punct.clone());
-        stack.push(Value::I32(d.tokenstream.push(TokenStream::from(tree))));
-        None
+        stack.push(Value::I32(d.tokenstream.push(TokenStream::from(tree))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_from_literal(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_from_literal(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let literal = &d.literal[pop(stack)];
+        let literal = d.literal.get(pop(stack))?;
         let tree = TokenTree::Literal(literal.clone());
-        stack.push(Value::I32(d.tokenstream.push(TokenStream::from(tree))));
-        None
+        stack.push(Value::I32(d.tokenstream.push(TokenStream::from(tree))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn token_stream_push_group(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_push_group(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let group = &d.group[pop(stack)];
-        let stream = &mut d.tokenstream[pop(stack)];
+        let group = d.group.get(pop(stack))?;
+        let stream = d.tokenstream.get_mut(pop(stack))?;
         stream.extend(once(TokenTree::Group(group.clone())));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn token_stream_push_ident(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_push_ident(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let ident = &d.ident[pop(stack)];
-        let stream = &mut d.tokenstream[pop(stack)];
+        let ident = d.ident.get(pop(stack))?;
+        let stream = d.tokenstream.get_mut(pop(stack))?;
         stream.extend(once(TokenTree::Ident(ident.clone())));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn token_stream_push_punct(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_push_punct(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let punct = &d.punct[pop(stack)];
-        let stream = &mut d.tokenstream[pop(stack)];
+        let punct = d.punct.get(pop(stack))?;
+        let stream = d.tokenstream.get_mut(pop(stack))?;
         stream.extend(once(TokenTree::Punct(punct.clone())));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn token_stream_push_literal(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_push_literal(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let literal = &d.literal[pop(stack)];
-        let stream = &mut d.tokenstream[pop(stack)];
+        let literal = d.literal.get(pop(stack))?;
+        let stream = d.tokenstream.get_mut(pop(stack))?;
         stream.extend(once(TokenTree::Literal(literal.clone())));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn token_stream_extend(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_extend(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let next = d.tokenstream[pop(stack)].clone();
-        let stream = &mut d.tokenstream[pop(stack)];
+        let next = d.tokenstream.get(pop(stack))?.clone();
+        let stream = d.tokenstream.get_mut(pop(stack))?;
         stream.extend(next);
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_tree_kind(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_tree_kind(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let token = &d.tokentree[pop(stack)];
+        let token = d.tokentree.get(pop(stack))?;
         stack.push(Value::I32(match token {
             TokenTree::Group(_) => TOKEN_GROUP,
             TokenTree::Ident(_) => TOKEN_IDENT,
             TokenTree::Punct(_) => TOKEN_PUNCT,
             TokenTree::Literal(_) => TOKEN_LITERAL,
         }));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_tree_unwrap_group(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_tree_unwrap_group(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let token = &d.tokentree[pop(stack)];
-        let group = match token {
+        let handle = pop(stack);
+        let group = match d.tokentree.get(handle)? {
             TokenTree::Group(group) => group,
-            _ => unreachable!(),
+            _ => return Err(format!("token tree {:#x} is not a Group", handle)),
         };
-        stack.push(Value::I32(d.group.push(group.clone())));
-        None
+        stack.push(Value::I32(d.group.push(group.clone())?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_tree_unwrap_ident(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_tree_unwrap_ident(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let token = &d.tokentree[pop(stack)];
-        let ident = match token {
+        let handle = pop(stack);
+        let ident = match d.tokentree.get(handle)? {
             TokenTree::Ident(ident) => ident,
-            _ => unreachable!(),
+            _ => return Err(format!("token tree {:#x} is not an Ident", handle)),
         };
-        stack.push(Value::I32(d.ident.push(ident.clone())));
-        None
+        stack.push(Value::I32(d.ident.push(ident.clone())?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_tree_unwrap_punct(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_tree_unwrap_punct(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let token = &d.tokentree[pop(stack)];
-        let punct = match token {
+        let handle = pop(stack);
+        let punct = match d.tokentree.get(handle)? {
             TokenTree::Punct(punct) => punct,
-            _ => unreachable!(),
+            _ => return Err(format!("token tree {:#x} is not a Punct", handle)),
         };
-        stack.push(Value::I32(d.punct.push(punct.clone())));
-        None
+        stack.push(Value::I32(d.punct.push(punct.clone())?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_tree_unwrap_literal(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_tree_unwrap_literal(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let token = &d.tokentree[pop(stack)];
-        let literal = match token {
+        let handle = pop(stack);
+        let literal = match d.tokentree.get(handle)? {
             TokenTree::Literal(literal) => literal,
-            _ => unreachable!(),
+            _ => return Err(format!("token tree {:#x} is not a Literal", handle)),
         };
-        stack.push(Value::I32(d.literal.push(literal.clone())));
-        None
+        stack.push(Value::I32(d.literal.push(literal.clone())?));
+        Ok(())
     })
 }
 
 // args: []
 // result: [Int(I32)]
-pub fn span_call_site(stack: &mut Vec<Value>) -> Option<String> {
+pub fn span_call_site(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        stack.push(Value::I32(d.span.push(Span::call_site())));
-        None
+        stack.push(Value::I32(d.call_site()?));
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: [Int(I32)]
-pub fn group_new(stack: &mut Vec<Value>) -> Option<String> {
+pub fn group_new(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let stream = &d.tokenstream[pop(stack)];
+        let stream = d.tokenstream.get(pop(stack))?;
         let delimiter = pop(stack);
         let delimiter = if delimiter == DELIMITER_PARENTHESIS {
             Delimiter::Parenthesis
@@ -274,63 +332,63 @@ pub fn group_new(stack: &mut Vec<Value>) -> Option<String> {
         } else if delimiter == DELIMITER_NONE {
             Delimiter::None
         } else {
-            unreachable!()
+            return Err(format!("invalid delimiter {}", delimiter));
         };
         let group = Group::new(delimiter, stream.clone());
-        stack.push(Value::I32(d.group.push(group)));
-        None
+        stack.push(Value::I32(d.group.push(group)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn group_delimiter(stack: &mut Vec<Value>) -> Option<String> {
+pub fn group_delimiter(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let group = &d.group[pop(stack)];
+        let group = d.group.get(pop(stack))?;
         stack.push(Value::I32(match group.delimiter() {
             Delimiter::Parenthesis => DELIMITER_PARENTHESIS,
             Delimiter::Brace => DELIMITER_BRACE,
             Delimiter::Bracket => DELIMITER_BRACKET,
             Delimiter::None => DELIMITER_NONE,
         }));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn group_stream(stack: &mut Vec<Value>) -> Option<String> {
+pub fn group_stream(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let group = &d.group[pop(stack)];
-        stack.push(Value::I32(d.tokenstream.push(group.stream())));
-        None
+        let group = d.group.get(pop(stack))?;
+        stack.push(Value::I32(d.tokenstream.push(group.stream())?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn group_span(stack: &mut Vec<Value>) -> Option<String> {
+pub fn group_span(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let group = &d.group[pop(stack)];
-        stack.push(Value::I32(d.span.push(group.span())));
-        None
+        let group = d.group.get(pop(stack))?;
+        stack.push(Value::I32(d.span.push(group.span())?));
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn group_set_span(stack: &mut Vec<Value>) -> Option<String> {
+pub fn group_set_span(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let span = d.span[pop(stack)];
-        let group = &mut d.group[pop(stack)];
+        let span = *d.span.get(pop(stack))?;
+        let group = d.group.get_mut(pop(stack))?;
         group.set_span(span);
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: [Int(I32)]
-pub fn punct_new(stack: &mut Vec<Value>) -> Option<String> {
+pub fn punct_new(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let spacing = pop(stack);
         let op = pop(stack);
@@ -339,709 +397,892 @@ pub fn punct_new(stack: &mut Vec<Value>) -> Option<String> {
         } else if spacing == SPACING_JOINT {
             Spacing::Joint
         } else {
-            unreachable!()
+            return Err(format!("invalid spacing {}", spacing));
         };
-        let op = char::from_u32(op).unwrap();
-        stack.push(Value::I32(d.punct.push(Punct::new(op, spacing))));
-        None
+        let op = match char::from_u32(op) {
+            Some(op) if local::PUNCT_CHARS.contains(op) => op,
+            _ => return Err(format!("invalid punctuation character {:#x}", op)),
+        };
+        stack.push(Value::I32(d.punct.push(Punct::new(op, spacing))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn punct_as_char(stack: &mut Vec<Value>) -> Option<String> {
+pub fn punct_as_char(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let punct = &d.punct[pop(stack)];
+        let punct = d.punct.get(pop(stack))?;
         stack.push(Value::I32(punct.as_char() as u32));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn punct_spacing(stack: &mut Vec<Value>) -> Option<String> {
+pub fn punct_spacing(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let punct = &d.punct[pop(stack)];
+        let punct = d.punct.get(pop(stack))?;
         stack.push(Value::I32(match punct.spacing() {
             Spacing::Alone => SPACING_ALONE,
             Spacing::Joint => SPACING_JOINT,
         }));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn punct_span(stack: &mut Vec<Value>) -> Option<String> {
+pub fn punct_span(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let punct = &d.punct[pop(stack)];
-        stack.push(Value::I32(d.span.push(punct.span())));
-        None
+        let punct = d.punct.get(pop(stack))?;
+        stack.push(Value::I32(d.span.push(punct.span())?));
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn punct_set_span(stack: &mut Vec<Value>) -> Option<String> {
+pub fn punct_set_span(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let span = d.span[pop(stack)];
-        let punct = &mut d.punct[pop(stack)];
+        let span = *d.span.get(pop(stack))?;
+        let punct = d.punct.get_mut(pop(stack))?;
         punct.set_span(span);
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: [Int(I32)]
-pub fn ident_new(stack: &mut Vec<Value>) -> Option<String> {
+pub fn ident_new(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let span = d.span[pop(stack)];
-        let string = &d.string[pop(stack)];
-        stack.push(Value::I32(d.ident.push(Ident::new(string, span))));
-        None
+        let span = *d.span.get(pop(stack))?;
+        let string = d.string.get(pop(stack))?;
+        let ident = match local::ident(string, span) {
+            Some(ident) => ident,
+            None => return Err(format!("invalid identifier {:?}", string)),
+        };
+        stack.push(Value::I32(d.ident.push(ident)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn ident_span(stack: &mut Vec<Value>) -> Option<String> {
+pub fn ident_span(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let ident = &d.ident[pop(stack)];
-        stack.push(Value::I32(d.span.push(ident.span())));
-        None
+        let ident = d.ident.get(pop(stack))?;
+        stack.push(Value::I32(d.span.push(ident.span())?));
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn ident_set_span(stack: &mut Vec<Value>) -> Option<String> {
+pub fn ident_set_span(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let span = d.span[pop(stack)];
-        let ident = &mut d.ident[pop(stack)];
+        let span = *d.span.get(pop(stack))?;
+        let ident = d.ident.get_mut(pop(stack))?;
         ident.set_span(span);
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: [Int(I32)]
-pub fn ident_eq(stack: &mut Vec<Value>) -> Option<String> {
+pub fn ident_eq(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let other = &d.ident[pop(stack)];
-        let ident = &d.ident[pop(stack)];
+        let other = d.ident.get(pop(stack))?;
+        let ident = d.ident.get(pop(stack))?;
         let eq = ident.to_string() == other.to_string();
         stack.push(Value::I32(eq as u32));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: [Int(I32)]
-pub fn ident_eq_str(stack: &mut Vec<Value>) -> Option<String> {
+pub fn ident_eq_str(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let other = &d.string[pop(stack)];
-        let ident = &d.ident[pop(stack)];
+        let other = d.string.get(pop(stack))?;
+        let ident = d.ident.get(pop(stack))?;
         let eq = ident.to_string() == *other;
         stack.push(Value::I32(eq as u32));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: [Int(I32)]
-pub fn ident_cmp(stack: &mut Vec<Value>) -> Option<String> {
+pub fn ident_cmp(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let other = &d.ident[pop(stack)];
-        let ident = &d.ident[pop(stack)];
+        let other = d.ident.get(pop(stack))?;
+        let ident = d.ident.get(pop(stack))?;
         let cmp = match ident.to_string().cmp(&other.to_string()) {
             Ordering::Less => ORDERING_LESS,
             Ordering::Equal => ORDERING_EQUAL,
             Ordering::Greater => ORDERING_GREATER,
         };
         stack.push(Value::I32(cmp));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_u8_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_u8_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as u8;
-        stack.push(Value::I32(d.literal.push(Literal::u8_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::u8_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_u16_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_u16_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as u16;
-        stack.push(Value::I32(d.literal.push(Literal::u16_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::u16_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_u32_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_u32_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack);
-        stack.push(Value::I32(d.literal.push(Literal::u32_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::u32_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I64)]
 // result: [Int(I32)]
-pub fn literal_u64_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_u64_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop64(stack);
-        stack.push(Value::I32(d.literal.push(Literal::u64_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::u64_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I64), Int(I64)]
 // result: [Int(I32)]
-pub fn literal_u128_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_u128_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let hi = pop64(stack);
         let lo = pop64(stack);
         let n = ((hi as u128) << 64) + lo as u128;
-        stack.push(Value::I32(d.literal.push(Literal::u128_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::u128_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_usize_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_usize_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as usize;
-        stack.push(Value::I32(d.literal.push(Literal::usize_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::usize_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_i8_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_i8_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as i8;
-        stack.push(Value::I32(d.literal.push(Literal::i8_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::i8_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_i16_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_i16_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as i16;
-        stack.push(Value::I32(d.literal.push(Literal::i16_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::i16_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_i32_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_i32_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as i32;
-        stack.push(Value::I32(d.literal.push(Literal::i32_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::i32_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I64)]
 // result: [Int(I32)]
-pub fn literal_i64_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_i64_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop64(stack) as i64;
-        stack.push(Value::I32(d.literal.push(Literal::i64_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::i64_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I64), Int(I64)]
 // result: [Int(I32)]
-pub fn literal_i128_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_i128_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let hi = pop64(stack);
         let lo = pop64(stack);
         let n = (((hi as u128) << 64) + lo as u128) as i128;
-        stack.push(Value::I32(d.literal.push(Literal::i128_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::i128_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_isize_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_isize_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as isize;
-        stack.push(Value::I32(d.literal.push(Literal::isize_suffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::isize_suffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_u8_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_u8_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as u8;
-        stack.push(Value::I32(d.literal.push(Literal::u8_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::u8_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_u16_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_u16_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as u16;
-        stack.push(Value::I32(d.literal.push(Literal::u16_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::u16_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_u32_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_u32_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack);
-        stack.push(Value::I32(d.literal.push(Literal::u32_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::u32_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I64)]
 // result: [Int(I32)]
-pub fn literal_u64_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_u64_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop64(stack);
-        stack.push(Value::I32(d.literal.push(Literal::u64_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::u64_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I64), Int(I64)]
 // result: [Int(I32)]
-pub fn literal_u128_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_u128_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let hi = pop64(stack);
         let lo = pop64(stack);
         let n = ((hi as u128) << 64) + lo as u128;
-        stack.push(Value::I32(d.literal.push(Literal::u128_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::u128_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_usize_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_usize_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as usize;
-        stack.push(Value::I32(d.literal.push(Literal::usize_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::usize_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_i8_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_i8_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as i8;
-        stack.push(Value::I32(d.literal.push(Literal::i8_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::i8_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_i16_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_i16_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as i16;
-        stack.push(Value::I32(d.literal.push(Literal::i16_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::i16_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_i32_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_i32_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as i32;
-        stack.push(Value::I32(d.literal.push(Literal::i32_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::i32_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I64)]
 // result: [Int(I32)]
-pub fn literal_i64_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_i64_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop64(stack) as i64;
-        stack.push(Value::I32(d.literal.push(Literal::i64_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::i64_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I64), Int(I64)]
 // result: [Int(I32)]
-pub fn literal_i128_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_i128_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let hi = pop64(stack);
         let lo = pop64(stack);
         let n = (((hi as u128) << 64) + lo as u128) as i128;
-        stack.push(Value::I32(d.literal.push(Literal::i128_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::i128_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_isize_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_isize_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let n = pop(stack) as isize;
-        stack.push(Value::I32(d.literal.push(Literal::isize_unsuffixed(n))));
-        None
+        stack.push(Value::I32(d.literal.push(Literal::isize_unsuffixed(n))?));
+        Ok(())
     })
 }
 
 // args: [Int(F64)]
 // result: [Int(I32)]
-pub fn literal_f64_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_f64_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let f = popf64(stack);
-        stack.push(Value::I32(d.literal.push(Literal::f64_unsuffixed(f))));
-        None
+        let f = pop_finite64(stack)?;
+        stack.push(Value::I32(d.literal.push(Literal::f64_unsuffixed(f))?));
+        Ok(())
     })
 }
 
 // args: [Int(F64)]
 // result: [Int(I32)]
-pub fn literal_f64_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_f64_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let f = popf64(stack);
-        stack.push(Value::I32(d.literal.push(Literal::f64_suffixed(f))));
-        None
+        let f = pop_finite64(stack)?;
+        stack.push(Value::I32(d.literal.push(Literal::f64_suffixed(f))?));
+        Ok(())
     })
 }
 
 // args: [Int(F32)]
 // result: [Int(I32)]
-pub fn literal_f32_unsuffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_f32_unsuffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let f = popf(stack);
-        stack.push(Value::I32(d.literal.push(Literal::f32_unsuffixed(f))));
-        None
+        let f = pop_finite(stack)?;
+        stack.push(Value::I32(d.literal.push(Literal::f32_unsuffixed(f))?));
+        Ok(())
     })
 }
 
 // args: [Int(F32)]
 // result: [Int(I32)]
-pub fn literal_f32_suffixed(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_f32_suffixed(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let f = popf(stack);
-        stack.push(Value::I32(d.literal.push(Literal::f32_suffixed(f))));
-        None
+        let f = pop_finite(stack)?;
+        stack.push(Value::I32(d.literal.push(Literal::f32_suffixed(f))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_string(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_string(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let string = &d.string[pop(stack)];
-        stack.push(Value::I32(d.literal.push(Literal::string(string))));
-        None
+        let string = d.string.get(pop(stack))?;
+        stack.push(Value::I32(d.literal.push(Literal::string(string))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_character(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_character(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let ch = char::from_u32(pop(stack)).unwrap();
-        stack.push(Value::I32(d.literal.push(Literal::character(ch))));
-        None
+        let ch = pop_char(stack)?;
+        stack.push(Value::I32(d.literal.push(Literal::character(ch))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_byte_string(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_byte_string(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let bytes = &d.bytes[pop(stack)];
-        stack.push(Value::I32(d.literal.push(Literal::byte_string(bytes))));
-        None
+        let bytes = d.bytes.get(pop(stack))?;
+        stack.push(Value::I32(d.literal.push(Literal::byte_string(bytes))?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_span(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_span(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let literal = &d.literal[pop(stack)];
-        stack.push(Value::I32(d.span.push(literal.span())));
-        None
+        let literal = d.literal.get(pop(stack))?;
+        stack.push(Value::I32(d.span.push(literal.span())?));
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn literal_set_span(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_set_span(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let span = d.span[pop(stack)];
-        let literal = &mut d.literal[pop(stack)];
+        let span = *d.span.get(pop(stack))?;
+        let literal = d.literal.get_mut(pop(stack))?;
         literal.set_span(span);
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_clone(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_clone(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let clone = d.tokenstream[pop(stack)].clone();
-        stack.push(Value::I32(d.tokenstream.push(clone)));
-        None
+        let clone = d.tokenstream.get(pop(stack))?.clone();
+        stack.push(Value::I32(d.tokenstream.push(clone)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn group_clone(stack: &mut Vec<Value>) -> Option<String> {
+pub fn group_clone(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let clone = d.group[pop(stack)].clone();
-        stack.push(Value::I32(d.group.push(clone)));
-        None
+        let clone = d.group.get(pop(stack))?.clone();
+        stack.push(Value::I32(d.group.push(clone)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn ident_clone(stack: &mut Vec<Value>) -> Option<String> {
+pub fn ident_clone(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let clone = d.ident[pop(stack)].clone();
-        stack.push(Value::I32(d.ident.push(clone)));
-        None
+        let clone = d.ident.get(pop(stack))?.clone();
+        stack.push(Value::I32(d.ident.push(clone)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn punct_clone(stack: &mut Vec<Value>) -> Option<String> {
+pub fn punct_clone(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let clone = d.punct[pop(stack)].clone();
-        stack.push(Value::I32(d.punct.push(clone)));
-        None
+        let clone = d.punct.get(pop(stack))?.clone();
+        stack.push(Value::I32(d.punct.push(clone)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_clone(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_clone(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let clone = d.literal[pop(stack)].clone();
-        stack.push(Value::I32(d.literal.push(clone)));
-        None
+        let clone = d.literal.get(pop(stack))?.clone();
+        stack.push(Value::I32(d.literal.push(clone)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_iter_clone(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_iter_clone(stack: &mut Vec<Value>) -> Result {
+    Data::with(|d| {
+        let clone = d.intoiter.get(pop(stack))?.clone();
+        stack.push(Value::I32(d.intoiter.push(clone)?));
+        Ok(())
+    })
+}
+
+// args: [Int(I32)]
+// result: []
+pub fn token_stream_drop(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let clone = d.intoiter[pop(stack)].clone();
-        stack.push(Value::I32(d.intoiter.push(clone)));
-        None
+        d.tokenstream.remove(pop(stack))?;
+        Ok(())
+    })
+}
+
+// args: [Int(I32)]
+// result: []
+pub fn group_drop(stack: &mut Vec<Value>) -> Result {
+    Data::with(|d| {
+        d.group.remove(pop(stack))?;
+        Ok(())
+    })
+}
+
+// args: [Int(I32)]
+// result: []
+pub fn ident_drop(stack: &mut Vec<Value>) -> Result {
+    Data::with(|d| {
+        d.ident.remove(pop(stack))?;
+        Ok(())
+    })
+}
+
+// args: [Int(I32)]
+// result: []
+pub fn punct_drop(stack: &mut Vec<Value>) -> Result {
+    Data::with(|d| {
+        d.punct.remove(pop(stack))?;
+        Ok(())
+    })
+}
+
+// args: [Int(I32)]
+// result: []
+pub fn literal_drop(stack: &mut Vec<Value>) -> Result {
+    Data::with(|d| {
+        d.literal.remove(pop(stack))?;
+        Ok(())
+    })
+}
+
+// args: [Int(I32)]
+// result: []
+pub fn token_stream_iter_drop(stack: &mut Vec<Value>) -> Result {
+    Data::with(|d| {
+        d.intoiter.remove(pop(stack))?;
+        Ok(())
+    })
+}
+
+// args: [Int(I32)]
+// result: []
+pub fn watt_string_drop(stack: &mut Vec<Value>) -> Result {
+    Data::with(|d| {
+        d.string.remove(pop(stack))?;
+        Ok(())
+    })
+}
+
+// args: [Int(I32)]
+// result: []
+pub fn watt_bytes_drop(stack: &mut Vec<Value>) -> Result {
+    Data::with(|d| {
+        d.bytes.remove(pop(stack))?;
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_to_string(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_to_string(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let string = d.tokenstream[pop(stack)].to_string();
-        stack.push(Value::I32(d.string.push(string)));
-        None
+        let string = d.tokenstream.get(pop(stack))?.to_string();
+        stack.push(Value::I32(d.string.push(string)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn group_to_string(stack: &mut Vec<Value>) -> Option<String> {
+pub fn group_to_string(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let string = d.group[pop(stack)].to_string();
-        stack.push(Value::I32(d.string.push(string)));
-        None
+        let string = d.group.get(pop(stack))?.to_string();
+        stack.push(Value::I32(d.string.push(string)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn ident_to_string(stack: &mut Vec<Value>) -> Option<String> {
+pub fn ident_to_string(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let string = d.ident[pop(stack)].to_string();
-        stack.push(Value::I32(d.string.push(string)));
-        None
+        let string = d.ident.get(pop(stack))?.to_string();
+        stack.push(Value::I32(d.string.push(string)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn punct_to_string(stack: &mut Vec<Value>) -> Option<String> {
+pub fn punct_to_string(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let string = d.punct[pop(stack)].to_string();
-        stack.push(Value::I32(d.string.push(string)));
-        None
+        let string = d.punct.get(pop(stack))?.to_string();
+        stack.push(Value::I32(d.string.push(string)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_to_string(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_to_string(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let string = d.literal[pop(stack)].to_string();
-        stack.push(Value::I32(d.string.push(string)));
-        None
+        let string = d.literal.get(pop(stack))?.to_string();
+        stack.push(Value::I32(d.string.push(string)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn token_stream_debug(stack: &mut Vec<Value>) -> Option<String> {
+pub fn token_stream_debug(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let debug = format!("{:?}", d.tokenstream[pop(stack)]);
-        stack.push(Value::I32(d.string.push(debug)));
-        None
+        let debug = format!("{:?}", d.tokenstream.get(pop(stack))?);
+        stack.push(Value::I32(d.string.push(debug)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn group_debug(stack: &mut Vec<Value>) -> Option<String> {
+pub fn group_debug(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let debug = format!("{:?}", d.group[pop(stack)]);
-        stack.push(Value::I32(d.string.push(debug)));
-        None
+        let debug = format!("{:?}", d.group.get(pop(stack))?);
+        stack.push(Value::I32(d.string.push(debug)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn ident_debug(stack: &mut Vec<Value>) -> Option<String> {
+pub fn ident_debug(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let debug = format!("{:?}", d.ident[pop(stack)]);
-        stack.push(Value::I32(d.string.push(debug)));
-        None
+        let debug = format!("{:?}", d.ident.get(pop(stack))?);
+        stack.push(Value::I32(d.string.push(debug)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn punct_debug(stack: &mut Vec<Value>) -> Option<String> {
+pub fn punct_debug(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let debug = format!("{:?}", d.punct[pop(stack)]);
-        stack.push(Value::I32(d.string.push(debug)));
-        None
+        let debug = format!("{:?}", d.punct.get(pop(stack))?);
+        stack.push(Value::I32(d.string.push(debug)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn literal_debug(stack: &mut Vec<Value>) -> Option<String> {
+pub fn literal_debug(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let debug = format!("{:?}", d.literal[pop(stack)]);
-        stack.push(Value::I32(d.string.push(debug)));
-        None
+        let debug = format!("{:?}", d.literal.get(pop(stack))?);
+        stack.push(Value::I32(d.string.push(debug)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn span_debug(stack: &mut Vec<Value>) -> Option<String> {
+pub fn span_debug(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let debug = format!("{:?}", d.span[pop(stack)]);
-        stack.push(Value::I32(d.string.push(debug)));
-        None
+        let debug = format!("{:?}", *d.span.get(pop(stack))?);
+        stack.push(Value::I32(d.string.push(debug)?));
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn watt_string_with_capacity(stack: &mut Vec<Value>) -> Option<String> {
+pub fn watt_string_with_capacity(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let cap = pop(stack) as usize;
-        let string = d.string.push(String::with_capacity(cap));
+        let string = d.string.push(String::with_capacity(cap))?;
         stack.push(Value::I32(string));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn watt_string_push_char(stack: &mut Vec<Value>) -> Option<String> {
+pub fn watt_string_push_char(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let ch = pop(stack);
-        let string = &mut d.string[pop(stack)];
-        string.push(char::from_u32(ch).unwrap());
-        None
+        let ch = pop_char(stack)?;
+        let string = d.string.get_mut(pop(stack))?;
+        string.push(ch);
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn watt_string_len(stack: &mut Vec<Value>) -> Option<String> {
+pub fn watt_string_len(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
-        let string = &d.string[pop(stack)];
+        let string = d.string.get(pop(stack))?;
         stack.push(Value::I32(string.len() as u32));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: [Int(I32)]
-pub fn watt_string_char_at(stack: &mut Vec<Value>) -> Option<String> {
+pub fn watt_string_char_at(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let pos = pop(stack) as usize;
-        let string = &d.string[pop(stack)];
-        let ch = string[pos..].chars().next().unwrap() as u32;
-        stack.push(Value::I32(ch));
-        None
+        let string = d.string.get(pop(stack))?;
+        let ch = match string.get(pos..).and_then(|rest| rest.chars().next()) {
+            Some(ch) => ch,
+            None => return Err(format!("no char at position {} of string", pos)),
+        };
+        stack.push(Value::I32(ch as u32));
+        Ok(())
+    })
+}
+
+// args: [Int(I32), Int(I32)]
+// result: [Int(I32)]
+pub fn watt_string_new(caller: &mut Caller, stack: &mut Vec<Value>) -> Result {
+    let mem = match caller.memory() {
+        Some(mem) => mem,
+        None => return Err(NO_MEMORY.to_owned()),
+    };
+    Data::with(|d| {
+        let len = pop(stack);
+        let ptr = pop(stack);
+        let string = match guest_bytes(mem, ptr, len).map(str::from_utf8) {
+            Some(Ok(string)) => string.to_owned(),
+            Some(Err(_)) => return Err("string is not valid UTF-8".to_owned()),
+            None => return Err(OUT_OF_BOUNDS.to_owned()),
+        };
+        stack.push(Value::I32(d.string.push(string)?));
+        Ok(())
+    })
+}
+
+// args: [Int(I32), Int(I32)]
+// result: []
+pub fn watt_string_copy(caller: &mut Caller, stack: &mut Vec<Value>) -> Result {
+    let mem = match caller.memory() {
+        Some(mem) => mem,
+        None => return Err(NO_MEMORY.to_owned()),
+    };
+    Data::with(|d| {
+        let ptr = pop(stack);
+        let string = d.string.get(pop(stack))?;
+        match guest_bytes_mut(mem, ptr, string.len() as u32) {
+            Some(dst) => dst.copy_from_slice(string.as_bytes()),
+            None => return Err(OUT_OF_BOUNDS.to_owned()),
+        }
+        Ok(())
+    })
+}
+
+// args: [Int(I32), Int(I32)]
+// result: [Int(I32)]
+pub fn watt_bytes_new(caller: &mut Caller, stack: &mut Vec<Value>) -> Result {
+    let mem = match caller.memory() {
+        Some(mem) => mem,
+        None => return Err(NO_MEMORY.to_owned()),
+    };
+    Data::with(|d| {
+        let len = pop(stack);
+        let ptr = pop(stack);
+        let bytes = match guest_bytes(mem, ptr, len) {
+            Some(bytes) => bytes.to_vec(),
+            None => return Err(OUT_OF_BOUNDS.to_owned()),
+        };
+        stack.push(Value::I32(d.bytes.push(bytes)?));
+        Ok(())
+    })
+}
+
+// args: [Int(I32)]
+// result: [Int(I32)]
+pub fn watt_bytes_len(stack: &mut Vec<Value>) -> Result {
+    Data::with(|d| {
+        let bytes = d.bytes.get(pop(stack))?;
+        stack.push(Value::I32(bytes.len() as u32));
+        Ok(())
+    })
+}
+
+// args: [Int(I32), Int(I32)]
+// result: []
+pub fn watt_bytes_copy(caller: &mut Caller, stack: &mut Vec<Value>) -> Result {
+    let mem = match caller.memory() {
+        Some(mem) => mem,
+        None => return Err(NO_MEMORY.to_owned()),
+    };
+    Data::with(|d| {
+        let ptr = pop(stack);
+        let bytes = d.bytes.get(pop(stack))?;
+        match guest_bytes_mut(mem, ptr, bytes.len() as u32) {
+            Some(dst) => dst.copy_from_slice(bytes),
+            None => return Err(OUT_OF_BOUNDS.to_owned()),
+        }
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: [Int(I32)]
-pub fn watt_bytes_with_capacity(stack: &mut Vec<Value>) -> Option<String> {
+pub fn watt_bytes_with_capacity(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let cap = pop(stack) as usize;
-        let string = d.bytes.push(Vec::with_capacity(cap));
+        let string = d.bytes.push(Vec::with_capacity(cap))?;
         stack.push(Value::I32(string));
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32), Int(I32)]
 // result: []
-pub fn watt_bytes_push(stack: &mut Vec<Value>) -> Option<String> {
+pub fn watt_bytes_push(stack: &mut Vec<Value>) -> Result {
     Data::with(|d| {
         let b = pop(stack) as u8;
-        let bytes = &mut d.bytes[pop(stack)];
+        let bytes = d.bytes.get_mut(pop(stack))?;
         bytes.push(b);
-        None
+        Ok(())
     })
 }
 
 // args: [Int(I32)]
 // result: []
-pub fn watt_print_panic(stack: &mut Vec<Value>) -> Option<String> {
-    Data::with(|d| panic!("{}", d.string[pop(stack)]))
+pub fn watt_print_panic(stack: &mut Vec<Value>) -> Result {
+    // The module may still catch the unwinding panic, so only report it once
+    // the exception escapes the macro.
+    Data::with(|d| {
+        d.panic = Some(d.string.get(pop(stack))?.clone());
+        Ok(())
+    })
+}
+
+const OUT_OF_BOUNDS: &str = "out of bounds memory access";
+const NO_MEMORY: &str = "host function requires the memory of its caller";
+
+fn guest_bytes(mem: &[u8], ptr: u32, len: u32) -> Option<&[u8]> {
+    mem.get(ptr as usize..)?.get(..len as usize)
+}
+
+fn guest_bytes_mut(mem: &mut [u8], ptr: u32, len: u32) -> Option<&mut [u8]> {
+    mem.get_mut(ptr as usize..)?.get_mut(..len as usize)
 }
 
 fn pop(stack: &mut Vec<Value>) -> u32 {
@@ -1071,3 +1312,27 @@ fn popf64(stack: &mut Vec<Value>) -> f64 {
         _ => unreachable!("unexpected Value type on stack"),
     }
 }
+
+fn pop_char(stack: &mut Vec<Value>) -> Result<char> {
+    let ch = pop(stack);
+    char::from_u32(ch).ok_or_else(|| format!("invalid char {:#x}", ch))
+}
+
+// Float literals cannot be infinite or NaN
+fn pop_finite(stack: &mut Vec<Value>) -> Result<f32> {
+    let f = popf(stack);
+    if f.is_finite() {
+        Ok(f)
+    } else {
+        Err(format!("invalid float literal {}", f))
+    }
+}
+
+fn pop_finite64(stack: &mut Vec<Value>) -> Result<f64> {
+    let f = popf64(stack);
+    if f.is_finite() {
+        Ok(f)
+    } else {
+        Err(format!("invalid float literal {}", f))
+    }
+}